@@ -1,16 +1,42 @@
 use super::parse::CallDef;
+use proc_macro2::{Group, TokenStream, TokenTree};
 use quote::quote;
 
 /// See the `fn call` docs at the `lib.rs` of this crate for a high level definition.
 pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
-	let CallDef { pallet_struct, methods } = def;
+	let CallDef { pallet_struct, generics, methods, support } = def;
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+	// This is the pallet the calls are implemented for, such as `Pallet<T>`.
+	let pallet = quote!(#pallet_struct #ty_generics);
+	// These are the bounds of the `where` clause of the `impl`, which the functions of the calls
+	// need, so the impls which dispatch the calls have them too, along with their own bounds. They
+	// may name the pallet as `Self`, which is the `Call` in those impls, so it is replaced.
+	let predicates = where_clause.into_iter().flat_map(|where_clause| &where_clause.predicates);
+	let predicates = replace_self(quote!(#( #predicates, )*), &pallet);
+	// This is the generic type of the pallet which implements its `Config`, which is mostly `T`.
+	let config_type = &generics.type_params().next().expect("checked by the parser").ident;
+	// These are all the generic types, which the `Call` enum marks as used, since its calls need
+	// not take arguments of every generic type.
+	let type_params = generics.type_params().map(|param| &param.ident);
+	let marked = quote!(( #( #type_params, )* ));
+	// The instance of a pallet, the last generic type when it is named `I`, defaults to `()` in the
+	// `Call` enum, as it does in the pallet struct, so the calls of the default instance can be
+	// named without it.
+	let mut enum_generics = generics.clone();
+	if let Some(syn::GenericParam::Type(param)) = enum_generics.params.last_mut() {
+		if param.ident == "I" && param.default.is_none() {
+			param.eq_token = Some(Default::default());
+			param.default = Some(syn::parse_quote!(()));
+		}
+	}
 
 	// This is a vector of all the callable function names.
 	let fn_name = methods.iter().map(|method| &method.name).collect::<Vec<_>>();
 
 	// This is a nested vector of all the arguments for each of the functions in `fn_name`. It does
-	// not include the `self` or `caller: T::AccountId` parameter, which we always assume are the
-	// first two parameters to these calls.
+	// not include the `self` or runtime parameter, nor the `caller: T::AccountId` parameter, which
+	// we always assume are the first two parameters to these calls, except for inherents which have
+	// no caller.
 	let args_name = methods
 		.iter()
 		.map(|method| method.args.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>())
@@ -23,38 +49,305 @@ pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
 		.map(|method| method.args.iter().map(|(_, type_)| type_.clone()).collect::<Vec<_>>())
 		.collect::<Vec<_>>();
 
+	// This is a nested vector of the names the arguments are bound to when they are compared with
+	// the arguments of another call.
+	let other_args_name = args_name
+		.iter()
+		.map(|names| {
+			names
+				.iter()
+				.map(|name| quote::format_ident!("other_{}", name))
+				.collect::<Vec<_>>()
+		})
+		.collect::<Vec<_>>();
+
+	// This is a nested vector of the lines of the doc comment of each function, for the metadata.
+	let docs = methods.iter().map(|method| method.docs.clone()).collect::<Vec<_>>();
+
+	// This is the index of each function in the `Call` enum, which identifies the call when it is
+	// encoded, instead of the order in which the functions are declared.
+	let call_index = methods.iter().map(|method| method.index).collect::<Vec<_>>();
+
+	// The calls are encoded with serde, which is bounded on the types of the arguments like the
+	// other impls, rather than on the generic types. Serde takes these bounds, and its own path, as
+	// strings.
+	let serde = format!("{}::serde", quote!(#support));
+	let mut arg_types =
+		args_type.iter().flatten().map(|ty| quote!(#ty).to_string()).collect::<Vec<_>>();
+	arg_types.sort();
+	arg_types.dedup();
+	let serialize_bound = arg_types
+		.iter()
+		.map(|ty| format!("{}: {}::Serialize", ty, serde))
+		.collect::<Vec<_>>()
+		.join(", ");
+	let deserialize_bound = arg_types
+		.iter()
+		.map(|ty| format!("{}: {}::Deserialize<'de>", ty, serde))
+		.collect::<Vec<_>>()
+		.join(", ");
+
+	// The pallet level dispatch, which maps each of the items in the `Call` enum to the function
+	// of the pallet with all arguments, including the `caller`. Only pallets whose calls all take
+	// `&self` or `&mut self` and a caller can dispatch them without the runtime.
+	let pallet_dispatch = methods.iter().all(|method| !method.takes_runtime && !method.inherent);
+	let pallet_dispatch = pallet_dispatch.then(|| {
+		quote! {
+			impl #impl_generics #support::Dispatch for #pallet #where_clause {
+				type Caller = #config_type::AccountId;
+				type Call = Call #ty_generics;
+
+				fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> #support::DispatchResult {
+					match call {
+						#(
+							Call::#fn_name { #( #args_name ),* } => {
+								self.#fn_name(
+									// Note that we assume the first argument of every call is the `caller`.
+									caller,
+									#( #args_name ),*
+								)?;
+							},
+						)*
+						Call::__Ignore(_, never) => match never {},
+					}
+					Ok(())
+				}
+			}
+		}
+	});
+
+	// This is how each call is dispatched at the runtime level, by a signed extrinsic.
+	let dispatch_arm = methods.iter().zip(&args_name).map(|(method, args_name)| {
+		let fn_name = &method.name;
+		if method.inherent {
+			quote!(Call::#fn_name { .. } => Err("call can only be dispatched as an inherent".into()),)
+		} else if method.takes_runtime {
+			quote! {
+				Call::#fn_name { #( #args_name ),* } =>
+					<#pallet>::#fn_name(runtime, caller, #( #args_name ),*),
+			}
+		} else {
+			quote! {
+				Call::#fn_name { #( #args_name ),* } =>
+					#support::HasPallet::<#pallet>::pallet_mut(runtime)
+						.#fn_name(caller, #( #args_name ),*),
+			}
+		}
+	});
+
+	// This is how the inherents are dispatched, without a caller. Pallets without inherents keep
+	// the default of `Dispatchable`, which rejects every call.
+	let inherent_arm = methods.iter().zip(&args_name).filter(|(method, _)| method.inherent).map(
+		|(method, args_name)| {
+			let fn_name = &method.name;
+			if method.takes_runtime {
+				quote!(Call::#fn_name { #( #args_name ),* } => <#pallet>::#fn_name(runtime, #( #args_name ),*),)
+			} else {
+				quote! {
+					Call::#fn_name { #( #args_name ),* } =>
+						#support::HasPallet::<#pallet>::pallet_mut(runtime)
+							.#fn_name(#( #args_name ),*),
+				}
+			}
+		},
+	);
+	let inherent_arm = inherent_arm.collect::<Vec<_>>();
+	let dispatch_inherent = (!inherent_arm.is_empty()).then(|| {
+		quote! {
+			fn dispatch_inherent(self, runtime: &mut #config_type) -> #support::DispatchResult {
+				match self {
+					#( #inherent_arm )*
+					_ => Err("call is not an inherent".into()),
+				}
+			}
+		}
+	});
+
+	// This is the weight of each call. The expression of `#[call(weight = expr)]` can use the
+	// arguments of the call, but need not use all of them.
+	let weight_arm = methods.iter().zip(&args_name).map(|(method, args_name)| {
+		let fn_name = &method.name;
+		match &method.weight {
+			Some(weight) => quote! {
+				#[allow(unused_variables)]
+				Call::#fn_name { #( ref #args_name ),* } => #weight,
+			},
+			None => quote!(Call::#fn_name { .. } => #support::DEFAULT_CALL_WEIGHT,),
+		}
+	});
+
 	// This quote block creates an `enum Call` which contains all the calls exposed by our pallet,
 	// and the `Dispatch` trait logic to route a `caller` to access those functions.
 	let dispatch_impl = quote! {
-		// The callable functions exposed by this pallet.
+		/// The callable functions exposed by this pallet.
 		//
 		// The parsed function names will be `snake_case`, and that will show up in the enum.
 		#[allow(non_camel_case_types)]
-		pub enum Call<T: Config> {
+		#[derive(#support::serde::Serialize, #support::serde::Deserialize)]
+		#[serde(
+			crate = #serde,
+			bound(serialize = #serialize_bound, deserialize = #deserialize_bound),
+		)]
+		pub enum Call #enum_generics {
 			#(
+				#( #[doc = #docs] )*
 				#fn_name { #( #args_name: #args_type),* },
 			)*
+			// This variant can never be constructed, it only marks the generic types as used.
+			#[doc(hidden)]
+			#[serde(skip)]
+			__Ignore(core::marker::PhantomData<#marked>, core::convert::Infallible),
+		}
+
+		#pallet_dispatch
+
+		// Runtime level dispatch, which calls the function of each call with the pallet stored in
+		// the runtime, or with the runtime itself for the functions which take it. Inherents
+		// cannot be dispatched by a signed extrinsic, and are only dispatched as inherents.
+		impl #impl_generics #support::Dispatchable<#config_type> for Call #ty_generics
+		where
+			#predicates
+			#config_type: #support::HasPallet<#pallet>,
+		{
+			type Caller = #config_type::AccountId;
+
+			fn dispatch(self, runtime: &mut #config_type, caller: Self::Caller) -> #support::DispatchResult {
+				match self {
+					#( #dispatch_arm )*
+					Call::__Ignore(_, never) => match never {},
+				}
+			}
+
+			#dispatch_inherent
+		}
+
+		// Calls are cloned, for example by block builders which keep each extrinsic they apply.
+		// Like `Hash` below, the bounds are on the argument types.
+		impl #impl_generics Clone for Call #ty_generics
+		where
+			#( #( #args_type: Clone, )* )*
+		{
+			fn clone(&self) -> Self {
+				match *self {
+					#(
+						Call::#fn_name { #( ref #args_name ),* } => Call::#fn_name {
+							#( #args_name: #args_name.clone() ),*
+						},
+					)*
+					Call::__Ignore(_, never) => match never {},
+				}
+			}
+		}
+
+		// Calls are compared, printed and displayed, for example in the tests of a runtime. Like
+		// `Clone`, the bounds are on the argument types.
+		impl #impl_generics core::fmt::Debug for Call #ty_generics
+		where
+			#( #( #args_type: core::fmt::Debug, )* )*
+		{
+			fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+				match *self {
+					#(
+						Call::#fn_name { #( ref #args_name ),* } => f
+							.debug_struct(stringify!(#fn_name))
+							#( .field(stringify!(#args_name), #args_name) )*
+							.finish(),
+					)*
+					Call::__Ignore(_, never) => match never {},
+				}
+			}
+		}
+
+		impl #impl_generics PartialEq for Call #ty_generics
+		where
+			#( #( #args_type: PartialEq, )* )*
+		{
+			fn eq(&self, other: &Self) -> bool {
+				match (self, other) {
+					#(
+						(
+							Call::#fn_name { #( #args_name ),* },
+							Call::#fn_name { #( #args_name: #other_args_name ),* },
+						) => true #( && #args_name == #other_args_name )*,
+					)*
+					(Call::__Ignore(_, never), _) => match *never {},
+					_ => false,
+				}
+			}
+		}
+
+		impl #impl_generics Eq for Call #ty_generics
+		where
+			#( #( #args_type: Eq, )* )*
+		{
 		}
 
-		// Dispatch logic at the pallet level, mapping each of the items in the `Call` enum to the
-		// appropriate function call with all arguments, including the `caller`.
-		impl<T: Config> crate::support::Dispatch for #pallet_struct<T> {
-			type Caller = T::AccountId;
-			type Call = Call<T>;
+		// A call is displayed as `name(arg: value, ..)`.
+		impl #impl_generics core::fmt::Display for Call #ty_generics
+		where
+			#( #( #args_type: core::fmt::Debug, )* )*
+		{
+			fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+				match *self {
+					#(
+						Call::#fn_name { #( ref #args_name ),* } => #support::fmt_call(
+							f,
+							stringify!(#fn_name),
+							&[ #( (stringify!(#args_name), #args_name) ),* ],
+						),
+					)*
+					Call::__Ignore(_, never) => match never {},
+				}
+			}
+		}
+
+		// Describe the calls, with the argument types resolved for the runtime `T`, for the metadata
+		// of the runtime.
+		impl #impl_generics #support::GetCallMetadata for Call #ty_generics {
+			fn call_metadata() -> Vec<#support::VariantMetadata> {
+				vec![
+					#(
+						#support::VariantMetadata {
+							name: stringify!(#fn_name),
+							index: #call_index,
+							fields: vec![
+								#( #support::FieldMetadata::new::<#args_type>(stringify!(#args_name), &[]) ),*
+							],
+							docs: vec![ #( #docs ),* ],
+						},
+					)*
+				]
+			}
+		}
 
-			fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> crate::support::DispatchResult {
-				match call {
+		// Calls have the default weight, unless they are given their own with
+		// `#[call(weight = expr)]`.
+		impl #impl_generics #support::GetWeight for Call #ty_generics {
+			fn weight(&self) -> #support::Weight {
+				match *self {
+					#( #weight_arm )*
+					Call::__Ignore(_, never) => match never {},
+				}
+			}
+		}
+
+		// Calls are hashed to identify them, for example by pallets which approve a call before it
+		// is dispatched. Each call is identified by its call index. The bounds are on the argument
+		// types, so that `T` itself need not be hashable.
+		impl #impl_generics core::hash::Hash for Call #ty_generics
+		where
+			#( #( #args_type: core::hash::Hash, )* )*
+		{
+			fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+				match *self {
 					#(
-						Call::#fn_name { #( #args_name ),* } => {
-							self.#fn_name(
-								// Note that we assume the first argument of every call is the `caller`.
-								caller,
-								#( #args_name ),*
-							)?;
+						Call::#fn_name { #( ref #args_name ),* } => {
+							#call_index.hash(state);
+							#( #args_name.hash(state); )*
 						},
 					)*
+					Call::__Ignore(_, never) => match never {},
 				}
-				Ok(())
 			}
 		}
 	};
@@ -62,3 +355,17 @@ pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
 	// Return the generated code.
 	dispatch_impl.into()
 }
+
+/// Replace `Self` in `tokens` with the type `ty`.
+fn replace_self(tokens: TokenStream, ty: &TokenStream) -> TokenStream {
+	let replace = |token| match token {
+		TokenTree::Ident(ident) if ident == "Self" => ty.clone(),
+		TokenTree::Group(group) => {
+			let mut replaced = Group::new(group.delimiter(), replace_self(group.stream(), ty));
+			replaced.set_span(group.span());
+			TokenTree::Group(replaced).into()
+		},
+		token => token.into(),
+	};
+	tokens.into_iter().map(replace).collect()
+}
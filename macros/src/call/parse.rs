@@ -1,12 +1,5 @@
-use quote::ToTokens;
 use syn::spanned::Spanned;
 
-// Custom keywords we match to when parsing the calls in a pallet.
-mod keyword {
-	syn::custom_keyword!(T);
-	syn::custom_keyword!(AccountId);
-}
-
 /// This object will collect all the information we need to keep while parsing the callable
 /// functions.
 #[derive(Debug)]
@@ -14,8 +7,13 @@ pub struct CallDef {
 	/// This is the name of the pallet struct where the callable functions are implemented. We
 	/// mostly assume it is `Pallet`.
 	pub pallet_struct: syn::Ident,
+	/// These are the generics of the `impl` block, which the `Call` enum is generic over too. The
+	/// first type parameter is the type implementing the `Config` of the pallet, mostly `T`.
+	pub generics: syn::Generics,
 	/// This is a list of the callable functions exposed by this pallet. See `CallVariantDef`.
 	pub methods: Vec<CallVariantDef>,
+	/// The path of the module with the support types and traits, from the `support` argument.
+	pub support: syn::Path,
 }
 
 /// This is the metadata we keep about each callable function in our pallet.
@@ -23,119 +21,285 @@ pub struct CallDef {
 pub struct CallVariantDef {
 	/// The function name.
 	pub name: syn::Ident,
-	/// Information on args of the function: `(name, type)`.
+	/// The index of the call in the `Call` enum, from `#[call_index(n)]`, or else the position of
+	/// the function in the `impl` block, not counting the functions marked with `#[call(skip)]`.
+	pub index: u8,
+	/// Information on args of the function: `(name, type)`. Unused arguments, such as `_amount`,
+	/// are named without the underscore, and arguments which are not a plain identifier, such as
+	/// `_`, are named after their position, as `arg0`, `arg1` and so on.
 	pub args: Vec<(syn::Ident, Box<syn::Type>)>,
+	/// The lines of the doc comment of the function, for the metadata and the `Call` enum.
+	pub docs: Vec<String>,
+	/// Whether the function takes the whole runtime, such as `runtime: &mut T`, instead of `&self`
+	/// or `&mut self`, for calls which use other pallets.
+	pub takes_runtime: bool,
+	/// Whether the call is an inherent, from `#[call(inherent)]`. Inherents have no caller, and
+	/// are only dispatched as inherents, never by a signed extrinsic.
+	pub inherent: bool,
+	/// The weight of the call, from `#[call(weight = expr)]`, or else the default weight. The
+	/// expression can use the arguments of the call, which are references.
+	pub weight: Option<syn::Expr>,
+}
+
+/// The options of a function given with `#[call(..)]`.
+#[derive(Default)]
+struct CallOptions {
+	/// `#[call(skip)]` keeps a helper function out of the calls.
+	skip: bool,
+	/// `#[call(inherent)]` makes the call an inherent.
+	inherent: bool,
+	/// `#[call(weight = expr)]` gives the call its own weight.
+	weight: Option<syn::Expr>,
 }
 
 impl CallDef {
-	pub fn try_from(item: syn::Item) -> syn::Result<Self> {
+	pub fn try_from(item: syn::Item, support: syn::Path) -> syn::Result<Self> {
 		// First we check that we are parsing an `impl`.
 		let item_impl = if let syn::Item::Impl(item) = item {
 			item
 		} else {
 			return Err(syn::Error::new(item.span(), "Invalid pallet::call, expected item impl"))
 		};
+		if let Some((_, path, _)) = &item_impl.trait_ {
+			let msg = "Invalid pallet::call, expected an inherent impl, not a trait impl";
+			return Err(syn::Error::new(path.span(), msg))
+		}
 
 		// Extract the name of the struct. We mostly assume it is `Pallet`, but we can handle it
 		// when it isn't.
 		let pallet_struct = match &*item_impl.self_ty {
-			syn::Type::Path(tp) => tp.path.segments.first().unwrap().ident.clone(),
-			_ => panic!("not supported tokens"),
+			syn::Type::Path(tp) if tp.qself.is_none() => tp.path.segments.last().map(|s| &s.ident),
+			_ => None,
 		};
+		let pallet_struct = pallet_struct.cloned().ok_or_else(|| {
+			let msg = "Invalid pallet::call, expected the pallet struct, such as `Pallet<T>`";
+			syn::Error::new(item_impl.self_ty.span(), msg)
+		})?;
+
+		// The `Call` enum uses the generics of the `impl`, whose first type parameter implements
+		// the `Config` of the pallet.
+		let generics = item_impl.generics.clone();
+		if generics.type_params().next().is_none() {
+			let msg = "Invalid pallet::call, expected a generic type implementing `Config`, such \
+				as `impl<T: Config>`";
+			return Err(syn::Error::new(item_impl.impl_token.span(), msg))
+		}
+
+		// Functions which take the whole runtime take it as this type, such as `runtime: &mut T`.
+		let config_type = &generics.type_params().next().expect("checked above").ident;
 
 		// Here is where we will store all the callable functions.
-		let mut methods = vec![];
+		let mut methods: Vec<CallVariantDef> = vec![];
 		for item in item_impl.items {
-			if let syn::ImplItem::Fn(method) = item {
-				// Here is where we will store all the args for each callable functions.
-				let mut args = vec![];
+			let method = match item {
+				syn::ImplItem::Fn(method) => method,
+				_ => continue,
+			};
+			// Helper functions marked with `#[call(skip)]` are not calls.
+			let options = get_options(&method.attrs)?;
+			if options.skip {
+				continue
+			}
 
-				// First argument should be some variant of `self`.
-				match method.sig.inputs.first() {
-					Some(syn::FnArg::Receiver(_)) => {},
+			// Two calls with the same index would be encoded the same way.
+			let index = match get_index(&method.attrs, "call_index")? {
+				Some((index, attr)) => {
+					if methods.iter().any(|method| method.index == index) {
+						let msg = format!("Invalid call, duplicate call index {}", index);
+						return Err(syn::Error::new_spanned(attr, msg))
+					}
+					index
+				},
+				None => match u8::try_from(methods.len()) {
+					Ok(index) if !methods.iter().any(|method| method.index == index) => index,
 					_ => {
-						let msg = "Invalid call, first argument must be a variant of self";
+						let msg = "Invalid call, the implicit call index is already used, add an \
+							explicit `#[call_index(n)]`";
 						return Err(syn::Error::new(method.sig.span(), msg))
 					},
-				}
-
-				// The second argument should be the `caller: T::AccountId` argument.
-				match method.sig.inputs.iter().skip(1).next() {
-					Some(syn::FnArg::Typed(arg)) => {
-						// Here we specifically check that this argument is as we expect for
-						// `caller: T::AccountId`.
-						check_caller_arg(arg)?;
+				},
+			};
+
+			// The arguments of a call are kept in the `Call` enum, which cannot be generic over
+			// the generics of a single function.
+			if let Some(param) = method.sig.generics.params.first() {
+				let msg = "Invalid call, a call cannot have generic parameters, use the generics \
+					of the `impl` instead";
+				return Err(syn::Error::new(param.span(), msg))
+			}
+
+			// First argument should be `&self` or `&mut self`, since calls are dispatched on the
+			// pallet stored in the runtime, or else the runtime itself, such as `runtime: &mut T`,
+			// for calls which use other pallets too.
+			let msg = "Invalid call, first argument must be `&self`, `&mut self` or the runtime, \
+				such as `runtime: &mut T`";
+			let mut inputs = method.sig.inputs.iter();
+			let takes_runtime = match inputs.next() {
+				Some(syn::FnArg::Receiver(receiver))
+					if matches!(*receiver.ty, syn::Type::Reference(_)) =>
+					false,
+				Some(syn::FnArg::Typed(arg)) if is_runtime(&arg.ty, config_type) => true,
+				Some(arg) => return Err(syn::Error::new(arg.span(), msg)),
+				None => return Err(syn::Error::new(method.sig.ident.span(), msg)),
+			};
+
+			// The second argument is the caller, such as `caller: T::AccountId`. Its type is
+			// checked by the compiler where the call is dispatched. Inherents are not made by
+			// anyone, so they have no caller.
+			if !options.inherent && inputs.next().is_none() {
+				let msg = "Invalid call, second argument must be the caller, such as \
+					`caller: T::AccountId`";
+				return Err(syn::Error::new(method.sig.ident.span(), msg))
+			}
+
+			// Here is where we will store all the args for each callable functions.
+			let mut args = vec![];
+			for (position, arg) in inputs.enumerate() {
+				// Only the first argument can be `self`, so the rest are typed.
+				let arg = match arg {
+					syn::FnArg::Typed(arg) => arg,
+					syn::FnArg::Receiver(receiver) => {
+						let msg = "Invalid call, only the first argument can be `self`";
+						return Err(syn::Error::new(receiver.span(), msg))
 					},
-					_ => {
-						let msg = "Invalid call, second argument should be `caller: T::AccountId`";
-						return Err(syn::Error::new(method.sig.span(), msg))
+				};
+
+				// Extract the name of the argument, without the underscore of an unused argument,
+				// or name it after its position.
+				let arg_ident = match &*arg.pat {
+					syn::Pat::Ident(pat) => match pat.ident.to_string().strip_prefix('_') {
+						Some(name) if !name.is_empty() => syn::Ident::new(name, pat.ident.span()),
+						_ => pat.ident.clone(),
 					},
-				}
-
-				let fn_name = method.sig.ident.clone();
-
-				// Parsing the rest of the args. Skipping 2 for `self` and `caller`.
-				for arg in method.sig.inputs.iter().skip(2) {
-					// All arguments should be typed.
-					let arg = if let syn::FnArg::Typed(arg) = arg {
-						arg
-					} else {
-						unreachable!("All args should be typed.");
-					};
-
-					// Extract the name of the argument.
-					let arg_ident = if let syn::Pat::Ident(pat) = &*arg.pat {
-						pat.ident.clone()
-					} else {
-						let msg = "Invalid pallet::call, argument must be ident";
-						return Err(syn::Error::new(arg.pat.span(), msg))
-					};
-
-					// Store the argument name and the argument type for generating code.
-					args.push((arg_ident, arg.ty.clone()));
-				}
-
-				// Store all the function name and the arg data for the function.
-				methods.push(CallVariantDef { name: fn_name, args });
+					pat => quote::format_ident!("arg{}", position, span = pat.span()),
+				};
+
+				// Store the argument name and the argument type for generating code.
+				args.push((arg_ident, arg.ty.clone()));
 			}
+
+			// Store all the function name, the arg data and the docs for the function.
+			let docs = get_docs(&method.attrs);
+			methods.push(CallVariantDef {
+				name: method.sig.ident,
+				index,
+				args,
+				docs,
+				takes_runtime,
+				inherent: options.inherent,
+				weight: options.weight,
+			});
 		}
 
 		// Return all callable functions for this pallet.
-		Ok(Self { pallet_struct, methods })
+		Ok(Self { pallet_struct, generics, methods, support })
+	}
+}
+
+/// Get the options of a function, given with `#[call(skip)]`, `#[call(inherent)]` and
+/// `#[call(weight = expr)]`, or several at once, such as `#[call(inherent, weight = 0)]`.
+fn get_options(attrs: &[syn::Attribute]) -> syn::Result<CallOptions> {
+	let mut options = CallOptions::default();
+	for attr in attrs.iter().filter(|attr| attr.path().is_ident("call")) {
+		attr.parse_nested_meta(|meta| {
+			let given = if meta.path.is_ident("skip") {
+				core::mem::replace(&mut options.skip, true)
+			} else if meta.path.is_ident("inherent") {
+				core::mem::replace(&mut options.inherent, true)
+			} else if meta.path.is_ident("weight") {
+				options.weight.replace(meta.value()?.parse()?).is_some()
+			} else {
+				let msg = "Invalid attribute, expected `#[call(skip)]`, `#[call(inherent)]` or \
+					`#[call(weight = expr)]`";
+				return Err(syn::Error::new_spanned(attr, msg))
+			};
+			if given {
+				let msg = "Invalid attribute, this option of `#[call(..)]` is given more than once";
+				return Err(syn::Error::new_spanned(&meta.path, msg))
+			}
+			Ok(())
+		})?;
+	}
+	Ok(options)
+}
+
+/// Check whether `ty` is the runtime, as `&mut T` where `T` is the generic type implementing the
+/// `Config` of the pallet.
+fn is_runtime(ty: &syn::Type, config_type: &syn::Ident) -> bool {
+	match ty {
+		syn::Type::Reference(reference) if reference.mutability.is_some() => matches!(
+			&*reference.elem,
+			syn::Type::Path(tp) if tp.qself.is_none() && tp.path.is_ident(config_type)
+		),
+		_ => false,
 	}
 }
 
-/// Check caller arg is exactly: `caller: T::AccountId`.
-///
-/// This is kept strict to keep the code simple.
-pub fn check_caller_arg(arg: &syn::PatType) -> syn::Result<()> {
-	pub struct CheckDispatchableFirstArg;
-	impl syn::parse::Parse for CheckDispatchableFirstArg {
-		fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-			input.parse::<keyword::T>()?;
-			input.parse::<syn::Token![::]>()?;
-			input.parse::<keyword::AccountId>()?;
-			Ok(Self)
+/// Get the index given by an attribute such as `#[call_index(n)]`, with the attribute itself for
+/// reporting errors, if it is present.
+pub fn get_index<'a>(
+	attrs: &'a [syn::Attribute],
+	name: &str,
+) -> syn::Result<Option<(u8, &'a syn::Attribute)>> {
+	let mut found = None;
+	for attr in attrs.iter().filter(|attr| attr.path().is_ident(name)) {
+		if found.is_some() {
+			let msg = format!("Invalid attribute, `#[{}]` is given more than once", name);
+			return Err(syn::Error::new_spanned(attr, msg))
 		}
+		let index = attr.parse_args::<syn::LitInt>()?.base10_parse::<u8>()?;
+		found = Some((index, attr));
 	}
+	Ok(found)
+}
 
-	// This checks the arg name is `caller` or `_caller`.
-	if let syn::Pat::Ident(ident) = &*arg.pat {
-		// We also support the name as `_caller` for when the variable is unused.
-		if &ident.ident != "caller" && &ident.ident != "_caller" {
-			let msg = "Invalid name for second parameter: expected `caller: T::AccountId`";
-			return Err(syn::Error::new(ident.span(), msg))
+/// This function removes the attributes which are only read by the call macro, such as
+/// `#[call_index(n)]` and `#[call(skip)]`, from the functions of the `impl` block, since they are
+/// not valid Rust attributes.
+pub fn strip_attributes(item: &mut syn::Item) {
+	if let syn::Item::Impl(item_impl) = item {
+		for item in item_impl.items.iter_mut() {
+			if let syn::ImplItem::Fn(method) = item {
+				method.attrs.retain(|attr| {
+					!attr.path().is_ident("call_index") && !attr.path().is_ident("call")
+				});
+			}
 		}
 	}
+}
 
-	// This checks the type is `T::AccountId` with `CheckDispatchableFirstArg`
-	let ty = &arg.ty;
-	syn::parse2::<CheckDispatchableFirstArg>(ty.to_token_stream()).map_err(|e| {
-		let msg = "Invalid type for second parameter: expected `caller: T::AccountId`";
-		let mut err = syn::Error::new(ty.span(), msg);
-		err.combine(e);
-		err
-	})?;
+/// Collect the lines of a doc comment, which are `#[doc = "..."]` attributes, without the space
+/// after each `///`.
+pub fn get_docs(attrs: &[syn::Attribute]) -> Vec<String> {
+	let mut docs = vec![];
+	for attr in attrs.iter().filter(|attr| attr.path().is_ident("doc")) {
+		if let syn::Meta::NameValue(syn::MetaNameValue {
+			value: syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(line), .. }),
+			..
+		}) = &attr.meta
+		{
+			let line = line.value();
+			docs.push(line.strip_prefix(' ').unwrap_or(&line).to_string());
+		}
+	}
+	docs
+}
 
-	Ok(())
+/// Parse the arguments of the macros which expand a part of a pallet, such as
+/// `#[macros::call(support = path)]`. The only argument is the path of the module with the support
+/// types and traits, `crate::support` by default, so that pallets can be declared outside of this
+/// crate.
+pub fn get_support(attr: proc_macro2::TokenStream) -> syn::Result<syn::Path> {
+	let mut support = None;
+	let parser = syn::meta::parser(|meta| {
+		if !meta.path.is_ident("support") {
+			return Err(meta.error("Invalid argument, expected `support`"))
+		}
+		if support.replace(meta.value()?.parse()?).is_some() {
+			return Err(meta.error("Invalid argument, duplicate argument"))
+		}
+		Ok(())
+	});
+	syn::parse::Parser::parse2(parser, attr)?;
+	Ok(support.unwrap_or_else(|| syn::parse_quote!(crate::support)))
 }
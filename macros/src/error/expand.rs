@@ -0,0 +1,46 @@
+use super::parse::ErrorDef;
+use quote::quote;
+
+/// See the `fn error` docs at the `lib.rs` of this crate for a high level definition.
+pub fn expand_error(def: ErrorDef) -> proc_macro2::TokenStream {
+	let ErrorDef { name, generics, variants, support } = def;
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+	// This is a vector of all the error names.
+	let variant = variants.iter().map(|variant| &variant.name).collect::<Vec<_>>();
+
+	// This is the index of each error, which is its position in the enum.
+	let index = (0..variants.len()).map(|index| index as u8).collect::<Vec<_>>();
+
+	// This is a vector of the lines of the doc comment of each error, for the metadata.
+	let docs = variants.iter().map(|variant| &variant.docs).collect::<Vec<_>>();
+
+	quote! {
+		// Errors are returned from the calls with their index and the name of their variant, so
+		// that they can be returned with `?` or `.into()` as a `DispatchResult`. The runtime finds
+		// their pallet from the module they are declared in.
+		impl #impl_generics From<#name #ty_generics> for #support::DispatchError #where_clause {
+			fn from(error: #name #ty_generics) -> Self {
+				let (error, message) = match error {
+					#( #name::#variant => (#index, stringify!(#variant)), )*
+				};
+				#support::DispatchError::Pallet { module: module_path!(), error, message }
+			}
+		}
+
+		impl #impl_generics #support::GetErrorMetadata for #name #ty_generics #where_clause {
+			fn error_metadata() -> Vec<#support::VariantMetadata> {
+				vec![
+					#(
+						#support::VariantMetadata {
+							name: stringify!(#variant),
+							index: #index,
+							fields: vec![],
+							docs: vec![ #( #docs ),* ],
+						},
+					)*
+				]
+			}
+		}
+	}
+}
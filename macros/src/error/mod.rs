@@ -0,0 +1,26 @@
+pub mod expand;
+pub mod parse;
+
+/// See the `fn error` docs at the `lib.rs` of this crate for a high level definition.
+pub fn error(
+	attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	let item_enum = syn::parse_macro_input!(item as syn::Item);
+
+	// First we parse the arguments of the macro and the errors of the pallet...
+	let def = crate::call::parse::get_support(attr.into())
+		.and_then(|support| parse::ErrorDef::try_from(&item_enum, support));
+	let generated: proc_macro::TokenStream = match def {
+		// ..then we generate our new code.
+		Ok(def) => expand::expand_error(def).into(),
+		Err(e) => e.to_compile_error().into(),
+	};
+
+	// The final product contains all of our old code too.
+	let mut finished: proc_macro::TokenStream = quote::quote!(#item_enum).into();
+
+	// Add our generated code to the end, and return the final result.
+	finished.extend(generated);
+	finished
+}
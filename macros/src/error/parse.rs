@@ -0,0 +1,53 @@
+use syn::spanned::Spanned;
+
+/// This object will collect all the information we need to keep while parsing the error enum of
+/// a pallet.
+#[derive(Debug)]
+pub struct ErrorDef {
+	/// The name of the enum. We mostly assume it is `Error`.
+	pub name: syn::Ident,
+	/// The generics of the enum.
+	pub generics: syn::Generics,
+	/// The errors, in the order they are declared. See `ErrorVariantDef`.
+	pub variants: Vec<ErrorVariantDef>,
+	/// The path of the module with the support types and traits, from the `support` argument.
+	pub support: syn::Path,
+}
+
+/// This is the metadata we keep about each error.
+#[derive(Debug)]
+pub struct ErrorVariantDef {
+	/// The variant name, which is also the message the error is returned as.
+	pub name: syn::Ident,
+	/// The lines of the doc comment of the error, for the metadata.
+	pub docs: Vec<String>,
+}
+
+impl ErrorDef {
+	pub fn try_from(item: &syn::Item, support: syn::Path) -> syn::Result<Self> {
+		// First we check that we are parsing an `enum`.
+		let item_enum = if let syn::Item::Enum(item) = item {
+			item
+		} else {
+			return Err(syn::Error::new(item.span(), "Invalid error, expected item enum"))
+		};
+
+		// Errors are returned as a message, so they cannot carry any fields.
+		let mut variants = vec![];
+		for variant in item_enum.variants.iter() {
+			if !matches!(variant.fields, syn::Fields::Unit) {
+				let msg = "Invalid error, expected a variant without fields";
+				return Err(syn::Error::new(variant.fields.span(), msg))
+			}
+			let docs = crate::call::parse::get_docs(&variant.attrs);
+			variants.push(ErrorVariantDef { name: variant.ident.clone(), docs });
+		}
+
+		Ok(Self {
+			name: item_enum.ident.clone(),
+			generics: item_enum.generics.clone(),
+			variants,
+			support,
+		})
+	}
+}
@@ -0,0 +1,64 @@
+use super::parse::EventDef;
+use quote::quote;
+
+/// See the `fn event` docs at the `lib.rs` of this crate for a high level definition.
+pub fn expand_event(def: EventDef) -> proc_macro2::TokenStream {
+	let EventDef { name, generics, variants, support } = def;
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+	// This is a vector of all the event names.
+	let variant = variants.iter().map(|variant| &variant.name).collect::<Vec<_>>();
+
+	// This is the index of each event, which is its position in the enum.
+	let index = (0..variants.len()).map(|index| index as u8).collect::<Vec<_>>();
+
+	// These are nested vectors of the names and types of the fields of each event.
+	let field_name = variants
+		.iter()
+		.map(|variant| variant.fields.iter().map(|(name, _)| name).collect::<Vec<_>>())
+		.collect::<Vec<_>>();
+	let field_type = variants
+		.iter()
+		.map(|variant| variant.fields.iter().map(|(_, type_)| type_).collect::<Vec<_>>())
+		.collect::<Vec<_>>();
+
+	// This is a vector of the lines of the doc comment of each event, for the metadata.
+	let docs = variants.iter().map(|variant| &variant.docs).collect::<Vec<_>>();
+
+	quote! {
+		// Events are kept in the `events` storage item of the pallet, until the pallet clears them.
+		impl #impl_generics Pallet #ty_generics #where_clause {
+			/// Emit an event from this pallet.
+			fn deposit_event(&mut self, event: #name #ty_generics) {
+				self.events.mutate(|events| events.push(event));
+			}
+
+			/// Get the events emitted by this pallet, in the order they were emitted.
+			pub fn events(&self) -> &[#name #ty_generics] {
+				self.events.get()
+			}
+		}
+
+		impl #impl_generics #support::GetEventMetadata for #name #ty_generics #where_clause {
+			fn event_metadata() -> Vec<#support::VariantMetadata> {
+				vec![
+					#(
+						#support::VariantMetadata {
+							name: stringify!(#variant),
+							index: #index,
+							fields: vec![
+								#(
+									#support::FieldMetadata::new::<#field_type>(
+										stringify!(#field_name),
+										&[],
+									),
+								)*
+							],
+							docs: vec![ #( #docs ),* ],
+						},
+					)*
+				]
+			}
+		}
+	}
+}
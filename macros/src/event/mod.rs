@@ -0,0 +1,26 @@
+pub mod expand;
+pub mod parse;
+
+/// See the `fn event` docs at the `lib.rs` of this crate for a high level definition.
+pub fn event(
+	attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	let mut item_enum = syn::parse_macro_input!(item as syn::Item);
+
+	// First we parse the arguments of the macro and the events of the pallet...
+	let def = crate::call::parse::get_support(attr.into())
+		.and_then(|support| parse::EventDef::try_from(&mut item_enum, support));
+	let generated: proc_macro::TokenStream = match def {
+		// ..then we generate our new code.
+		Ok(def) => expand::expand_event(def).into(),
+		Err(e) => e.to_compile_error().into(),
+	};
+
+	// The final product contains all of our old code too, with the marker of its generic types.
+	let mut finished: proc_macro::TokenStream = quote::quote!(#item_enum).into();
+
+	// Add our generated code to the end, and return the final result.
+	finished.extend(generated);
+	finished
+}
@@ -0,0 +1,75 @@
+use syn::spanned::Spanned;
+
+/// This object will collect all the information we need to keep while parsing the event enum of
+/// a pallet.
+#[derive(Debug)]
+pub struct EventDef {
+	/// The name of the enum. We mostly assume it is `Event`.
+	pub name: syn::Ident,
+	/// The generics of the enum, which must be the same as those of the `Pallet` struct.
+	pub generics: syn::Generics,
+	/// The events, in the order they are declared. See `EventVariantDef`.
+	pub variants: Vec<EventVariantDef>,
+	/// The path of the module with the support types and traits, from the `support` argument.
+	pub support: syn::Path,
+}
+
+/// This is the metadata we keep about each event.
+#[derive(Debug)]
+pub struct EventVariantDef {
+	/// The variant name.
+	pub name: syn::Ident,
+	/// Information on the fields of the event: `(name, type)`.
+	pub fields: Vec<(syn::Ident, syn::Type)>,
+	/// The lines of the doc comment of the event, for the metadata.
+	pub docs: Vec<String>,
+}
+
+impl EventDef {
+	pub fn try_from(item: &mut syn::Item, support: syn::Path) -> syn::Result<Self> {
+		// First we check that we are parsing an `enum`.
+		let item_enum = if let syn::Item::Enum(item) = item {
+			item
+		} else {
+			return Err(syn::Error::new(item.span(), "Invalid event, expected item enum"))
+		};
+
+		// Events have named fields, or none at all.
+		let mut variants = vec![];
+		for variant in item_enum.variants.iter() {
+			let fields = match &variant.fields {
+				syn::Fields::Unit => vec![],
+				syn::Fields::Named(fields) => fields
+					.named
+					.iter()
+					.map(|field| (field.ident.clone().expect("fields are named"), field.ty.clone()))
+					.collect(),
+				fields => {
+					let msg = "Invalid event, expected a variant with named fields";
+					return Err(syn::Error::new(fields.span(), msg))
+				},
+			};
+			let docs = crate::call::parse::get_docs(&variant.attrs);
+			variants.push(EventVariantDef { name: variant.ident.clone(), fields, docs });
+		}
+
+		// Events need not have fields of every generic type, such as the instance of a pallet, so
+		// we add a variant which marks them as used. It can never be constructed, and is not
+		// described in the metadata.
+		let type_params = item_enum.generics.type_params().map(|param| &param.ident);
+		let marker: syn::Variant = syn::parse_quote! {
+			#[doc(hidden)]
+			__Ignore(core::marker::PhantomData<( #( #type_params, )* )>, core::convert::Infallible)
+		};
+		if item_enum.generics.type_params().next().is_some() {
+			item_enum.variants.push(marker);
+		}
+
+		Ok(Self {
+			name: item_enum.ident.clone(),
+			generics: item_enum.generics.clone(),
+			variants,
+			support,
+		})
+	}
+}
@@ -1,6 +1,47 @@
 mod call;
+mod error;
+mod event;
+mod pallet;
 mod runtime;
+mod storage;
 
+/// Expand the callable functions of a pallet.
+///
+/// This generates an `enum Call` with a variant for every function in the `impl` block, and
+/// implements `support::Dispatchable`, `support::GetWeight`, `support::GetCallMetadata`, `Clone`,
+/// `core::fmt::Debug`, `PartialEq`, `Eq`, `core::hash::Hash` and `core::fmt::Display` for the
+/// `Call`, and `support::Dispatch` for the pallet when its calls do not need the runtime. These
+/// are bounded on the types of the arguments, rather than on the generic types, and `Display`
+/// writes a call as `name(arg: value, ..)` with `support::fmt_call`. The `Call` also derives the
+/// `Serialize` and `Deserialize` of the serde re-exported as `support::serde`, so calls can be
+/// encoded, with the same bounds.
+/// The metadata of each call includes the names and types of its arguments and its doc comment,
+/// which is also given to its variant.
+///
+/// Every function takes `&self` or `&mut self`, then the caller, such as `caller: T::AccountId`,
+/// then the arguments of the call. Functions which use other pallets, or dispatch other calls, take
+/// the whole runtime as `runtime: &mut T` instead of `self`. The bounds they need, such as
+/// `T: HasPallet<Self>`, go in the `where` clause of the `impl` block, which only the dispatch of
+/// the calls is bounded on. Unused arguments, such as `_amount`, are named without the underscore,
+/// and arguments which are not a plain name, such as `_`, are named `arg0`, `arg1` and so on after
+/// their position. The `Call` has the generics of the `impl` block, whose first type parameter
+/// implements the `Config` of the pallet, so the arguments can have any type built from them, but
+/// the functions themselves cannot be generic. The instance of a pallet, a last type parameter
+/// named `I`, defaults to `()` in the `Call`, as in `Pallet<T, I = ()>`.
+/// Helper functions are kept out of the calls with `#[call(skip)]`.
+///
+/// A call marked with `#[call(inherent)]` has no caller, and is only dispatched as an inherent,
+/// with `Dispatchable::dispatch_inherent`, never by a signed extrinsic. Calls have the default
+/// weight, unless they are given their own with `#[call(weight = expr)]`, where the expression can
+/// use the arguments of the call, such as `DEFAULT_CALL_WEIGHT + call.weight()` for a call which
+/// dispatches another call.
+///
+/// Each call is identified by its index when it is encoded, which is given with
+/// `#[call_index(n)]` on the function, or else is the position of the function in the `impl`
+/// block. Two calls with the same index are a compile error.
+///
+/// The generated code finds the support types and traits in `crate::support`, or in the module
+/// given with `#[macros::call(support = path)]`, so pallets can be declared in other crates.
 #[proc_macro_attribute]
 pub fn call(
 	attr: proc_macro::TokenStream,
@@ -9,22 +50,182 @@ pub fn call(
 	call::call(attr, item)
 }
 
+/// Expand the storage of a pallet, declared as the fields of its `Pallet` struct.
+///
+/// Every field must be a `support::StorageValue<V>`, `support::StorageMap<K, V>` or
+/// `support::StorageDoubleMap<K1, K2, V>`. This adds a `PhantomData` marker for the generic types
+/// of the struct, and generates:
+/// - `fn new()` - which creates the pallet with empty maps, and values at their default. The
+///   default of a value is given with `#[default(expr)]` on its field, or else is the `Default` of
+///   its type.
+/// - `Default` - which is the same as `fn new()`.
+/// - a getter for every field with `#[getter(name)]`, which returns a copy of the value, or of the
+///   value of the given keys for a map.
+/// - `support::GetStorageMetadata` - which describes the items with their types and doc comments.
+/// - `support::ReadStorage` - which reads an item by its name, or an entry of a map at a key given
+///   as JSON, formatted with `Debug`.
+///
+/// This must be placed above the `#[derive(..)]` of the struct, so that the derives include the
+/// marker. The support module is `crate::support`, or is given with
+/// `#[macros::storage(support = path)]`, as with `#[call]`.
+#[proc_macro_attribute]
+pub fn storage(
+	attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	storage::storage(attr, item)
+}
+
+/// Expand the event enum of a pallet, usually `enum Event<T: Config>`.
+///
+/// The variants of the enum must have named fields, or none. The pallet must have the same
+/// generics as the enum, and a storage item `events: StorageValue<Vec<Event<T>>>`. A hidden variant
+/// which can never be constructed marks the generic types as used, so an enum such as
+/// `Event<T: Config<I>, I = ()>` need not have fields of every type. This generates:
+/// - `fn deposit_event()` - which emits an event, by adding it to the `events` storage item.
+/// - `fn events()` - which gets the events emitted by the pallet. Pallets which only keep the
+///   events of the current block clear them in their `support::Hooks`.
+/// - `support::GetEventMetadata` - which describes the events with the types of their fields and
+///   their doc comments.
+///
+/// The runtime includes the events of the pallets whose field is marked with `#[event]` in its
+/// `RuntimeEvent`. The support module is `crate::support`, or is given with
+/// `#[macros::event(support = path)]`, as with `#[call]`.
+#[proc_macro_attribute]
+pub fn event(
+	attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	event::event(attr, item)
+}
+
+/// Expand the error enum of a pallet, usually `enum Error`.
+///
+/// The variants of the enum must have no fields. This generates:
+/// - `From<Error> for support::DispatchError` - which converts an error to a
+///   `DispatchError::Pallet` with its index and the name of its variant, so that calls can return
+///   it with `?` or `.into()`.
+/// - `support::GetErrorMetadata` - which describes the errors with their doc comments. The index of
+///   an error is its position in the enum, and the runtime adds the index of the pallet declaring
+///   the enum when a call returns the error, as a `support::ModuleError`.
+///
+/// The support module is `crate::support`, or is given with `#[macros::error(support = path)]`, as
+/// with `#[call]`.
+#[proc_macro_attribute]
+pub fn error(
+	attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	error::error(attr, item)
+}
+
+/// Expand a whole pallet, declared as an inline module whose items are marked with the section of
+/// the pallet they belong to:
+/// - `#[pallet::config]` - the `trait Config` of the pallet. Required.
+/// - `#[pallet::storage]` - the `struct Pallet<T: Config>`, expanded as with `#[storage]`.
+///   Required.
+/// - `#[pallet::call]` - the `impl` of the callable functions, expanded as with `#[call]`.
+///   Required.
+/// - `#[pallet::event]` - the enum of the events of the pallet, expanded as with `#[event]`.
+/// - `#[pallet::error]` - the enum of the errors of the pallet, expanded as with `#[error]`.
+/// - `#[pallet::hooks]` - the `impl support::Hooks` of the pallet. If it is missing, the pallet
+///   implements the hooks with their default methods.
+/// - `#[pallet::genesis_config]` - the `struct GenesisConfig`, which must implement
+///   `support::BuildGenesisConfig` for the pallet. This generates `fn from_genesis()`, which
+///   constructs the pallet in the genesis state built from the config.
+///
+/// It also implements `support::GetPalletMetadata` with the events and errors of the pallet. Other
+/// items of the module are kept as they are. Misplaced or duplicate sections are compile errors
+/// which point at the offending item.
+///
+/// The generated code finds the items it is built from with the arguments of the macro, so pallets
+/// can be declared in other crates, such as `#[macros::pallet(system = crate::system, support =
+/// crate::support)]`, where each argument is optional and these are the defaults:
+/// - `system` - the path of the module of the system pallet, whose `Config` gives the block number
+///   of the hooks.
+/// - `support` - the path of the module with the support types and traits, which is also given to
+///   the sections of the pallet.
+#[proc_macro_attribute]
+pub fn pallet(
+	attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	pallet::pallet(attr, item)
+}
+
 /// Expand the `Runtime` definition.
 ///
-/// This generates function implementations on `Runtime`:
+/// This generates function implementations on the runtime struct, named `Runtime` below:
 /// - `fn new()` - which generates a new instance of the runtime, by instantiating all the pallets
 ///   included in the runtime.
 /// - `fn execute_block()` - which handles basic logic for executing a block of extrinsics. It does
 ///   basic actions like incrementing the block number and checking the block to be executed has a
-///   valid block number.
+///   valid block number, parent hash and roots. An invalid block leaves the runtime as it was,
+///   since the block is executed on a copy of the runtime, which must implement `Clone`. The errors
+///   of the extrinsics are reported with their `support::ModuleError`, if they are errors of the
+///   error enum of a pallet.
+///
+/// It also implements the runtime APIs used by the node:
+/// - `support::BuildBlock` - the steps `execute_block` is made of, so blocks can be built one
+///   extrinsic at a time. Applying an extrinsic checks it is valid, charges its fee through
+///   `support::ChargeTransaction`, increments the nonce of the caller, and dispatches its call. The
+///   runtime must implement `support::ChargeTransaction<types::Extrinsic>`, even if only with the
+///   default methods, and `core::hash::Hash`, which gives the state root. The extrinsics of
+///   `types::Block` are `support::UncheckedExtrinsic<types::Extrinsic, RuntimeCall>`, so a block
+///   can also include inherents, which are dispatched with
+///   `support::Dispatchable::dispatch_inherent`. The inherents are created and checked by the
+///   pallets marked with `#[inherent]`, which must implement `support::ProvideInherent`, and
+///   `execute_block` rejects blocks whose inherents are not at their start, or which are missing a
+///   mandatory inherent. When the spec version in `system::Config::VERSION` is higher than that of
+///   the runtime which last migrated the state, starting a block first calls
+///   `system::Config::on_runtime_upgrade`. The `support::Hooks` of every pallet, including system,
+///   are called at the start and end of every block, so every pallet must implement them.
+/// - `support::ExecuteBlock` - which imports blocks with `execute_block`.
+/// - `support::ValidateTransaction` - which checks the signature and nonce of an extrinsic against
+///   the current state, for use by the transaction pool.
+/// - `support::GetRuntimeEvents` - which gets the events of the pallets marked with `#[event]`, as
+///   the `enum RuntimeEvent`, an "outer"-enum of their events, which each convert into.
+/// - `support::GetRuntimeMetadata` - which describes the pallets of the runtime, with their
+///   indices, calls, events, errors and storage, to client tools. Every pallet must implement
+///   `support::GetPalletMetadata`.
+/// - `support::ReadRuntimeStorage` - which reads a storage item of a pallet by the name of its
+///   field and the name of the item, or an entry of a map. Every pallet must implement
+///   `support::ReadStorage`.
 ///
 /// This also generates code needed for dispatching calls to the pallets:
-/// - Note: For simplicity, we assume that the system pallet is not callable.
+/// - Note: The calls of every pallet, including system, are in a `Call` enum in the module of the
+///   pallet, named after its field in the struct. The `Call` and `Event` of a pallet are found from
+///   the type of its field, such as `balances::Call<Runtime, Instance1>` for
+///   `balances::Pallet<Self, Instance1>`, so a pallet can be included several times, as different
+///   instances. Each instance has its own variant in `RuntimeCall` and `RuntimeEvent`, and its own
+///   storage, described in the metadata under the name of its field. Two fields of the same type
+///   are a compile error.
+/// - Each pallet is identified by its index when its calls are encoded, which is given with
+///   `#[pallet_index(n)]` on its field, or else is the position of the field in the struct. Two
+///   pallets with the same index are a compile error.
 /// - `enum RuntimeCall` - an "outer"-enum representing the accumulation of all possible calls to
-///   all pallets. The system pallet is not included.
-/// - implements the trait `support::Dispatch` to dispatch calls to the appropriate pallet. Basic
-///   logic like incrementing the nonce of the user is included in the generated code. The system
-///   pallet is not included.
+///   all pallets. It implements `support::GetWeight`, `Clone`, `core::fmt::Debug`, `PartialEq`,
+///   `Eq`, `core::hash::Hash`, and `core::fmt::Display`, which writes a call as `pallet.name(arg:
+///   value, ..)`, and the `Serialize` and `Deserialize` of `support::serde`, which encode a call as
+///   `{"pallet": {"name": {"arg": value, ..}}}`, so the `Call` of every pallet must implement these
+///   too.
+/// - implements the trait `support::Dispatch` to dispatch calls to the appropriate pallet.
+/// - implements the trait `support::KillStorage`, which resets a pallet other than system to
+///   `new()`, for the root-only `kill_storage` call of system.
+/// - implements the trait `support::HasPallet` for every pallet, including system, so that calls
+///   dispatched through `support::Dispatchable` can access any pallet of the runtime.
+///
+/// The first field of the struct is the system pallet, whatever its name. The generated code finds
+/// the items it is built from with the arguments of the macro, so runtimes can be built in other
+/// crates, such as `#[macros::runtime(block = types::Block, extrinsic = types::Extrinsic, system =
+/// crate::system, support = crate::support)]`, where each argument is optional and these are the
+/// defaults:
+/// - `block` - the type of the blocks of the runtime.
+/// - `extrinsic` - the type of the signed extrinsics of the runtime.
+/// - `system` - the path of the module of the system pallet, which has its `Config`.
+/// - `support` - the path of the module with the support types and traits.
+///
+/// Other items, such as `Vec` and `Result`, are named by their full paths in `core` and `std`.
 #[proc_macro_attribute]
 pub fn runtime(
 	attr: proc_macro::TokenStream,
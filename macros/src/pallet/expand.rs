@@ -0,0 +1,82 @@
+use super::parse::{PalletArgs, PalletDef};
+use quote::quote;
+
+/// See the `fn pallet` docs at the `lib.rs` of this crate for a high level definition.
+pub fn expand_pallet(def: PalletDef) -> proc_macro2::TokenStream {
+	let PalletDef { mut item_mod, args, storage, call, event, error, hooks, genesis_config } = def;
+	let PalletArgs { system, support } = args;
+
+	let pallet_struct = storage.item_struct.ident.clone();
+	let generics = storage.item_struct.generics.clone();
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+	// This is the generic type of the pallet which implements its `Config`, which is mostly `T`.
+	let config_type = &generics.type_params().next().expect("checked by the parser").ident;
+
+	// The storage and calls of the pallet are generated in the same way as with the storage and
+	// call macros.
+	let mut generated = vec![
+		crate::storage::expand::expand_storage(storage),
+		crate::call::expand::expand_call(call),
+	];
+
+	// Pallets without a `#[pallet::hooks]` impl do nothing at the start and end of a block.
+	if !hooks {
+		generated.push(quote! {
+			impl #impl_generics #support::Hooks<<#config_type as #system::Config>::BlockNumber>
+				for #pallet_struct #ty_generics #where_clause {}
+		});
+	}
+
+	// The events and errors of the pallet are generated in the same way as with the event and
+	// error macros, and are described in the metadata of the pallet.
+	let event_metadata = event.map(|event| {
+		let name = event.name.clone();
+		let (_, event_ty_generics, _) = event.generics.split_for_impl();
+		let event_type = quote!(#name #event_ty_generics);
+		generated.push(crate::event::expand::expand_event(event));
+		quote! {
+			fn event_metadata() -> Vec<#support::VariantMetadata> {
+				<#event_type as #support::GetEventMetadata>::event_metadata()
+			}
+		}
+	});
+	let error_metadata = error.map(|error| {
+		let name = error.name.clone();
+		let (_, error_ty_generics, _) = error.generics.split_for_impl();
+		let error_type = quote!(#name #error_ty_generics);
+		generated.push(crate::error::expand::expand_error(error));
+		quote! {
+			fn error_metadata() -> Vec<#support::VariantMetadata> {
+				<#error_type as #support::GetErrorMetadata>::error_metadata()
+			}
+		}
+	});
+
+	generated.push(quote! {
+		impl #impl_generics #support::GetPalletMetadata for #pallet_struct #ty_generics #where_clause {
+			#event_metadata
+			#error_metadata
+		}
+	});
+
+	// The pallet can be constructed in its genesis state, if it has a genesis config.
+	if let Some(genesis_config) = genesis_config {
+		let genesis_struct = &genesis_config.ident;
+		let (_, genesis_ty_generics, _) = genesis_config.generics.split_for_impl();
+		generated.push(quote! {
+			impl #impl_generics #pallet_struct #ty_generics #where_clause {
+				/// Constructs this pallet in the genesis state built from `config`.
+				pub fn from_genesis(config: &#genesis_struct #genesis_ty_generics) -> Result<Self, &'static str> {
+					let mut pallet = Self::new();
+					#support::BuildGenesisConfig::build(config, &mut pallet)?;
+					Ok(pallet)
+				}
+			}
+		});
+	}
+
+	// Add our generated code to the end of the module, and return the final result.
+	let (_, items) = item_mod.content.as_mut().expect("checked by the parser");
+	items.extend(generated.into_iter().map(syn::Item::Verbatim));
+	quote!(#item_mod)
+}
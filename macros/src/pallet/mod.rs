@@ -0,0 +1,26 @@
+pub mod expand;
+pub mod parse;
+
+/// See the `fn pallet` docs at the `lib.rs` of this crate for a high level definition.
+pub fn pallet(
+	attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	let mut item_mod = syn::parse_macro_input!(item as syn::Item);
+
+	// First we parse the sections of the pallet module, then we generate the module again, with our
+	// new code inside it.
+	let def = parse::PalletArgs::try_from(attr.into())
+		.and_then(|args| parse::PalletDef::try_from(item_mod.clone(), args));
+	match def {
+		Ok(def) => expand::expand_pallet(def).into(),
+		// Keep the module as it was written, without the attributes which only this macro
+		// understands, so that the code using the pallet still resolves.
+		Err(e) => {
+			parse::strip_attributes(&mut item_mod);
+			let mut finished: proc_macro::TokenStream = quote::quote!(#item_mod).into();
+			finished.extend(proc_macro::TokenStream::from(e.to_compile_error()));
+			finished
+		},
+	}
+}
@@ -0,0 +1,262 @@
+use crate::{
+	call::parse::CallDef, error::parse::ErrorDef, event::parse::EventDef,
+	storage::parse::StorageDef,
+};
+use syn::spanned::Spanned;
+
+/// This object will collect all the information we need to keep while parsing a pallet module.
+#[derive(Debug)]
+pub struct PalletDef {
+	/// This is the pallet module, without the attributes which only this macro understands, and
+	/// without the storage struct, which is generated again from `storage`.
+	pub item_mod: syn::ItemMod,
+	/// The arguments of the macro.
+	pub args: PalletArgs,
+	/// The storage items of the pallet, from the `#[pallet::storage]` struct.
+	pub storage: StorageDef,
+	/// The callable functions of the pallet, from the `#[pallet::call]` impl.
+	pub call: CallDef,
+	/// The events of the pallet, from the `#[pallet::event]` enum, if there is one.
+	pub event: Option<EventDef>,
+	/// The errors of the pallet, from the `#[pallet::error]` enum, if there is one.
+	pub error: Option<ErrorDef>,
+	/// True if the pallet implements `support::Hooks` itself, in a `#[pallet::hooks]` impl.
+	pub hooks: bool,
+	/// The `#[pallet::genesis_config]` struct, if there is one.
+	pub genesis_config: Option<syn::ItemStruct>,
+}
+
+/// These are the arguments of `#[macros::pallet(..)]`, which tell the macro where to find the items
+/// the pallet is built from, so that pallets can be declared outside of this crate.
+#[derive(Debug)]
+pub struct PalletArgs {
+	/// The path of the module of the system pallet, `crate::system` by default.
+	pub system: syn::Path,
+	/// The path of the module with the support types and traits, `crate::support` by default.
+	pub support: syn::Path,
+}
+
+impl PalletArgs {
+	pub fn try_from(attr: proc_macro2::TokenStream) -> syn::Result<Self> {
+		let mut system = None;
+		let mut support = None;
+		let parser = syn::meta::parser(|meta| {
+			let found = if meta.path.is_ident("system") {
+				system.replace(meta.value()?.parse()?).is_some()
+			} else if meta.path.is_ident("support") {
+				support.replace(meta.value()?.parse()?).is_some()
+			} else {
+				return Err(meta.error("Invalid pallet argument, expected `system` or `support`"))
+			};
+			if found {
+				return Err(meta.error("Invalid pallet argument, duplicate argument"))
+			}
+			Ok(())
+		});
+		syn::parse::Parser::parse2(parser, attr)?;
+
+		Ok(Self {
+			system: system.unwrap_or_else(|| syn::parse_quote!(crate::system)),
+			support: support.unwrap_or_else(|| syn::parse_quote!(crate::support)),
+		})
+	}
+}
+
+/// The sections of a pallet module, each marked with `#[pallet::<section>]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+	Config,
+	Storage,
+	Call,
+	Event,
+	Error,
+	Hooks,
+	GenesisConfig,
+}
+
+impl Section {
+	fn from_ident(ident: &syn::Ident) -> Option<Self> {
+		let section = match ident.to_string().as_str() {
+			"config" => Self::Config,
+			"storage" => Self::Storage,
+			"call" => Self::Call,
+			"event" => Self::Event,
+			"error" => Self::Error,
+			"hooks" => Self::Hooks,
+			"genesis_config" => Self::GenesisConfig,
+			_ => return None,
+		};
+		Some(section)
+	}
+}
+
+impl PalletDef {
+	pub fn try_from(item: syn::Item, args: PalletArgs) -> syn::Result<Self> {
+		// First we check that we are parsing an inline module.
+		let mut item_mod = if let syn::Item::Mod(item) = item {
+			item
+		} else {
+			return Err(syn::Error::new(item.span(), "Invalid pallet, expected item mod"))
+		};
+		let mod_span = item_mod.ident.span();
+		let items = if let Some((_, items)) = &mut item_mod.content {
+			items
+		} else {
+			let msg = "Invalid pallet, expected an inline module";
+			return Err(syn::Error::new(mod_span, msg))
+		};
+
+		let mut config = false;
+		let mut storage = None;
+		let mut call = None;
+		let mut event = None;
+		let mut error = None;
+		let mut hooks = false;
+		let mut genesis_config = None;
+
+		// Here we find the section of each item, and remove the attribute which marks it.
+		let mut found: Vec<Section> = vec![];
+		let mut index = 0;
+		while index < items.len() {
+			let (section, attr) = match take_section(&mut items[index])? {
+				Some(section) => section,
+				None => {
+					index += 1;
+					continue
+				},
+			};
+			if found.contains(&section) {
+				let name = &attr.path().segments[1].ident;
+				let msg = format!("Invalid pallet, duplicate `#[pallet::{}]`", name);
+				return Err(syn::Error::new_spanned(attr, msg))
+			}
+			found.push(section);
+
+			let item = &mut items[index];
+			match section {
+				Section::Config => match item {
+					syn::Item::Trait(item) if item.ident == "Config" => config = true,
+					_ => {
+						let msg = "Invalid pallet::config, expected `trait Config`";
+						return Err(syn::Error::new(item_span(item), msg))
+					},
+				},
+				Section::Storage => {
+					// The storage struct is generated again, with the items of the pallet.
+					storage =
+						Some(StorageDef::try_from(items.remove(index), args.support.clone())?);
+					continue
+				},
+				Section::Call => {
+					call = Some(CallDef::try_from(item.clone(), args.support.clone())?);
+					crate::call::parse::strip_attributes(item);
+				},
+				Section::Event => event = Some(EventDef::try_from(item, args.support.clone())?),
+				Section::Error => error = Some(ErrorDef::try_from(item, args.support.clone())?),
+				Section::Hooks => match item {
+					syn::Item::Impl(syn::ItemImpl { trait_: Some((_, path, _)), .. })
+						if path.segments.last().is_some_and(|segment| segment.ident == "Hooks") =>
+						hooks = true,
+					_ => {
+						let msg =
+							"Invalid pallet::hooks, expected `impl Hooks<T::BlockNumber> for \
+							Pallet<T>`";
+						return Err(syn::Error::new(item_span(item), msg))
+					},
+				},
+				Section::GenesisConfig => match item {
+					syn::Item::Struct(item) if item.ident == "GenesisConfig" =>
+						genesis_config = Some(item.clone()),
+					_ => {
+						let msg = "Invalid pallet::genesis_config, expected `struct GenesisConfig`";
+						return Err(syn::Error::new(item_span(item), msg))
+					},
+				},
+			}
+			index += 1;
+		}
+
+		// A pallet always has a config, storage and calls.
+		if !config {
+			let msg = "Invalid pallet, missing `#[pallet::config]` trait";
+			return Err(syn::Error::new(mod_span, msg))
+		}
+		let storage = storage.ok_or_else(|| {
+			syn::Error::new(mod_span, "Invalid pallet, missing `#[pallet::storage]` struct")
+		})?;
+		if storage.item_struct.generics.type_params().next().is_none() {
+			let msg = "Invalid pallet::storage, expected `struct Pallet<T: Config>`";
+			return Err(syn::Error::new(storage.item_struct.ident.span(), msg))
+		}
+		let call = call.ok_or_else(|| {
+			syn::Error::new(mod_span, "Invalid pallet, missing `#[pallet::call]` impl")
+		})?;
+
+		Ok(Self { item_mod, args, storage, call, event, error, hooks, genesis_config })
+	}
+}
+
+/// Remove the `#[pallet::<section>]` attribute of an item, and return its section with the
+/// attribute itself for reporting errors, if there is one.
+fn take_section(item: &mut syn::Item) -> syn::Result<Option<(Section, syn::Attribute)>> {
+	let attrs = match item {
+		syn::Item::Trait(item) => &mut item.attrs,
+		syn::Item::Struct(item) => &mut item.attrs,
+		syn::Item::Enum(item) => &mut item.attrs,
+		syn::Item::Impl(item) => &mut item.attrs,
+		_ => return Ok(None),
+	};
+	let position = attrs.iter().position(|attr| {
+		let segments = &attr.path().segments;
+		segments.len() == 2 && segments[0].ident == "pallet"
+	});
+	let attr = match position {
+		Some(position) => attrs.remove(position),
+		None => return Ok(None),
+	};
+	let name = &attr.path().segments[1].ident;
+	match Section::from_ident(name) {
+		Some(section) if matches!(attr.meta, syn::Meta::Path(_)) => Ok(Some((section, attr))),
+		Some(_) => {
+			let msg = format!("Invalid pallet, `#[pallet::{}]` takes no arguments", name);
+			Err(syn::Error::new_spanned(&attr, msg))
+		},
+		None => {
+			let msg = format!("Invalid pallet, unknown section `#[pallet::{}]`", name);
+			Err(syn::Error::new_spanned(&attr, msg))
+		},
+	}
+}
+
+/// The span of the name of an item, or of the whole item if it has no name, for errors about the
+/// item.
+fn item_span(item: &syn::Item) -> proc_macro2::Span {
+	match item {
+		syn::Item::Trait(item) => item.ident.span(),
+		syn::Item::Struct(item) => item.ident.span(),
+		syn::Item::Enum(item) => item.ident.span(),
+		syn::Item::Impl(item) => item.self_ty.span(),
+		item => item.span(),
+	}
+}
+
+/// This function removes the attributes which are only read by the pallet macro, such as
+/// `#[pallet::config]` and the attributes of the storage items and calls, from the items of the
+/// module, since they are not valid Rust attributes. This is used when the pallet is invalid, so
+/// that only the error of the pallet is reported.
+pub fn strip_attributes(item: &mut syn::Item) {
+	if let syn::Item::Mod(syn::ItemMod { content: Some((_, items)), .. }) = item {
+		for item in items.iter_mut() {
+			// Errors here are reported by `PalletDef::try_from`.
+			let _ = take_section(item);
+			crate::call::parse::strip_attributes(item);
+			if let syn::Item::Struct(item_struct) = item {
+				for field in item_struct.fields.iter_mut() {
+					field.attrs.retain(|attr| {
+						!attr.path().is_ident("getter") && !attr.path().is_ident("default")
+					});
+				}
+			}
+		}
+	}
+}
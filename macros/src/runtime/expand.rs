@@ -1,45 +1,240 @@
-use super::parse::RuntimeDef;
+use super::parse::{RuntimeArgs, RuntimeDef};
 use quote::quote;
 
 /// See the `fn runtime` docs at the `lib.rs` of this crate for a high level definition.
 pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
-	let RuntimeDef { runtime_struct, pallets } = def;
+	let RuntimeDef { runtime_struct, pallets, system, inherents, events, args } = def;
+	let RuntimeArgs { block, extrinsic, system: system_path, support } = args;
+	let system_name = &system.name;
 
 	// This is a vector of all the pallet names, not including system.
-	let pallet_names = pallets.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
+	let pallet_names = pallets.iter().map(|pallet| pallet.name.clone()).collect::<Vec<_>>();
 	// This is a vector of all the pallet types, not including system.
-	let pallet_types = pallets.iter().map(|(_, type_)| type_.clone()).collect::<Vec<_>>();
+	let pallet_types = pallets.iter().map(|pallet| pallet.ty.clone()).collect::<Vec<_>>();
+	// This is the index of each pallet in the runtime, which identifies its calls when they are
+	// encoded, instead of the order in which the pallets are declared.
+	let pallet_index = pallets.iter().map(|pallet| pallet.index).collect::<Vec<_>>();
+	let system_index = system.index;
+	// These are the names and types of the pallets which provide inherents.
+	let inherent_names = inherents.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
+	let inherent_types = inherents.iter().map(|(_, type_)| type_.clone()).collect::<Vec<_>>();
+	// These are the names and types of the pallets with events, and the types of their events.
+	let event_names = events.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
+	let event_types = events.iter().map(|(_, type_)| type_.clone()).collect::<Vec<_>>();
+	// These are the names, types, calls and indices of all the pallets, including system, which
+	// has calls and hooks like the other pallets.
+	let all_pallets = core::iter::once(&system).chain(pallets.iter()).collect::<Vec<_>>();
+	let all_pallet_names = all_pallets.iter().map(|pallet| pallet.name.clone()).collect::<Vec<_>>();
+	let all_pallet_types = all_pallets.iter().map(|pallet| pallet.ty.clone()).collect::<Vec<_>>();
+	let all_pallet_calls = all_pallets.iter().map(|pallet| pallet.call.clone()).collect::<Vec<_>>();
+	let all_pallet_index = all_pallets.iter().map(|pallet| pallet.index).collect::<Vec<_>>();
+
+	// The calls are encoded with the serde which `support` re-exports, given to serde as a string.
+	let serde_crate = format!("{}::serde", quote!(#support));
 
 	// This quote block implements functions on the `Runtime` struct.
 	let runtime_impl = quote! {
 		impl #runtime_struct {
-			// Create a new instance of the main Runtime, by creating a new instance of each pallet.
-			fn new() -> Self {
+			// The indices and type names of the pallets, from which the errors of the pallets are
+			// given the index of their pallet.
+			fn pallet_types() -> ::std::vec::Vec<(u8, &'static str)> {
+				::std::vec![ #( (#all_pallet_index, ::core::any::type_name::<#all_pallet_types>()) ),* ]
+			}
+
+			/// Create a new instance of the main Runtime, by creating a new instance of each pallet.
+			pub fn new() -> Self {
 				Self {
-					// Since system is not included in the list of pallets, we manually add it here.
-					system: <system::Pallet::<Self>>::new(),
 					#(
-						#pallet_names: <#pallet_types>::new()
+						#all_pallet_names: <#all_pallet_types>::new()
 					),*
 				}
 			}
 
-			// Execute a block of extrinsics. Increments the block number.
-			fn execute_block(&mut self, block: types::Block) -> crate::support::DispatchResult {
-				self.system.inc_block_number();
-				if block.header.block_number != self.system.block_number() {
-					return Err(&"block number does not match what is expected")
+			/// Execute a block of extrinsics. Increments the block number.
+			///
+			/// The block is invalid if it does not follow the last block, if any of its extrinsics
+			/// is invalid, if its inherents are not at its start or a mandatory one is missing, or if
+			/// its roots do not match the result of executing it. An extrinsic whose call fails is
+			/// still included. The digest is taken from the block as is, since it is checked with
+			/// `VerifyHeader` before the block is executed.
+			///
+			/// The block is executed on a copy of the state, which replaces the state only once the
+			/// whole block is found valid, so an invalid block leaves the state as it was.
+			pub fn execute_block(&mut self, block: #block) -> #support::DispatchResult {
+				// The block is checked against the last block before the state is copied.
+				if block.header.block_number != self.#system_name.next_block_number() {
+					return ::core::result::Result::Err("block number does not match what is expected".into())
 				}
-				for (i, support::Extrinsic { caller, call }) in block.extrinsics.into_iter().enumerate() {
-					self.system.inc_nonce(&caller);
-					let _res = self.dispatch(caller, call).map_err(|e| {
-						eprintln!(
-							"Extrinsic Error\n\tBlock Number: {}\n\tExtrinsic Number: {}\n\tError: {}",
-							block.header.block_number, i, e
+				if block.header.parent_hash != self.#system_name.parent_hash() {
+					return ::core::result::Result::Err("parent hash does not match the last block".into())
+				}
+				let is_inherent = |extrinsic: &&#support::UncheckedExtrinsic<#extrinsic, RuntimeCall>| {
+					::core::matches!(extrinsic, #support::UncheckedExtrinsic::Inherent(_))
+				};
+				let inherents = block.extrinsics.iter().take_while(is_inherent).collect::<::std::vec::Vec<_>>();
+				if block.extrinsics.iter().skip(inherents.len()).any(|extrinsic| is_inherent(&extrinsic)) {
+					return ::core::result::Result::Err("inherents must be at the start of the block".into())
+				}
+				#(
+					if <#inherent_types as #support::ProvideInherent>::IS_MANDATORY
+						&& !inherents.iter().any(|extrinsic| ::core::matches!(
+							extrinsic,
+							#support::UncheckedExtrinsic::Inherent(RuntimeCall::#inherent_names(_))
+						))
+					{
+						return ::core::result::Result::Err("block is missing a mandatory inherent".into())
+					}
+				)*
+				let mut state = ::core::clone::Clone::clone(self);
+				let mut header = #support::BuildBlock::initialize_block(&mut state, block.header.digest);
+				header.extrinsics_root = #support::hash(&block.extrinsics);
+				for (i, extrinsic) in block.extrinsics.into_iter().enumerate() {
+					let _res = #support::BuildBlock::apply_extrinsic(&mut state, extrinsic)?.map_err(|e| {
+						::std::eprintln!(
+							"Extrinsic Error\n\tBlock Number: {}\n\tExtrinsic Number: {}\n\tError: {}\n\tModule Error: {:?}",
+							block.header.block_number, i, e, e.module_error()
 						)
 					});
 				}
-				Ok(())
+				if #support::BuildBlock::finalize_block(&mut state, header) != block.header {
+					return ::core::result::Result::Err("block roots do not match the result of executing it".into())
+				}
+				*self = state;
+				::core::result::Result::Ok(())
+			}
+		}
+
+		impl #support::BuildBlock for #runtime_struct {
+			type BlockNumber = <Self as #system_path::Config>::BlockNumber;
+			type Extrinsic = #support::UncheckedExtrinsic<#extrinsic, RuntimeCall>;
+
+			// Increment the block number, and start a header on top of the last block. If the
+			// runtime was upgraded since the last block, migrate the state first. A block cannot fail
+			// to start, so an error of the migrations is only reported. Every pallet, starting with
+			// system, is then told the block has started.
+			fn initialize_block(
+				&mut self,
+				digest: #support::Digest,
+			) -> #support::Header<Self::BlockNumber> {
+				if self.#system_name.needs_runtime_upgrade() {
+					if let ::core::result::Result::Err(e) = #system_path::Config::on_runtime_upgrade(self) {
+						::std::eprintln!("Runtime Upgrade Error\n\tError: {}\n\tModule Error: {:?}", e, e.module_error());
+					}
+					self.#system_name.note_runtime_upgrade();
+				}
+				self.#system_name.inc_block_number();
+				let block_number = self.#system_name.block_number();
+				#(
+					#support::Hooks::on_initialize(&mut self.#all_pallet_names, block_number);
+				)*
+				#support::Header {
+					parent_hash: self.#system_name.parent_hash(),
+					block_number: self.#system_name.block_number(),
+					extrinsics_root: 0,
+					state_root: 0,
+					digest,
+				}
+			}
+
+			// Check an extrinsic is valid, charge its fee, increment the caller's nonce, and
+			// dispatch its call.
+			//
+			// Inherents are dispatched without a caller. They are part of the block itself, so an
+			// inherent which fails makes the block invalid.
+			fn apply_extrinsic(
+				&mut self,
+				extrinsic: Self::Extrinsic,
+			) -> #support::ApplyExtrinsicResult {
+				let extrinsic = match extrinsic {
+					#support::UncheckedExtrinsic::Signed(extrinsic) => extrinsic,
+					#support::UncheckedExtrinsic::Inherent(call) => {
+						self.dispatch_inherent(call)?;
+						return ::core::result::Result::Ok(::core::result::Result::Ok(()))
+					},
+				};
+				let valid = #support::ValidateTransaction::validate_transaction(self, &extrinsic)?;
+				if valid.future {
+					return ::core::result::Result::Err("nonce is ahead of the account nonce")
+				}
+				#support::ChargeTransaction::withdraw_fee(self, &extrinsic)?;
+				self.#system_name.inc_nonce(&extrinsic.caller);
+				::core::result::Result::Ok(#support::Dispatch::dispatch(self, extrinsic.caller, extrinsic.call))
+			}
+
+			// Create the inherents of the pallets marked with `#[inherent]`.
+			fn inherent_extrinsics(
+				&self,
+				data: &#support::InherentData,
+			) -> ::std::vec::Vec<Self::Extrinsic> {
+				let mut inherents = ::std::vec::Vec::new();
+				#(
+					if let ::core::option::Option::Some(call) = #support::ProvideInherent::create_inherent(&self.#inherent_names, data) {
+						inherents.push(#support::UncheckedExtrinsic::Inherent(RuntimeCall::#inherent_names(call)));
+					}
+				)*
+				inherents
+			}
+
+			// Check every inherent of the block with the pallet which provides it.
+			fn check_inherents(
+				&self,
+				block: &#block,
+				data: &#support::InherentData,
+			) -> #support::DispatchResult {
+				for extrinsic in &block.extrinsics {
+					match extrinsic {
+						#(
+							#support::UncheckedExtrinsic::Inherent(RuntimeCall::#inherent_names(call)) =>
+								#support::ProvideInherent::check_inherent(&self.#inherent_names, call, data)?,
+						)*
+						_ => {},
+					}
+				}
+				::core::result::Result::Ok(())
+			}
+
+			// The state root is the hash of the whole runtime, taken after every pallet is told the
+			// block has ended, and before the block itself is recorded as the parent of the next
+			// block.
+			fn finalize_block(
+				&mut self,
+				mut header: #support::Header<Self::BlockNumber>,
+			) -> #support::Header<Self::BlockNumber> {
+				#(
+					#support::Hooks::on_finalize(&mut self.#all_pallet_names, header.block_number);
+				)*
+				header.state_root = #support::hash(self);
+				self.#system_name.set_parent_hash(header.hash());
+				header
+			}
+		}
+
+		impl #support::ExecuteBlock for #runtime_struct {
+			fn execute_block(&mut self, block: #block) -> #support::DispatchResult {
+				#runtime_struct::execute_block(self, block)
+			}
+		}
+
+		impl #support::ValidateTransaction for #runtime_struct {
+			type Extrinsic = #extrinsic;
+
+			// Check the signature and nonce of an extrinsic, and that its caller can pay for it.
+			//
+			// Extrinsics with a nonce ahead of the caller's account nonce are valid, but marked as
+			// `future`, since they can only be applied after the extrinsics before them.
+			fn validate_transaction(
+				&self,
+				extrinsic: &#extrinsic,
+			) -> #support::TransactionValidity {
+				if !extrinsic.verify() {
+					return ::core::result::Result::Err("invalid signature")
+				}
+				let nonce = self.#system_name.nonce(&extrinsic.caller);
+				if extrinsic.nonce < nonce {
+					return ::core::result::Result::Err("nonce is stale")
+				}
+				let priority = #support::ChargeTransaction::validate_payment(self, extrinsic)?;
+				::core::result::Result::Ok(#support::ValidTransaction { priority, future: extrinsic.nonce > nonce })
 			}
 		}
 	};
@@ -51,14 +246,40 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 		//
 		// The parsed function names will be `snake_case`, and that will show up in the enum.
 		#[allow(non_camel_case_types)]
+		#[derive(::core::fmt::Debug, ::core::clone::Clone, ::core::cmp::PartialEq, ::core::cmp::Eq)]
+		#[derive(#support::serde::Serialize, #support::serde::Deserialize)]
+		#[serde(crate = #serde_crate)]
 		pub enum RuntimeCall {
-			#( #pallet_names(#pallet_names::Call<#runtime_struct>) ),*
+			#( #all_pallet_names(#all_pallet_calls) ),*
 		}
 
-		impl crate::support::Dispatch for #runtime_struct {
-			type Caller = <Runtime as system::Config>::AccountId;
+		impl RuntimeCall {
+			// The index of the pallet the call is routed to, which encodes the errors it returns.
+			fn pallet_index(&self) -> u8 {
+				match self {
+					#( RuntimeCall::#all_pallet_names(_) => #all_pallet_index, )*
+				}
+			}
+		}
+
+		impl #runtime_struct {
+			// Dispatch an inherent call, which has no caller.
+			fn dispatch_inherent(&mut self, runtime_call: RuntimeCall) -> #support::DispatchResult {
+				let called = runtime_call.pallet_index();
+				let result = match runtime_call {
+					#(
+						RuntimeCall::#all_pallet_names(call) =>
+							#support::Dispatchable::dispatch_inherent(call, self),
+					)*
+				};
+				result.map_err(|error| error.in_runtime(called, &Self::pallet_types()))
+			}
+		}
+
+		impl #support::Dispatch for #runtime_struct {
+			type Caller = <#runtime_struct as #system_path::Config>::AccountId;
 			type Call = RuntimeCall;
-			// Dispatch a call on behalf of a caller. Increments the caller's nonce.
+			// Dispatch a call on behalf of a caller.
 			//
 			// Dispatch allows us to identify which underlying pallet call we want to execute.
 			// Note that we extract the `caller` from the extrinsic, and use that information
@@ -67,17 +288,172 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 				&mut self,
 				caller: Self::Caller,
 				runtime_call: Self::Call,
-			) -> crate::support::DispatchResult {
+			) -> #support::DispatchResult {
 				// This match statement will allow us to correctly route `RuntimeCall`s
-				// to the appropriate pallet level call.
-				match runtime_call {
+				// to the appropriate pallet level call. The errors of the pallets are then given
+				// the index of their pallet.
+				let called = runtime_call.pallet_index();
+				let result = match runtime_call {
 					#(
-						RuntimeCall::#pallet_names(call) => {
-							self.#pallet_names.dispatch(caller, call)?;
-						}
-					),*
+						RuntimeCall::#all_pallet_names(call) =>
+							#support::Dispatchable::dispatch(call, self, caller),
+					)*
+				};
+				result.map_err(|error| error.in_runtime(called, &Self::pallet_types()))
+			}
+		}
+
+		// A call is displayed as `pallet.name(arg: value, ..)`.
+		impl ::core::fmt::Display for RuntimeCall {
+			fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+				match self {
+					#(
+						RuntimeCall::#all_pallet_names(call) => {
+							::core::write!(f, "{}.", ::core::stringify!(#all_pallet_names))?;
+							::core::fmt::Display::fmt(call, f)
+						},
+					)*
+				}
+			}
+		}
+
+		impl #support::GetWeight for RuntimeCall {
+			fn weight(&self) -> #support::Weight {
+				match self {
+					#(
+						RuntimeCall::#all_pallet_names(call) => #support::GetWeight::weight(call),
+					)*
+				}
+			}
+		}
+
+		// Hashing a `RuntimeCall` hashes the index of the pallet it is routed to, followed by the
+		// pallet call.
+		impl ::core::hash::Hash for RuntimeCall {
+			fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+				match *self {
+					#(
+						RuntimeCall::#all_pallet_names(ref call) => {
+							::core::hash::Hash::hash(&#all_pallet_index, state);
+							::core::hash::Hash::hash(call, state);
+						},
+					)*
+				}
+			}
+		}
+
+		// Only root can kill the storage of a pallet, through the `kill_storage` call of system.
+		impl #support::KillStorage for #runtime_struct {
+			fn kill_storage(&mut self, pallet_index: u8) -> #support::DispatchResult {
+				match pallet_index {
+					#system_index => ::core::result::Result::Err("storage of system cannot be killed".into()),
+					#(
+						#pallet_index => {
+							self.#pallet_names = <#pallet_types>::new();
+							::core::result::Result::Ok(())
+						},
+					)*
+					_ => ::core::result::Result::Err("no pallet has this index".into()),
+				}
+			}
+		}
+	};
+
+	// This quote block gives every pallet, including system, access to the other pallets of the
+	// runtime through the `HasPallet` trait.
+	let has_pallet_impl = quote! {
+		#(
+			impl #support::HasPallet<#all_pallet_types> for #runtime_struct {
+				fn pallet(&self) -> &#all_pallet_types {
+					&self.#all_pallet_names
+				}
+
+				fn pallet_mut(&mut self) -> &mut #all_pallet_types {
+					&mut self.#all_pallet_names
 				}
-				Ok(())
+			}
+		)*
+	};
+
+	// This quote block describes the runtime to client tools. Every pallet, including system, must
+	// implement `support::GetPalletMetadata`, and has a `Call` enum implementing
+	// `support::GetCallMetadata`.
+	let metadata_impl = quote! {
+		impl #support::GetRuntimeMetadata for #runtime_struct {
+			fn metadata() -> #support::RuntimeMetadata {
+				let mut pallets = ::std::vec![
+					#(
+						#support::PalletMetadata::new::<#all_pallet_types>(
+							::core::stringify!(#all_pallet_names),
+							#all_pallet_index,
+							<#all_pallet_calls as #support::GetCallMetadata>::call_metadata(),
+						),
+					)*
+				];
+				pallets.sort_by_key(|pallet| pallet.index);
+				#support::RuntimeMetadata { pallets }
+			}
+		}
+
+		// Every pallet, including system, must implement `support::ReadStorage`, and is found by
+		// its name in the runtime.
+		impl #support::ReadRuntimeStorage for #runtime_struct {
+			fn read_storage(
+				&self,
+				pallet: &str,
+				item: &str,
+			) -> ::core::option::Option<::std::string::String> {
+				match pallet {
+					#(
+						::core::stringify!(#all_pallet_names) =>
+							#support::ReadStorage::read_storage(&self.#all_pallet_names, item),
+					)*
+					_ => ::core::option::Option::None,
+				}
+			}
+
+			fn read_storage_entry(
+				&self,
+				pallet: &str,
+				item: &str,
+				key: &str,
+			) -> ::core::option::Option<::std::string::String> {
+				match pallet {
+					#(
+						::core::stringify!(#all_pallet_names) =>
+							#support::ReadStorage::read_storage_entry(&self.#all_pallet_names, item, key),
+					)*
+					_ => ::core::option::Option::None,
+				}
+			}
+		}
+	};
+
+	// This quote block implements the `RuntimeEvent` enum, an "outer"-enum of the events of the
+	// pallets marked with `#[event]`, which client tools read from the runtime.
+	let event_impl = quote! {
+		#[allow(non_camel_case_types)]
+		#[derive(::core::fmt::Debug, ::core::clone::Clone, ::core::cmp::PartialEq, ::core::cmp::Eq)]
+		pub enum RuntimeEvent {
+			#( #event_names(#event_types) ),*
+		}
+
+		#(
+			impl ::core::convert::From<#event_types> for RuntimeEvent {
+				fn from(event: #event_types) -> Self {
+					RuntimeEvent::#event_names(event)
+				}
+			}
+		)*
+
+		impl #support::GetRuntimeEvents for #runtime_struct {
+			type RuntimeEvent = RuntimeEvent;
+
+			// The events of each pallet, in the order of the pallets in the runtime.
+			fn events(&self) -> ::std::vec::Vec<RuntimeEvent> {
+				::core::iter::empty()
+					#( .chain(self.#event_names.events().iter().cloned().map(RuntimeEvent::from)) )*
+					.collect()
 			}
 		}
 	};
@@ -85,7 +461,10 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 	// We combine and return all the generated code.
 	quote! {
 		#dispatch_impl
+		#event_impl
 		#runtime_impl
+		#has_pallet_impl
+		#metadata_impl
 	}
 	.into()
 }
@@ -1,3 +1,5 @@
+use crate::call::parse::get_index;
+use quote::ToTokens;
 use syn::spanned::Spanned;
 
 /// This object will collect all the information we need to keep while parsing the `Runtime` struct.
@@ -7,11 +9,86 @@ pub struct RuntimeDef {
 	pub runtime_struct: syn::Ident,
 	/// This is the list of pallets included in the `Runtime` struct. We omit `system` from this
 	/// list, but during parsing we check that system exists.
-	pub pallets: Vec<(syn::Ident, syn::Type)>,
+	pub pallets: Vec<PalletDef>,
+	/// This is the system pallet, which is always the first field of the `Runtime` struct.
+	pub system: PalletDef,
+	/// This is the list of pallets marked with `#[inherent]`, which provide inherents through
+	/// `support::ProvideInherent`.
+	pub inherents: Vec<(syn::Ident, syn::Type)>,
+	/// This is the list of pallets marked with `#[event]`, with the type of their `Event` enum
+	/// expanded with `#[macros::event]`. Unlike the other lists, this can include `system`.
+	pub events: Vec<(syn::Ident, syn::Type)>,
+	/// These are the paths the generated code is built from, given as arguments of the macro.
+	pub args: RuntimeArgs,
+}
+
+/// These are the arguments of `#[macros::runtime(..)]`, which tell the macro where to find the
+/// items it builds the runtime from, so that runtimes can be built outside of this crate.
+#[derive(Debug)]
+pub struct RuntimeArgs {
+	/// The type of the blocks of the runtime, `types::Block` by default.
+	pub block: syn::Type,
+	/// The type of the signed extrinsics of the runtime, `types::Extrinsic` by default.
+	pub extrinsic: syn::Type,
+	/// The path of the module of the system pallet, `crate::system` by default.
+	pub system: syn::Path,
+	/// The path of the module with the support types and traits, `crate::support` by default.
+	pub support: syn::Path,
+}
+
+impl RuntimeArgs {
+	pub fn try_from(attr: proc_macro2::TokenStream) -> syn::Result<Self> {
+		let mut block = None;
+		let mut extrinsic = None;
+		let mut system = None;
+		let mut support = None;
+		let parser = syn::meta::parser(|meta| {
+			let found = if meta.path.is_ident("block") {
+				block.replace(meta.value()?.parse()?).is_some()
+			} else if meta.path.is_ident("extrinsic") {
+				extrinsic.replace(meta.value()?.parse()?).is_some()
+			} else if meta.path.is_ident("system") {
+				system.replace(meta.value()?.parse()?).is_some()
+			} else if meta.path.is_ident("support") {
+				support.replace(meta.value()?.parse()?).is_some()
+			} else {
+				let msg = "Invalid runtime argument, expected `block`, `extrinsic`, `system` or \
+					`support`";
+				return Err(meta.error(msg))
+			};
+			if found {
+				return Err(meta.error("Invalid runtime argument, duplicate argument"))
+			}
+			Ok(())
+		});
+		syn::parse::Parser::parse2(parser, attr)?;
+
+		Ok(Self {
+			block: block.unwrap_or_else(|| syn::parse_quote!(types::Block)),
+			extrinsic: extrinsic.unwrap_or_else(|| syn::parse_quote!(types::Extrinsic)),
+			system: system.unwrap_or_else(|| syn::parse_quote!(crate::system)),
+			support: support.unwrap_or_else(|| syn::parse_quote!(crate::support)),
+		})
+	}
+}
+
+/// This is what we keep about each pallet of the `Runtime` struct.
+#[derive(Debug)]
+pub struct PalletDef {
+	/// The name of the field, which names the pallet in `RuntimeCall`, `RuntimeEvent` and the
+	/// metadata. Two instances of the same pallet are told apart by their names.
+	pub name: syn::Ident,
+	/// The type of the pallet, such as `balances::Pallet<Runtime, Instance1>`, where `Self` is
+	/// replaced by the name of the runtime.
+	pub ty: syn::Type,
+	/// The type of the calls of the pallet, such as `balances::Call<Runtime, Instance1>`.
+	pub call: syn::Type,
+	/// The index of the pallet, from `#[pallet_index(n)]`, or else its position in the struct.
+	pub index: u8,
 }
 
 impl RuntimeDef {
-	pub fn try_from(item: syn::Item) -> syn::Result<Self> {
+	pub fn try_from(item: syn::Item, args: RuntimeArgs) -> syn::Result<Self> {
 		// First we check that we are parsing a `struct`.
 		let item_struct = if let syn::Item::Struct(item) = item {
 			item
@@ -24,44 +101,186 @@ impl RuntimeDef {
 
 		let runtime_struct = item_struct.ident;
 
-		// Here is where we will store a list of all the pallets.
-		let mut pallets = vec![];
-		// We skip `system`, which we ensure is the first field in `check_system`.
-		for field in item_struct.fields.into_iter().skip(1) {
-			if let Some(ident) = field.ident {
-				pallets.push((ident, field.ty))
+		// Here is where we will store the index of every pallet, from `#[pallet_index(n)]`, or else
+		// the position of the pallet in the `Runtime` struct. Two pallets with the same index would
+		// have their calls encoded the same way.
+		let mut indices: Vec<u8> = vec![];
+		for (position, field) in item_struct.fields.iter().enumerate() {
+			let index = match get_index(&field.attrs, "pallet_index")? {
+				Some((index, attr)) => {
+					if indices.contains(&index) {
+						let msg = format!("Invalid runtime, duplicate pallet index {}", index);
+						return Err(syn::Error::new_spanned(attr, msg))
+					}
+					index
+				},
+				None => match u8::try_from(position) {
+					Ok(index) if !indices.contains(&index) => index,
+					_ => {
+						let msg =
+							"Invalid runtime, the implicit pallet index is already used, add \
+							an explicit `#[pallet_index(n)]`";
+						return Err(syn::Error::new(field.span(), msg))
+					},
+				},
+			};
+			indices.push(index);
+		}
+
+		// Here is where we will store a list of all the pallets. Several fields can hold instances
+		// of the same pallet, but each instance must have its own type, so that the pallets can
+		// find it through `support::HasPallet`.
+		let mut all_pallets: Vec<PalletDef> = vec![];
+		let mut inherents = vec![];
+		let mut events = vec![];
+		for (field, index) in item_struct.fields.into_iter().zip(indices.iter().copied()) {
+			let Some(name) = field.ident else { continue };
+			let ty = resolve_self(&field.ty, &runtime_struct)?;
+			let ty_string = ty.to_token_stream().to_string();
+			if all_pallets
+				.iter()
+				.any(|pallet| pallet.ty.to_token_stream().to_string() == ty_string)
+			{
+				let msg =
+					"Invalid runtime, duplicate pallet type, use another instance of the pallet";
+				return Err(syn::Error::new(field.ty.span(), msg))
+			}
+			if has_flag(&field.attrs, "event")? {
+				events.push((name.clone(), pallet_item(&ty, "Event")?))
+			}
+			if has_flag(&field.attrs, "inherent")? {
+				inherents.push((name.clone(), ty.clone()))
 			}
+			let call = pallet_item(&ty, "Call")?;
+			all_pallets.push(PalletDef { name, ty, call, index })
 		}
 
-		Ok(Self { runtime_struct, pallets })
+		// We take out `system`, which is the first field, as checked in `check_system`.
+		let mut pallets = all_pallets.into_iter();
+		let system = pallets.next().expect("`check_system` ensures the runtime has fields; qed");
+		if inherents.first().is_some_and(|(name, _)| *name == system.name) {
+			let msg = "Invalid runtime, system cannot provide inherents";
+			return Err(syn::Error::new(system.name.span(), msg))
+		}
+
+		Ok(Self { runtime_struct, pallets: pallets.collect(), system, inherents, events, args })
 	}
 }
 
-/// This function checks that the `system` pallet is the first pallet included in the `Runtime`
-/// struct. We make many assumptions about the `system` pallet in order to keep these macros simple.
-/// For example, we assume that the system pallet has no callable functions, and that it contains
-/// specific functions like incrementing the block number and a user's nonce.
+/// This function replaces `Self` in the type of a pallet with the name of the runtime, so that the
+/// type can also be named outside of the impls of the runtime, such as in `RuntimeCall`.
+fn resolve_self(ty: &syn::Type, runtime_struct: &syn::Ident) -> syn::Result<syn::Type> {
+	fn replace(
+		tokens: proc_macro2::TokenStream,
+		runtime_struct: &syn::Ident,
+	) -> proc_macro2::TokenStream {
+		tokens
+			.into_iter()
+			.map(|token| match token {
+				proc_macro2::TokenTree::Ident(ident) if ident == "Self" =>
+					proc_macro2::TokenTree::Ident(syn::Ident::new(
+						&runtime_struct.to_string(),
+						ident.span(),
+					)),
+				proc_macro2::TokenTree::Group(group) => {
+					let mut new_group = proc_macro2::Group::new(
+						group.delimiter(),
+						replace(group.stream(), runtime_struct),
+					);
+					new_group.set_span(group.span());
+					proc_macro2::TokenTree::Group(new_group)
+				},
+				token => token,
+			})
+			.collect()
+	}
+	syn::parse2(replace(ty.to_token_stream(), runtime_struct))
+}
+
+/// This function finds another item of the module of a pallet, such as its `Call` or `Event`, by
+/// replacing `Pallet` in the type of the pallet, and keeping its generic arguments. For example,
+/// `balances::Pallet<Runtime, Instance1>` gives `balances::Call<Runtime, Instance1>`.
 ///
-/// You can consider these macros to be tightly coupled to the logic of the `system` pallet.
-fn check_system(item_struct: &syn::ItemStruct) -> syn::Result<()> {
-	// Extract the name of the first field in the `Runtime` struct.
-	let first_field_name = if let Some(first_field) = item_struct.fields.iter().next() {
-		if let Some(field_name) = &first_field.ident {
-			field_name.to_string()
-		} else {
-			let msg = "first field is expected to have the name system";
-			return Err(syn::Error::new(item_struct.span(), msg))
-		}
-	} else {
-		let msg = "runtime struct is expected to have fields";
-		return Err(syn::Error::new(item_struct.span(), msg))
+/// The new type is generated by this macro, so lints on the items which use it, such as
+/// `RuntimeCall`, are not reported at the field of the pallet, though errors still point to it.
+fn pallet_item(ty: &syn::Type, item: &str) -> syn::Result<syn::Type> {
+	let item_ty = match ty {
+		syn::Type::Path(syn::TypePath { qself: None, path }) => {
+			let mut path = path.clone();
+			let last = path.segments.last_mut().expect("a type path has segments; qed");
+			last.ident = syn::Ident::new(item, last.ident.span());
+			path.into_token_stream()
+		},
+		_ => {
+			let msg = "Invalid runtime, expected the type of a pallet, such as \
+				`balances::Pallet<Self>`";
+			return Err(syn::Error::new(ty.span(), msg))
+		},
 	};
+	let call_site = proc_macro2::Span::call_site();
+	let respanned = item_ty.into_iter().map(|token| respan(token, call_site)).collect();
+	syn::parse2(respanned)
+}
+
+/// This function gives a token, and any tokens it groups, the hygiene of `span`, while keeping
+/// their location.
+fn respan(token: proc_macro2::TokenTree, span: proc_macro2::Span) -> proc_macro2::TokenTree {
+	let mut token = match token {
+		proc_macro2::TokenTree::Group(group) => {
+			let stream = group.stream().into_iter().map(|token| respan(token, span)).collect();
+			let mut new_group = proc_macro2::Group::new(group.delimiter(), stream);
+			new_group.set_span(group.span());
+			proc_macro2::TokenTree::Group(new_group)
+		},
+		token => token,
+	};
+	token.set_span(span.located_at(token.span()));
+	token
+}
 
-	// Check if the first field is named "system"
-	if first_field_name != "system" {
-		let msg = "first field is expected to be named system";
-		return Err(syn::Error::new(item_struct.span(), msg))
+/// This function checks whether a pallet field is marked with an attribute which takes no
+/// arguments, such as `#[inherent]` or `#[event]`.
+fn has_flag(attrs: &[syn::Attribute], name: &str) -> syn::Result<bool> {
+	let mut found = false;
+	for attr in attrs.iter().filter(|attr| attr.path().is_ident(name)) {
+		attr.meta.require_path_only()?;
+		found = true;
 	}
+	Ok(found)
+}
+
+/// This function removes the `#[inherent]`, `#[event]` and `#[pallet_index(n)]` attributes from
+/// the fields of the `Runtime` struct, since they are only read by this macro, and are not valid
+/// Rust attributes.
+pub fn strip_attributes(item: &mut syn::Item) {
+	if let syn::Item::Struct(item_struct) = item {
+		for field in item_struct.fields.iter_mut() {
+			field.attrs.retain(|attr| {
+				!attr.path().is_ident("inherent") &&
+					!attr.path().is_ident("event") &&
+					!attr.path().is_ident("pallet_index")
+			});
+		}
+	}
+}
 
-	Ok(())
+/// This function checks that the `Runtime` struct has named fields, the first of which is the
+/// `system` pallet, though it need not be named `system`. We make many assumptions about the
+/// `system` pallet in order to keep these macros simple. For example, we assume that the system
+/// pallet contains specific functions like incrementing the block number and a user's nonce, and
+/// that its module, given with `#[macros::runtime(system = path)]`, has its `Config`.
+///
+/// You can consider these macros to be tightly coupled to the logic of the `system` pallet.
+fn check_system(item_struct: &syn::ItemStruct) -> syn::Result<()> {
+	match item_struct.fields.iter().next() {
+		Some(first_field) if first_field.ident.is_some() => Ok(()),
+		Some(first_field) => {
+			let msg = "Invalid runtime, expected named fields, the first of which is system";
+			Err(syn::Error::new(first_field.span(), msg))
+		},
+		None => {
+			let msg = "Invalid runtime, expected the system pallet as the first field";
+			Err(syn::Error::new(item_struct.span(), msg))
+		},
+	}
 }
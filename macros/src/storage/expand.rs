@@ -0,0 +1,179 @@
+use super::parse::{StorageDef, StorageKind};
+use quote::quote;
+use syn::parse::Parser;
+
+/// See the `fn storage` docs at the `lib.rs` of this crate for a high level definition.
+pub fn expand_storage(def: StorageDef) -> proc_macro2::TokenStream {
+	let StorageDef { mut item_struct, items, support } = def;
+
+	// The storage of a pallet does not need to use all of its generic types, such as `T`, so we
+	// add a marker for them.
+	let type_params =
+		item_struct.generics.type_params().map(|param| &param.ident).collect::<Vec<_>>();
+	let marked = match type_params.as_slice() {
+		[param] => quote!(#param),
+		params => quote!(( #( #params, )* )),
+	};
+	let phantom = syn::Field::parse_named
+		.parse2(quote! {
+			/// A marker for the generic types of the pallet.
+			_phantom: core::marker::PhantomData<#marked>
+		})
+		.expect("the marker is a valid field");
+	if let syn::Fields::Named(fields) = &mut item_struct.fields {
+		fields.named.push(phantom);
+	}
+
+	let pallet_struct = &item_struct.ident;
+	let (impl_generics, ty_generics, where_clause) = item_struct.generics.split_for_impl();
+
+	// This is a vector of all the storage item names, and of their types.
+	let name = items.iter().map(|item| &item.name).collect::<Vec<_>>();
+	let ty = items.iter().map(|item| &item.ty).collect::<Vec<_>>();
+	// These are the bounds of the `where` clause of the struct, which the impls generated here
+	// repeat along with their own bounds.
+	let predicates = where_clause.into_iter().flat_map(|where_clause| &where_clause.predicates);
+	let predicates = quote!(#( #predicates, )*);
+
+	// This is a vector of the value each item starts at. Maps always start empty, and values start
+	// at their `#[default(expr)]`, or else at the default of their type.
+	let default = items
+		.iter()
+		.map(|item| match (&item.kind, &item.default) {
+			(StorageKind::Value { .. }, Some(default)) => {
+				quote!(#support::StorageValue::new(#default))
+			},
+			(StorageKind::Value { .. }, None) => {
+				quote!(#support::StorageValue::new(core::default::Default::default()))
+			},
+			_ => quote!(core::default::Default::default()),
+		})
+		.collect::<Vec<_>>();
+
+	// This is a vector of the lines of the doc comment of each item, for the metadata.
+	let docs = items.iter().map(|item| item.docs.clone()).collect::<Vec<_>>();
+
+	// These are the entries of the maps, read at a key decoded from JSON, along with the bounds
+	// on the types of the keys and values this needs.
+	let (entries, entry_bounds): (Vec<_>, Vec<_>) = items
+		.iter()
+		.filter_map(|item| {
+			let name = &item.name;
+			match &item.kind {
+				StorageKind::Value { .. } => None,
+				StorageKind::Map { key, value } => Some((
+					quote! {
+						stringify!(#name) => {
+							let key = #support::decode_key::<#key>(key)?;
+							self.#name.get(&key).map(|value| format!("{:?}", value))
+						},
+					},
+					quote!(#key: Ord + #support::serde::de::DeserializeOwned, #value: core::fmt::Debug,),
+				)),
+				StorageKind::DoubleMap { key1, key2, value } => Some((
+					quote! {
+						stringify!(#name) => {
+							let (key1, key2) = #support::decode_key::<(#key1, #key2)>(key)?;
+							self.#name.get(&key1, &key2).map(|value| format!("{:?}", value))
+						},
+					},
+					quote! {
+						#key1: Ord + #support::serde::de::DeserializeOwned,
+						#key2: Ord + #support::serde::de::DeserializeOwned,
+						#value: core::fmt::Debug,
+					},
+				)),
+			}
+		})
+		.unzip();
+
+	// These are the getter functions asked for with `#[getter(name)]`. They return a copy of the
+	// value, so that they can be used while the pallet is being changed.
+	let getters = items.iter().filter_map(|item| {
+		let getter = item.getter.as_ref()?;
+		let name = &item.name;
+		let docs = &item.docs;
+		let getter = match &item.kind {
+			StorageKind::Value { value } => quote! {
+				#( #[doc = #docs] )*
+				pub fn #getter(&self) -> #value {
+					core::clone::Clone::clone(self.#name.get())
+				}
+			},
+			StorageKind::Map { key, value } => quote! {
+				#( #[doc = #docs] )*
+				pub fn #getter(&self, key: &#key) -> Option<#value> {
+					self.#name.get(key).cloned()
+				}
+			},
+			StorageKind::DoubleMap { key1, key2, value } => quote! {
+				#( #[doc = #docs] )*
+				pub fn #getter(&self, key1: &#key1, key2: &#key2) -> Option<#value> {
+					self.#name.get(key1, key2).cloned()
+				}
+			},
+		};
+		Some(getter)
+	});
+
+	quote! {
+		#item_struct
+
+		impl #impl_generics #pallet_struct #ty_generics #where_clause {
+			/// Constructs a new instance of this pallet.
+			pub fn new() -> Self {
+				Self {
+					#( #name: #default, )*
+					_phantom: core::marker::PhantomData,
+				}
+			}
+
+			#( #getters )*
+		}
+
+		// A pallet starts the same with `default` as with `new`, whether or not its generic types
+		// implement `Default`.
+		impl #impl_generics core::default::Default for #pallet_struct #ty_generics #where_clause {
+			fn default() -> Self {
+				Self::new()
+			}
+		}
+
+		// The storage items of the pallet, with their doc comments.
+		impl #impl_generics #support::GetStorageMetadata for #pallet_struct #ty_generics #where_clause {
+			fn storage_metadata() -> Vec<#support::FieldMetadata> {
+				vec![
+					#(
+						#support::FieldMetadata::new::<#ty>(
+							stringify!(#name),
+							&[ #( #docs ),* ],
+						),
+					)*
+				]
+			}
+		}
+
+		// The storage items of the pallet, read by name. The bounds are on the types of the items,
+		// so that `T` itself need not be printable.
+		impl #impl_generics #support::ReadStorage for #pallet_struct #ty_generics
+		where
+			#predicates
+			#( #ty: core::fmt::Debug, )*
+			#( #entry_bounds )*
+		{
+			fn read_storage(&self, item: &str) -> Option<String> {
+				match item {
+					#( stringify!(#name) => Some(format!("{:?}", self.#name)), )*
+					_ => None,
+				}
+			}
+
+			fn read_storage_entry(&self, item: &str, key: &str) -> Option<String> {
+				match item {
+					#( #entries )*
+					_ => None,
+				}
+			}
+		}
+	}
+}
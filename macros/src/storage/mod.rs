@@ -0,0 +1,25 @@
+pub mod expand;
+pub mod parse;
+
+/// See the `fn storage` docs at the `lib.rs` of this crate for a high level definition.
+pub fn storage(
+	attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	let item_struct = syn::parse_macro_input!(item as syn::Item);
+
+	// First we parse the arguments of the macro and the storage items of the pallet, then we
+	// generate the pallet struct again, without the attributes which only this macro understands,
+	// together with our new code.
+	let def = crate::call::parse::get_support(attr.into())
+		.and_then(|support| parse::StorageDef::try_from(item_struct.clone(), support));
+	match def {
+		Ok(def) => expand::expand_storage(def).into(),
+		// Keep the struct as it was written, so that the code using the pallet still resolves.
+		Err(e) => {
+			let mut finished: proc_macro::TokenStream = quote::quote!(#item_struct).into();
+			finished.extend(proc_macro::TokenStream::from(e.to_compile_error()));
+			finished
+		},
+	}
+}
@@ -0,0 +1,146 @@
+use syn::spanned::Spanned;
+
+/// This object will collect all the information we need to keep while parsing the storage of a
+/// pallet.
+#[derive(Debug)]
+pub struct StorageDef {
+	/// This is the pallet struct, without the attributes which only this macro understands.
+	pub item_struct: syn::ItemStruct,
+	/// This is a list of the storage items of the pallet. See `StorageItemDef`.
+	pub items: Vec<StorageItemDef>,
+	/// The path of the module with the support types and traits, from the `support` argument.
+	pub support: syn::Path,
+}
+
+/// The kinds of storage items, with the generic arguments of their types.
+#[derive(Debug)]
+pub enum StorageKind {
+	/// `StorageValue<V>`
+	Value { value: Box<syn::Type> },
+	/// `StorageMap<K, V>`
+	Map { key: Box<syn::Type>, value: Box<syn::Type> },
+	/// `StorageDoubleMap<K1, K2, V>`
+	DoubleMap { key1: Box<syn::Type>, key2: Box<syn::Type>, value: Box<syn::Type> },
+}
+
+/// This is the metadata we keep about each storage item of our pallet.
+#[derive(Debug)]
+pub struct StorageItemDef {
+	/// The field name.
+	pub name: syn::Ident,
+	/// The full type of the field, such as `StorageMap<T::AccountId, T::Balance>`.
+	pub ty: syn::Type,
+	/// The kind of storage item, from the type of the field.
+	pub kind: StorageKind,
+	/// The name of the getter function to generate, from `#[getter(name)]`, if there is one.
+	pub getter: Option<syn::Ident>,
+	/// The value the item starts at, from `#[default(expr)]`, if there is one. Only storage
+	/// values can have a default, maps always start empty.
+	pub default: Option<syn::Expr>,
+	/// The lines of the doc comment of the field, for the metadata.
+	pub docs: Vec<String>,
+}
+
+impl StorageDef {
+	pub fn try_from(item: syn::Item, support: syn::Path) -> syn::Result<Self> {
+		// First we check that we are parsing a struct with named fields.
+		let mut item_struct = if let syn::Item::Struct(item) = item {
+			item
+		} else {
+			return Err(syn::Error::new(item.span(), "Invalid storage, expected item struct"))
+		};
+		let fields = if let syn::Fields::Named(fields) = &mut item_struct.fields {
+			fields
+		} else {
+			let msg = "Invalid storage, expected struct with named fields";
+			return Err(syn::Error::new(item_struct.fields.span(), msg))
+		};
+
+		// Here is where we will store all the storage items.
+		let mut items = vec![];
+		for field in fields.named.iter_mut() {
+			// Fields are always named here.
+			let name = field.ident.clone().expect("fields are named");
+			let kind = get_kind(&field.ty)?;
+
+			let getter = match get_attribute(&field.attrs, "getter")? {
+				Some(attr) => Some(attr.parse_args::<syn::Ident>()?),
+				None => None,
+			};
+			let default = match get_attribute(&field.attrs, "default")? {
+				Some(attr) if !matches!(kind, StorageKind::Value { .. }) => {
+					let msg = "Invalid storage, only a `StorageValue` can have a `#[default(..)]`";
+					return Err(syn::Error::new_spanned(attr, msg))
+				},
+				Some(attr) => Some(attr.parse_args::<syn::Expr>()?),
+				None => None,
+			};
+			let docs = crate::call::parse::get_docs(&field.attrs);
+
+			// Remove the attributes only this macro understands, since they are not valid Rust
+			// attributes.
+			field
+				.attrs
+				.retain(|attr| !attr.path().is_ident("getter") && !attr.path().is_ident("default"));
+
+			items.push(StorageItemDef { name, ty: field.ty.clone(), kind, getter, default, docs });
+		}
+
+		// Return all storage items of this pallet.
+		Ok(Self { item_struct, items, support })
+	}
+}
+
+/// Get the attribute `#[name(..)]` of a field, if it is present.
+fn get_attribute<'a>(
+	attrs: &'a [syn::Attribute],
+	name: &str,
+) -> syn::Result<Option<&'a syn::Attribute>> {
+	let mut found = None;
+	for attr in attrs.iter().filter(|attr| attr.path().is_ident(name)) {
+		if found.is_some() {
+			let msg = format!("Invalid attribute, `#[{}]` is given more than once", name);
+			return Err(syn::Error::new_spanned(attr, msg))
+		}
+		found = Some(attr);
+	}
+	Ok(found)
+}
+
+/// Get the kind of storage item from the type of a field, which must be one of the storage types
+/// of `support`, such as `StorageValue<V>` or `support::StorageMap<K, V>`.
+fn get_kind(ty: &syn::Type) -> syn::Result<StorageKind> {
+	let msg = "Invalid storage, expected `StorageValue<V>`, `StorageMap<K, V>` or \
+		`StorageDoubleMap<K1, K2, V>`";
+
+	let segment = match ty {
+		syn::Type::Path(tp) if tp.qself.is_none() => tp.path.segments.last(),
+		_ => None,
+	};
+	let segment = segment.ok_or_else(|| syn::Error::new(ty.span(), msg))?;
+
+	// Collect the generic type arguments of the storage type.
+	let mut args = vec![];
+	if let syn::PathArguments::AngleBracketed(generics) = &segment.arguments {
+		for arg in generics.args.iter() {
+			match arg {
+				syn::GenericArgument::Type(ty) => args.push(Box::new(ty.clone())),
+				_ => return Err(syn::Error::new(arg.span(), msg)),
+			}
+		}
+	}
+
+	let name = segment.ident.to_string();
+	let mut args = args.into_iter();
+	match (name.as_str(), args.len()) {
+		("StorageValue", 1) => Ok(StorageKind::Value { value: args.next().unwrap() }),
+		("StorageMap", 2) =>
+			Ok(StorageKind::Map { key: args.next().unwrap(), value: args.next().unwrap() }),
+		("StorageDoubleMap", 3) => Ok(StorageKind::DoubleMap {
+			key1: args.next().unwrap(),
+			key2: args.next().unwrap(),
+			value: args.next().unwrap(),
+		}),
+		_ => Err(syn::Error::new(ty.span(), msg)),
+	}
+}
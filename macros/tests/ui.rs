@@ -0,0 +1,10 @@
+// The macros are checked against small pallets and runtimes which either expand, or are rejected
+// with a spanned error, whose expected output is kept next to each of them.
+#[test]
+fn ui() {
+	let t = trybuild::TestCases::new();
+	t.pass("tests/ui/call/pass/*.rs");
+	t.compile_fail("tests/ui/call/fail/*.rs");
+	t.compile_fail("tests/ui/pallet/fail/*.rs");
+	t.compile_fail("tests/ui/runtime/fail/*.rs");
+}
@@ -0,0 +1,14 @@
+pub trait Config {
+	type AccountId;
+}
+
+pub struct Pallet<T: Config>(core::marker::PhantomData<T>);
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	pub fn remark<R: AsRef<[u8]>>(&mut self, _caller: T::AccountId, _remark: R) -> Result<(), &'static str> {
+		Ok(())
+	}
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+pub trait Config {
+	type AccountId;
+}
+
+pub struct Pallet<T: Config>(core::marker::PhantomData<T>);
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	#[call(ignore)]
+	fn helper(&self) {}
+}
+
+fn main() {}
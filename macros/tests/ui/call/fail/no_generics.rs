@@ -0,0 +1,10 @@
+pub struct Pallet;
+
+#[macros::call]
+impl Pallet {
+	pub fn remark(&mut self, _caller: u32) -> Result<(), &'static str> {
+		Ok(())
+	}
+}
+
+fn main() {}
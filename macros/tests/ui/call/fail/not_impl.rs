@@ -0,0 +1,10 @@
+pub trait Config {
+	type AccountId;
+}
+
+pub struct Pallet<T: Config>(core::marker::PhantomData<T>);
+
+#[macros::call]
+pub fn transfer() {}
+
+fn main() {}
@@ -0,0 +1,15 @@
+pub trait Config {
+	type AccountId;
+}
+
+pub struct Pallet<T: Config>(core::marker::PhantomData<T>);
+
+#[macros::call]
+#[allow(unused_parens)]
+impl<T: Config> (Pallet<T>) {
+	pub fn remark(&mut self, _caller: T::AccountId) -> Result<(), &'static str> {
+		Ok(())
+	}
+}
+
+fn main() {}
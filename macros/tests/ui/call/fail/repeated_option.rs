@@ -0,0 +1,15 @@
+pub trait Config {
+	type AccountId;
+}
+
+pub struct Pallet<T: Config>(core::marker::PhantomData<T>);
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	#[call(weight = 1, weight = 2)]
+	pub fn remark(&mut self, _caller: T::AccountId) -> Result<(), &'static str> {
+		Ok(())
+	}
+}
+
+fn main() {}
@@ -0,0 +1,143 @@
+// The parts of `support` used by the code generated by `#[macros::call]`.
+mod support {
+	pub type DispatchResult = Result<(), &'static str>;
+	pub type Weight = u64;
+	pub const DEFAULT_CALL_WEIGHT: Weight = 10_000;
+	pub use serde;
+
+	pub trait Dispatch {
+		type Caller;
+		type Call;
+		fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> DispatchResult;
+	}
+
+	pub trait Dispatchable<Runtime> {
+		type Caller;
+		fn dispatch(self, runtime: &mut Runtime, caller: Self::Caller) -> DispatchResult;
+	}
+
+	pub trait HasPallet<Pallet> {
+		fn pallet_mut(&mut self) -> &mut Pallet;
+	}
+
+	pub trait GetWeight {
+		fn weight(&self) -> Weight;
+	}
+
+	pub struct VariantMetadata {
+		pub name: &'static str,
+		pub index: u8,
+		pub fields: Vec<FieldMetadata>,
+		pub docs: Vec<&'static str>,
+	}
+
+	pub struct FieldMetadata {
+		pub name: &'static str,
+	}
+
+	impl FieldMetadata {
+		pub fn new<T: ?Sized>(name: &'static str, _docs: &[&'static str]) -> Self {
+			Self { name }
+		}
+	}
+
+	pub trait GetCallMetadata {
+		fn call_metadata() -> Vec<VariantMetadata>;
+	}
+
+	pub fn fmt_call(
+		f: &mut core::fmt::Formatter,
+		name: &str,
+		args: &[(&str, &dyn core::fmt::Debug)],
+	) -> core::fmt::Result {
+		let args = args.iter().map(|(arg, value)| format!("{}: {:?}", arg, value));
+		write!(f, "{}({})", name, args.collect::<Vec<_>>().join(", "))
+	}
+}
+
+use support::{DispatchResult, GetCallMetadata};
+
+pub trait Config {
+	type AccountId;
+	type Balance: Copy + core::ops::Add<Output = Self::Balance>;
+}
+
+pub struct Pallet<T: Config, Extra> {
+	total: Option<T::Balance>,
+	remarks: usize,
+	extra: core::marker::PhantomData<Extra>,
+}
+
+#[macros::call]
+impl<T: Config, Extra> Pallet<T, Extra>
+where
+	Extra: Default,
+{
+	/// Add `amount` to the total.
+	pub fn add(&mut self, _caller: T::AccountId, amount: T::Balance) -> DispatchResult {
+		let amount = self.double(amount);
+		self.total = Some(self.total.map_or(amount, |total| total + amount));
+		Ok(())
+	}
+
+	/// Check there is a total, without changing it.
+	pub fn check(&self, _: T::AccountId) -> DispatchResult {
+		self.total.map(|_| ()).ok_or("no total")
+	}
+
+	/// Count a remark, whatever it is.
+	#[call_index(5)]
+	pub fn remark(&mut self, _who: T::AccountId, _: Vec<u8>, (a, b): (u8, u8)) -> DispatchResult {
+		self.remarks += usize::from(a) + usize::from(b);
+		Ok(())
+	}
+
+	#[call(skip)]
+	fn double(&self, amount: T::Balance) -> T::Balance {
+		amount + amount
+	}
+}
+
+struct Runtime {
+	pallet: Pallet<Runtime, ()>,
+}
+
+impl Config for Runtime {
+	type AccountId = &'static str;
+	type Balance = u32;
+}
+
+impl support::HasPallet<Pallet<Runtime, ()>> for Runtime {
+	fn pallet_mut(&mut self) -> &mut Pallet<Runtime, ()> {
+		&mut self.pallet
+	}
+}
+
+fn main() {
+	let pallet = Pallet { total: None, remarks: 0, extra: core::marker::PhantomData };
+	let mut runtime = Runtime { pallet };
+	use support::Dispatchable;
+	// The caller is not an argument of the `Call`, so `check` has none.
+	assert_eq!(Call::<Runtime, ()>::check {}.dispatch(&mut runtime, "alice"), Err("no total"));
+	assert_eq!(Call::<Runtime, ()>::add { amount: 2 }.dispatch(&mut runtime, "alice"), Ok(()));
+	assert_eq!(Call::<Runtime, ()>::check {}.dispatch(&mut runtime, "alice"), Ok(()));
+	assert_eq!(runtime.pallet.total, Some(4));
+	let remark = Call::<Runtime, ()>::remark { arg0: vec![1], arg1: (2, 3) };
+	assert_eq!(remark.dispatch(&mut runtime, "bob"), Ok(()));
+	assert_eq!(runtime.pallet.remarks, 5);
+
+	// Calls are bounded on the types of their arguments, so `Runtime` need not be `Debug` or
+	// `PartialEq` itself.
+	let remark = Call::<Runtime, ()>::remark { arg0: vec![1], arg1: (2, 3) };
+	assert_eq!(remark.clone(), remark);
+	assert_ne!(remark, Call::<Runtime, ()>::check {});
+	assert_eq!(format!("{:?}", remark), "remark { arg0: [1], arg1: (2, 3) }");
+	assert_eq!(remark.to_string(), "remark(arg0: [1], arg1: (2, 3))");
+
+	let metadata = Call::<Runtime, ()>::call_metadata();
+	let names = metadata.iter().map(|call| (call.index, call.name)).collect::<Vec<_>>();
+	assert_eq!(names, [(0, "add"), (1, "check"), (5, "remark")]);
+	let fields = metadata[2].fields.iter().map(|field| field.name).collect::<Vec<_>>();
+	assert_eq!(fields, ["arg0", "arg1"]);
+	assert_eq!(metadata[1].docs, ["Check there is a total, without changing it."]);
+}
@@ -0,0 +1,139 @@
+// The parts of `support` used by the code generated by `#[macros::call]`.
+mod support {
+	pub type DispatchResult = Result<(), &'static str>;
+	pub type Weight = u64;
+	pub const DEFAULT_CALL_WEIGHT: Weight = 10_000;
+	pub use serde;
+
+	pub trait Dispatch {
+		type Caller;
+		type Call;
+		fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> DispatchResult;
+	}
+
+	pub trait Dispatchable<Runtime> {
+		type Caller;
+		fn dispatch(self, runtime: &mut Runtime, caller: Self::Caller) -> DispatchResult;
+		fn dispatch_inherent(self, _runtime: &mut Runtime) -> DispatchResult
+		where
+			Self: Sized,
+		{
+			Err("call is not an inherent")
+		}
+	}
+
+	pub trait HasPallet<Pallet> {
+		fn pallet_mut(&mut self) -> &mut Pallet;
+	}
+
+	pub trait GetWeight {
+		fn weight(&self) -> Weight;
+	}
+
+	pub struct VariantMetadata {
+		pub name: &'static str,
+		pub index: u8,
+		pub fields: Vec<FieldMetadata>,
+		pub docs: Vec<&'static str>,
+	}
+
+	pub struct FieldMetadata {
+		pub name: &'static str,
+	}
+
+	impl FieldMetadata {
+		pub fn new<T: ?Sized>(name: &'static str, _docs: &[&'static str]) -> Self {
+			Self { name }
+		}
+	}
+
+	pub trait GetCallMetadata {
+		fn call_metadata() -> Vec<VariantMetadata>;
+	}
+
+	pub fn fmt_call(
+		f: &mut core::fmt::Formatter,
+		name: &str,
+		args: &[(&str, &dyn core::fmt::Debug)],
+	) -> core::fmt::Result {
+		let args = args.iter().map(|(arg, value)| format!("{}: {:?}", arg, value));
+		write!(f, "{}({})", name, args.collect::<Vec<_>>().join(", "))
+	}
+}
+
+use support::{DispatchResult, Dispatchable, GetWeight, HasPallet};
+
+pub trait Config {
+	type AccountId;
+}
+
+pub struct Pallet<T: Config> {
+	now: u64,
+	remarks: Vec<(T::AccountId, usize)>,
+}
+
+// Calls which take the runtime reach the pallet, and others, through the bounds of the `where`
+// clause, which the `Call` itself does not need.
+#[macros::call]
+impl<T: Config> Pallet<T>
+where
+	T: HasPallet<Self>,
+{
+	/// Set the time, once per block.
+	#[call(inherent, weight = 0)]
+	pub fn set(runtime: &mut T, now: u64) -> DispatchResult {
+		runtime.pallet_mut().now = now;
+		Ok(())
+	}
+
+	/// Remark as the caller, at a weight which grows with the remark.
+	#[call(weight = support::DEFAULT_CALL_WEIGHT + remark.len() as u64)]
+	pub fn remark(runtime: &mut T, caller: T::AccountId, remark: Vec<u8>) -> DispatchResult {
+		runtime.pallet_mut().remarks.push((caller, remark.len()));
+		Ok(())
+	}
+
+	/// Check the time has been set.
+	pub fn check(&self, _caller: T::AccountId) -> DispatchResult {
+		if self.now == 0 {
+			return Err("no time");
+		}
+		Ok(())
+	}
+}
+
+struct Runtime {
+	pallet: Pallet<Runtime>,
+}
+
+impl Config for Runtime {
+	type AccountId = &'static str;
+}
+
+impl HasPallet<Pallet<Runtime>> for Runtime {
+	fn pallet_mut(&mut self) -> &mut Pallet<Runtime> {
+		&mut self.pallet
+	}
+}
+
+fn main() {
+	let mut runtime = Runtime { pallet: Pallet { now: 0, remarks: Vec::new() } };
+
+	// Inherents are not signed, and signed calls are not inherents.
+	let set = Call::<Runtime>::set { now: 6 };
+	assert_eq!(set.clone().dispatch(&mut runtime, "alice"), Err("call can only be dispatched as an inherent"));
+	assert_eq!(Call::<Runtime>::check {}.dispatch_inherent(&mut runtime), Err("call is not an inherent"));
+	assert_eq!(Call::<Runtime>::check {}.dispatch(&mut runtime, "alice"), Err("no time"));
+	assert_eq!(set.dispatch_inherent(&mut runtime), Ok(()));
+	assert_eq!(Call::<Runtime>::check {}.dispatch(&mut runtime, "alice"), Ok(()));
+
+	// The caller of a call which takes the runtime is its second argument.
+	let remark = Call::<Runtime>::remark { remark: vec![1, 2, 3] };
+	assert_eq!(remark.to_string(), "remark(remark: [1, 2, 3])");
+	assert_eq!(remark.clone().dispatch(&mut runtime, "bob"), Ok(()));
+	assert_eq!(runtime.pallet.remarks, [("bob", 3)]);
+
+	assert_eq!(Call::<Runtime>::set { now: 6 }.weight(), 0);
+	assert_eq!(remark.weight(), support::DEFAULT_CALL_WEIGHT + 3);
+	assert_eq!(Call::<Runtime>::check {}.weight(), support::DEFAULT_CALL_WEIGHT);
+}
@@ -0,0 +1,30 @@
+#[macros::pallet]
+pub mod pallet {
+	#[pallet::config]
+	pub trait Config {
+		type AccountId;
+	}
+
+	pub struct StorageValue<V>(core::marker::PhantomData<V>);
+
+	#[pallet::storage]
+	pub struct Pallet<T: Config> {
+		owner: StorageValue<T::AccountId>,
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		pub fn noop(&mut self, _caller: T::AccountId) -> Result<(), &'static str> {
+			Ok(())
+		}
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		pub fn other_noop(&mut self, _caller: T::AccountId) -> Result<(), &'static str> {
+			Ok(())
+		}
+	}
+}
+
+fn main() {}
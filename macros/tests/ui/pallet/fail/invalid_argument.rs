@@ -0,0 +1,4 @@
+#[macros::pallet(runtime = crate::runtime)]
+pub mod pallet {}
+
+fn main() {}
@@ -0,0 +1,22 @@
+#[macros::pallet]
+pub mod pallet {
+	pub trait Config {
+		type AccountId;
+	}
+
+	pub struct StorageValue<V>(core::marker::PhantomData<V>);
+
+	#[pallet::storage]
+	pub struct Pallet<T: Config> {
+		owner: StorageValue<T::AccountId>,
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		pub fn noop(&mut self, _caller: T::AccountId) -> Result<(), &'static str> {
+			Ok(())
+		}
+	}
+}
+
+fn main() {}
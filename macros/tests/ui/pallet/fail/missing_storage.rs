@@ -0,0 +1,18 @@
+#[macros::pallet]
+pub mod pallet {
+	#[pallet::config]
+	pub trait Config {
+		type AccountId;
+	}
+
+	pub struct Pallet<T: Config>(core::marker::PhantomData<T>);
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		pub fn noop(&mut self, _caller: T::AccountId) -> Result<(), &'static str> {
+			Ok(())
+		}
+	}
+}
+
+fn main() {}
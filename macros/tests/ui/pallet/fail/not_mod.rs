@@ -0,0 +1,4 @@
+#[macros::pallet]
+pub struct Pallet;
+
+fn main() {}
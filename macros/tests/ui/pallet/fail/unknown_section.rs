@@ -0,0 +1,12 @@
+#[macros::pallet]
+pub mod pallet {
+	#[pallet::config]
+	pub trait Config {}
+
+	#[pallet::storages]
+	pub struct Pallet<T: Config> {
+		_phantom: core::marker::PhantomData<T>,
+	}
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+#[macros::pallet]
+pub mod pallet {
+	#[pallet::config]
+	pub trait Settings {}
+}
+
+fn main() {}
@@ -0,0 +1,22 @@
+mod system {
+	pub struct Pallet<T>(core::marker::PhantomData<T>);
+}
+
+mod balances {
+	pub struct Pallet<T>(core::marker::PhantomData<T>);
+}
+
+mod proof_of_existence {
+	pub struct Pallet<T>(core::marker::PhantomData<T>);
+}
+
+#[macros::runtime]
+pub struct Runtime {
+	system: system::Pallet<Self>,
+	#[pallet_index(1)]
+	balances: balances::Pallet<Self>,
+	#[pallet_index(1)]
+	proof_of_existence: proof_of_existence::Pallet<Self>,
+}
+
+fn main() {}
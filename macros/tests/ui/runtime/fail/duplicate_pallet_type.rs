@@ -0,0 +1,16 @@
+mod system {
+	pub struct Pallet<T>(core::marker::PhantomData<T>);
+}
+
+mod balances {
+	pub struct Pallet<T, I = ()>(core::marker::PhantomData<(T, I)>);
+}
+
+#[macros::runtime]
+pub struct Runtime {
+	system: system::Pallet<Self>,
+	balances: balances::Pallet<Self>,
+	rewards: balances::Pallet<Runtime>,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+mod system {
+	pub struct Pallet<T>(core::marker::PhantomData<T>);
+}
+
+#[macros::runtime(blocks = types::Block)]
+pub struct Runtime {
+	system: system::Pallet<Self>,
+}
+
+fn main() {}
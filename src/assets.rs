@@ -0,0 +1,458 @@
+use crate::support::{self, DispatchResult, StorageMap};
+use num::traits::{CheckedAdd, CheckedSub, Zero};
+
+/// Configuration trait for the Assets pallet.
+/// Assets are held by the same accounts as the System pallet.
+pub trait Config: crate::system::Config {
+	/// The type used to identify an asset.
+	type AssetId: Ord + Clone;
+	/// The type used to represent the balance of an asset.
+	type Balance: Zero + CheckedAdd + CheckedSub + Copy + Ord;
+}
+
+/// The details of an asset.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AssetDetails<AccountId, Balance> {
+	/// The account which created the asset, and which can set its metadata.
+	pub owner: AccountId,
+	/// The account which can mint, burn, freeze and thaw the asset.
+	pub admin: AccountId,
+	/// The minimum balance an account can hold of the asset, unless it holds none.
+	pub min_balance: Balance,
+	/// The total amount of the asset held by all accounts.
+	pub supply: Balance,
+}
+
+/// The metadata of an asset.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AssetMetadata {
+	/// The name of the asset.
+	pub name: String,
+	/// The ticker symbol of the asset.
+	pub symbol: String,
+	/// The number of decimals used to display balances of the asset.
+	pub decimals: u8,
+}
+
+/// The errors returned by the Assets pallet.
+#[macros::error]
+pub enum Error {
+	/// The asset does not exist.
+	Unknown,
+	/// The caller is not the admin of the asset.
+	NoPermission,
+	/// The balance would be below the minimum balance of the asset, without being zero.
+	BalanceLow,
+	/// The account is frozen, so it cannot transfer the asset.
+	Frozen,
+	/// The account does not hold enough of the asset.
+	InsufficientBalance,
+	/// A balance or the supply of the asset overflowed.
+	Overflow,
+	/// An asset with this id already exists.
+	InUse,
+	/// The minimum balance of the asset is zero.
+	MinBalanceZero,
+	/// The caller is not the owner of the asset.
+	NotOwner,
+	/// The caller is not allowed to transfer this much on behalf of the owner.
+	InsufficientAllowance,
+}
+
+/// The Assets pallet.
+/// Allows accounts to create and manage fungible assets, besides the native balance managed by
+/// the Balances pallet.
+#[macros::storage]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Pallet<T: Config> {
+	/// A mapping from asset IDs to their details.
+	assets: StorageMap<T::AssetId, AssetDetails<T::AccountId, T::Balance>>,
+	/// A mapping from asset IDs to their metadata.
+	metadata: StorageMap<T::AssetId, AssetMetadata>,
+	/// A mapping from an asset and an account to the balance of that asset.
+	accounts: StorageMap<(T::AssetId, T::AccountId), T::Balance>,
+	/// The accounts which are frozen for an asset, and cannot transfer it.
+	frozen: StorageMap<(T::AssetId, T::AccountId), ()>,
+	/// A mapping from an asset, an owner and a delegate to the amount the delegate may transfer
+	/// on behalf of the owner.
+	#[allow(clippy::type_complexity)]
+	approvals: StorageMap<(T::AssetId, T::AccountId, T::AccountId), T::Balance>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Get the details of an asset, if it exists.
+	pub fn asset(&self, id: &T::AssetId) -> Option<&AssetDetails<T::AccountId, T::Balance>> {
+		self.assets.get(id)
+	}
+
+	/// Get the metadata of an asset, if it has been set.
+	pub fn metadata(&self, id: &T::AssetId) -> Option<&AssetMetadata> {
+		self.metadata.get(id)
+	}
+
+	/// Get the balance of an asset held by an account.
+	/// Returns zero if the account holds none of the asset.
+	pub fn balance(&self, id: &T::AssetId, who: &T::AccountId) -> T::Balance {
+		*self.accounts.get(&(id.clone(), who.clone())).unwrap_or(&T::Balance::zero())
+	}
+
+	/// Get the total supply of an asset.
+	/// Returns zero if the asset does not exist.
+	pub fn total_supply(&self, id: &T::AssetId) -> T::Balance {
+		self.asset(id).map(|details| details.supply).unwrap_or(T::Balance::zero())
+	}
+
+	/// Returns true if an account is frozen for an asset.
+	pub fn is_frozen(&self, id: &T::AssetId, who: &T::AccountId) -> bool {
+		self.frozen.contains_key(&(id.clone(), who.clone()))
+	}
+
+	/// Get the amount of an asset which `delegate` may transfer on behalf of `owner`.
+	pub fn allowance(
+		&self,
+		id: &T::AssetId,
+		owner: &T::AccountId,
+		delegate: &T::AccountId,
+	) -> T::Balance {
+		let key = (id.clone(), owner.clone(), delegate.clone());
+		*self.approvals.get(&key).unwrap_or(&T::Balance::zero())
+	}
+
+	/// Get the details of an asset, checking that `who` is its admin.
+	fn ensure_admin(
+		&self,
+		id: &T::AssetId,
+		who: &T::AccountId,
+	) -> Result<&AssetDetails<T::AccountId, T::Balance>, support::DispatchError> {
+		let details = self.asset(id).ok_or(Error::Unknown)?;
+		if details.admin != *who {
+			return Err(Error::NoPermission.into());
+		}
+		Ok(details)
+	}
+
+	/// Check that `balance` is allowed by the minimum balance of an asset.
+	fn ensure_min_balance(
+		details: &AssetDetails<T::AccountId, T::Balance>,
+		balance: T::Balance,
+	) -> DispatchResult {
+		if !balance.is_zero() && balance < details.min_balance {
+			return Err(Error::BalanceLow.into());
+		}
+		Ok(())
+	}
+
+	/// Set the balance of an asset held by an account, removing the entry when it is zero.
+	fn set_balance(&mut self, id: &T::AssetId, who: &T::AccountId, balance: T::Balance) {
+		let key = (id.clone(), who.clone());
+		if balance.is_zero() {
+			self.accounts.remove(&key);
+		} else {
+			self.accounts.insert(key, balance);
+		}
+	}
+
+	/// Move `amount` of an asset from one account to another.
+	fn do_transfer(
+		&mut self,
+		id: T::AssetId,
+		from: T::AccountId,
+		to: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let details = self.asset(&id).ok_or(Error::Unknown)?;
+		if self.is_frozen(&id, &from) {
+			return Err(Error::Frozen.into());
+		}
+
+		let new_from_balance = self
+			.balance(&id, &from)
+			.checked_sub(&amount)
+			.ok_or(Error::InsufficientBalance)?;
+		Self::ensure_min_balance(details, new_from_balance)?;
+		if from == to {
+			return Ok(());
+		}
+		let new_to_balance = self.balance(&id, &to).checked_add(&amount).ok_or(Error::Overflow)?;
+		Self::ensure_min_balance(details, new_to_balance)?;
+
+		self.set_balance(&id, &from, new_from_balance);
+		self.set_balance(&id, &to, new_to_balance);
+
+		Ok(())
+	}
+}
+
+/// The dispatchable functions of the Assets pallet.
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Create a new asset `id`, owned by the `caller` and administered by `admin`.
+	///
+	/// Accounts can only hold the asset in amounts of at least `min_balance`, or none at all.
+	#[call_index(0)]
+	pub fn create(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		admin: T::AccountId,
+		min_balance: T::Balance,
+	) -> DispatchResult {
+		if self.assets.contains_key(&id) {
+			return Err(Error::InUse.into());
+		}
+		if min_balance.is_zero() {
+			return Err(Error::MinBalanceZero.into());
+		}
+		let details =
+			AssetDetails { owner: caller, admin, min_balance, supply: T::Balance::zero() };
+		self.assets.insert(id, details);
+		Ok(())
+	}
+
+	/// Set the metadata of an asset. Only the owner of the asset can set its metadata.
+	#[call_index(1)]
+	pub fn set_metadata(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		name: String,
+		symbol: String,
+		decimals: u8,
+	) -> DispatchResult {
+		let details = self.asset(&id).ok_or(Error::Unknown)?;
+		if details.owner != caller {
+			return Err(Error::NotOwner.into());
+		}
+		self.metadata.insert(id, AssetMetadata { name, symbol, decimals });
+		Ok(())
+	}
+
+	/// Mint `amount` of an asset into the account `beneficiary`.
+	#[call_index(2)]
+	pub fn mint(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		beneficiary: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let details = self.ensure_admin(&id, &caller)?;
+		let new_supply = details.supply.checked_add(&amount).ok_or(Error::Overflow)?;
+		let new_balance =
+			self.balance(&id, &beneficiary).checked_add(&amount).ok_or(Error::Overflow)?;
+		Self::ensure_min_balance(details, new_balance)?;
+
+		self.set_balance(&id, &beneficiary, new_balance);
+		self.assets.mutate(id, |details| details.as_mut().unwrap().supply = new_supply);
+		Ok(())
+	}
+
+	/// Burn `amount` of an asset from the account `who`.
+	#[call_index(3)]
+	pub fn burn(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		who: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let details = self.ensure_admin(&id, &caller)?;
+		let new_balance =
+			self.balance(&id, &who).checked_sub(&amount).ok_or(Error::InsufficientBalance)?;
+		Self::ensure_min_balance(details, new_balance)?;
+		let new_supply = details.supply.checked_sub(&amount).ok_or(Error::Overflow)?;
+
+		self.set_balance(&id, &who, new_balance);
+		self.assets.mutate(id, |details| details.as_mut().unwrap().supply = new_supply);
+		Ok(())
+	}
+
+	/// Transfer `amount` of an asset from the `caller` to `target`.
+	#[call_index(4)]
+	pub fn transfer(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		target: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		self.do_transfer(id, caller, target, amount)
+	}
+
+	/// Freeze the account `who`, so that it cannot transfer the asset.
+	#[call_index(5)]
+	pub fn freeze(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		who: T::AccountId,
+	) -> DispatchResult {
+		self.ensure_admin(&id, &caller)?;
+		self.frozen.insert((id, who), ());
+		Ok(())
+	}
+
+	/// Thaw the account `who`, so that it can transfer the asset again.
+	#[call_index(6)]
+	pub fn thaw(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		who: T::AccountId,
+	) -> DispatchResult {
+		self.ensure_admin(&id, &caller)?;
+		self.frozen.remove(&(id, who));
+		Ok(())
+	}
+
+	/// Allow `delegate` to transfer up to `amount` of an asset on behalf of the `caller`.
+	///
+	/// This replaces any previous approval of the `delegate`.
+	#[call_index(7)]
+	pub fn approve_transfer(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		delegate: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		if self.asset(&id).is_none() {
+			return Err(Error::Unknown.into());
+		}
+		let key = (id, caller, delegate);
+		if amount.is_zero() {
+			self.approvals.remove(&key);
+		} else {
+			self.approvals.insert(key, amount);
+		}
+		Ok(())
+	}
+
+	/// Transfer `amount` of an asset from `owner` to `destination`, using an approval given to
+	/// the `caller` by `owner`.
+	#[call_index(8)]
+	pub fn transfer_approved(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		owner: T::AccountId,
+		destination: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let new_allowance = self
+			.allowance(&id, &owner, &caller)
+			.checked_sub(&amount)
+			.ok_or(Error::InsufficientAllowance)?;
+		self.do_transfer(id.clone(), owner.clone(), destination, amount)?;
+		self.approve_transfer(owner, id, caller, new_allowance)
+	}
+}
+
+impl<T: Config> support::Hooks<T::BlockNumber> for Pallet<T> {}
+
+impl<T: Config> support::GetPalletMetadata for Pallet<T> {
+	fn error_metadata() -> Vec<support::VariantMetadata> {
+		<Error as support::GetErrorMetadata>::error_metadata()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{assets, system};
+
+	// Mock struct for testing purposes.
+	struct TestConfig;
+
+	impl system::Config for TestConfig {
+		type AccountId = &'static str;
+		type BlockNumber = u32;
+		type Nonce = u32;
+	}
+
+	impl assets::Config for TestConfig {
+		type AssetId = u32;
+		type Balance = u128;
+	}
+
+	#[test]
+	fn create_and_set_metadata() {
+		let mut assets = assets::Pallet::<TestConfig>::new();
+
+		assert_eq!(assets.create("alice", 1, "bob", 0), Err(assets::Error::MinBalanceZero.into()));
+		assert_eq!(assets.create("alice", 1, "bob", 10), Ok(()));
+		assert_eq!(assets.create("bob", 1, "bob", 10), Err(assets::Error::InUse.into()));
+		assert_eq!(assets.asset(&1).map(|details| details.admin), Some("bob"));
+
+		let set_metadata = |assets: &mut assets::Pallet<TestConfig>, caller| {
+			assets.set_metadata(caller, 1, "Token".to_string(), "TKN".to_string(), 12)
+		};
+		assert_eq!(set_metadata(&mut assets, "bob"), Err(assets::Error::NotOwner.into()));
+		assert_eq!(set_metadata(&mut assets, "alice"), Ok(()));
+		assert_eq!(assets.metadata(&1).map(|metadata| metadata.decimals), Some(12));
+	}
+
+	#[test]
+	fn mint_burn_and_transfer() {
+		let mut assets = assets::Pallet::<TestConfig>::new();
+		assert_eq!(assets.create("alice", 1, "alice", 10), Ok(()));
+
+		assert_eq!(assets.mint("bob", 1, "bob", 100), Err(assets::Error::NoPermission.into()));
+		assert_eq!(assets.mint("alice", 2, "bob", 100), Err(assets::Error::Unknown.into()));
+		assert_eq!(assets.mint("alice", 1, "bob", 5), Err(assets::Error::BalanceLow.into()));
+		assert_eq!(assets.mint("alice", 1, "bob", 100), Ok(()));
+		assert_eq!(assets.total_supply(&1), 100);
+
+		assert_eq!(assets.transfer("bob", 1, "charlie", 95), Err(assets::Error::BalanceLow.into()));
+		assert_eq!(assets.transfer("bob", 1, "charlie", 5), Err(assets::Error::BalanceLow.into()));
+		assert_eq!(
+			assets.transfer("bob", 1, "charlie", 101),
+			Err(assets::Error::InsufficientBalance.into())
+		);
+		assert_eq!(assets.transfer("bob", 1, "charlie", 100), Ok(()));
+		assert_eq!(assets.balance(&1, &"bob"), 0);
+		assert_eq!(assets.balance(&1, &"charlie"), 100);
+
+		assert_eq!(assets.burn("alice", 1, "charlie", 40), Ok(()));
+		assert_eq!(assets.balance(&1, &"charlie"), 60);
+		assert_eq!(assets.total_supply(&1), 60);
+	}
+
+	#[test]
+	fn freeze_and_thaw() {
+		let mut assets = assets::Pallet::<TestConfig>::new();
+		assert_eq!(assets.create("alice", 1, "alice", 1), Ok(()));
+		assert_eq!(assets.mint("alice", 1, "bob", 100), Ok(()));
+
+		assert_eq!(assets.freeze("bob", 1, "bob"), Err(assets::Error::NoPermission.into()));
+		assert_eq!(assets.freeze("alice", 1, "bob"), Ok(()));
+		assert!(assets.is_frozen(&1, &"bob"));
+		assert_eq!(assets.transfer("bob", 1, "charlie", 10), Err(assets::Error::Frozen.into()));
+		// Frozen accounts can still receive the asset.
+		assert_eq!(assets.mint("alice", 1, "bob", 10), Ok(()));
+
+		assert_eq!(assets.thaw("alice", 1, "bob"), Ok(()));
+		assert_eq!(assets.transfer("bob", 1, "charlie", 10), Ok(()));
+	}
+
+	#[test]
+	fn approve_and_transfer_approved() {
+		let mut assets = assets::Pallet::<TestConfig>::new();
+		assert_eq!(assets.create("alice", 1, "alice", 1), Ok(()));
+		assert_eq!(assets.mint("alice", 1, "bob", 100), Ok(()));
+
+		assert_eq!(
+			assets.transfer_approved("charlie", 1, "bob", "dave", 10),
+			Err(assets::Error::InsufficientAllowance.into())
+		);
+		assert_eq!(assets.approve_transfer("bob", 1, "charlie", 30), Ok(()));
+		assert_eq!(assets.allowance(&1, &"bob", &"charlie"), 30);
+
+		assert_eq!(assets.transfer_approved("charlie", 1, "bob", "dave", 20), Ok(()));
+		assert_eq!(assets.balance(&1, &"dave"), 20);
+		assert_eq!(assets.allowance(&1, &"bob", &"charlie"), 10);
+		assert_eq!(
+			assets.transfer_approved("charlie", 1, "bob", "dave", 20),
+			Err(assets::Error::InsufficientAllowance.into())
+		);
+	}
+}
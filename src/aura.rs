@@ -0,0 +1,267 @@
+use crate::{
+	support::{self, DispatchResult, HasPallet, Header, Slot, StorageValue},
+	system,
+};
+use core::hash::Hash;
+
+/// Configuration trait for the Aura pallet.
+pub trait Config: system::Config<AccountId: Hash> {
+	/// The maximum number of authorities.
+	const MAX_AUTHORITIES: usize;
+}
+
+/// The errors returned by the Aura pallet.
+#[macros::error]
+pub enum Error {
+	/// There are no authorities to author blocks.
+	NoAuthorities,
+	/// There are more authorities than `MAX_AUTHORITIES`.
+	TooManyAuthorities,
+	/// The authorities are not all different.
+	DuplicateAuthority,
+	/// The slot of the block is not after the slot of its parent.
+	SlotNotAfterParent,
+	/// The block is not sealed by the author of its slot.
+	BadSeal,
+}
+
+/// The Aura pallet.
+/// Holds the set of authorities which take turns authoring blocks, one slot each, in round-robin
+/// order.
+#[macros::storage]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Pallet<T: Config> {
+	/// The accounts allowed to author blocks, in the order of their slots.
+	#[getter(authorities)]
+	authorities: StorageValue<Vec<T::AccountId>>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Get the authority allowed to author a block in `slot`.
+	pub fn slot_author(&self, slot: Slot) -> Option<&T::AccountId> {
+		let authorities = self.authorities.get();
+		if authorities.is_empty() {
+			return None;
+		}
+		let index = slot % authorities.len() as Slot;
+		authorities.get(index as usize)
+	}
+
+	/// Check that `header` is sealed by the author of its slot, and that its slot is after the
+	/// `parent_slot` of the last block.
+	pub fn verify_header<BlockNumber: Hash>(
+		&self,
+		header: &Header<BlockNumber>,
+		parent_slot: Slot,
+	) -> DispatchResult {
+		let slot = header.digest.slot;
+		if slot <= parent_slot {
+			return Err(Error::SlotNotAfterParent.into());
+		}
+		let author = self.slot_author(slot).ok_or(Error::NoAuthorities)?;
+		if !header.verify_seal(author) {
+			return Err(Error::BadSeal.into());
+		}
+		Ok(())
+	}
+
+	/// Replace the authorities.
+	///
+	/// This is not a dispatchable call, but can be used to set up the authorities at genesis.
+	pub fn initialize_authorities(&mut self, authorities: Vec<T::AccountId>) -> DispatchResult {
+		if authorities.is_empty() {
+			return Err(Error::NoAuthorities.into());
+		}
+		if authorities.len() > T::MAX_AUTHORITIES {
+			return Err(Error::TooManyAuthorities.into());
+		}
+		let mut sorted = authorities.clone();
+		sorted.sort();
+		sorted.dedup();
+		if sorted.len() != authorities.len() {
+			return Err(Error::DuplicateAuthority.into());
+		}
+		self.authorities.put(authorities);
+		Ok(())
+	}
+}
+
+/// The dispatchable functions of the Aura pallet.
+///
+/// These take the whole runtime, since only the root account in `system` can change the
+/// authorities.
+#[macros::call]
+impl<T: Config> Pallet<T>
+where
+	T: HasPallet<Self> + HasPallet<system::Pallet<T>>,
+{
+	/// Replace the authorities. This is a root call.
+	///
+	/// The new authorities author the blocks after the one which includes this call.
+	pub fn set_authorities(
+		runtime: &mut T,
+		caller: T::AccountId,
+		authorities: Vec<T::AccountId>,
+	) -> DispatchResult {
+		let system: &system::Pallet<T> = runtime.pallet();
+		system.ensure_root(&caller)?;
+		let aura: &mut Self = runtime.pallet_mut();
+		aura.initialize_authorities(authorities)
+	}
+}
+
+impl<T: Config> support::Hooks<T::BlockNumber> for Pallet<T> {}
+
+impl<T: Config> support::GetPalletMetadata for Pallet<T> {
+	fn error_metadata() -> Vec<support::VariantMetadata> {
+		<Error as support::GetErrorMetadata>::error_metadata()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		aura,
+		block_builder::{BlockBuilder, BlockLimits},
+		chain_store::{ChainStore, LongestChain},
+		support::{self, Dispatch, InherentData},
+		system,
+	};
+
+	mod types {
+		pub type Extrinsic = crate::support::Extrinsic<String, super::RuntimeCall, u32, u128>;
+		pub type Block = crate::support::Block<
+			crate::support::Header<u32>,
+			crate::support::UncheckedExtrinsic<Extrinsic, super::RuntimeCall>,
+		>;
+	}
+
+	// A minimal runtime to author and import blocks with.
+	#[macros::runtime]
+	#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+	struct Runtime {
+		system: system::Pallet<Self>,
+		aura: aura::Pallet<Self>,
+	}
+
+	impl system::Config for Runtime {
+		type AccountId = String;
+		type BlockNumber = u32;
+		type Nonce = u32;
+	}
+
+	impl aura::Config for Runtime {
+		const MAX_AUTHORITIES: usize = 3;
+	}
+
+	impl support::ChargeTransaction<types::Extrinsic> for Runtime {}
+
+	impl support::VerifyHeader for Runtime {
+		fn verify_header(
+			&self,
+			header: &support::Header<u32>,
+			_extrinsics: &[Self::Extrinsic],
+			parent_slot: support::Slot,
+		) -> support::DispatchResult {
+			self.aura.verify_header(header, parent_slot)
+		}
+	}
+
+	fn genesis() -> Runtime {
+		let mut runtime = Runtime::new();
+		runtime.system.set_root(Some("root".to_string()));
+		runtime
+			.aura
+			.initialize_authorities(vec!["alice".to_string(), "bob".to_string()])
+			.unwrap();
+		runtime
+	}
+
+	// Build a block on top of `parent` in `slot`, sealed by `author`.
+	fn build(
+		parent: &Runtime,
+		slot: support::Slot,
+		author: &str,
+		extrinsics: Vec<types::Extrinsic>,
+	) -> types::Block {
+		let limits = BlockLimits { max_weight: u64::MAX, max_size: usize::MAX };
+		let mut state = parent.clone();
+		let mut builder =
+			BlockBuilder::new(&mut state, slot, &InherentData::new(), limits).unwrap();
+		for extrinsic in extrinsics {
+			builder.push(extrinsic);
+		}
+		let mut block = builder.build();
+		block.header.seal(author);
+		block
+	}
+
+	fn set_authorities(caller: &str, nonce: u32, authorities: &[&str]) -> types::Extrinsic {
+		let authorities = authorities.iter().map(|authority| authority.to_string()).collect();
+		let call = RuntimeCall::aura(aura::Call::set_authorities { authorities });
+		support::Extrinsic::new_signed(caller.to_string(), call, nonce, 0)
+	}
+
+	// An error of the aura pallet, as the runtime returns it.
+	fn error(error: aura::Error) -> support::DispatchError {
+		support::DispatchError::from(error).in_pallet(1)
+	}
+
+	#[test]
+	fn authors_take_turns() {
+		let mut chain = ChainStore::new(genesis(), LongestChain, usize::MAX);
+		assert_eq!(chain.best_state().aura.slot_author(1), Some(&"bob".to_string()));
+		assert_eq!(chain.best_state().aura.slot_author(4), Some(&"alice".to_string()));
+
+		let wrong_author = build(chain.best_state(), 1, "alice", vec![]);
+		let err = Err("BadSeal");
+		assert_eq!(chain.import(wrong_author, &InherentData::new()), err);
+		let mut unsealed = build(chain.best_state(), 1, "bob", vec![]);
+		unsealed.header.digest.seal = None;
+		assert_eq!(chain.import(unsealed, &InherentData::new()), err);
+
+		let block = build(chain.best_state(), 1, "bob", vec![]);
+		assert!(chain.import(block, &InherentData::new()).is_ok());
+		let same_slot = build(chain.best_state(), 1, "bob", vec![]);
+		assert_eq!(chain.import(same_slot, &InherentData::new()), Err("SlotNotAfterParent"));
+		// Slots can be skipped when their author is offline.
+		let block = build(chain.best_state(), 3, "bob", vec![]);
+		assert!(chain.import(block, &InherentData::new()).is_ok());
+	}
+
+	#[test]
+	fn root_changes_authorities() {
+		let mut runtime = genesis();
+		let call = |authorities: &[&str]| {
+			let authorities = authorities.iter().map(|authority| authority.to_string()).collect();
+			RuntimeCall::aura(aura::Call::set_authorities { authorities })
+		};
+		let root = "root".to_string();
+		assert_eq!(
+			runtime.dispatch("alice".to_string(), call(&["alice"])),
+			Err(support::DispatchError::from(system::Error::NotRoot).in_pallet(0))
+		);
+		assert_eq!(
+			runtime.dispatch(root.clone(), call(&[])),
+			Err(error(aura::Error::NoAuthorities))
+		);
+		let err = Err(error(aura::Error::TooManyAuthorities));
+		assert_eq!(runtime.dispatch(root.clone(), call(&["a", "b", "c", "d"])), err);
+		assert_eq!(
+			runtime.dispatch(root.clone(), call(&["a", "b", "a"])),
+			Err(error(aura::Error::DuplicateAuthority))
+		);
+
+		// The new authorities author the blocks after the one which changes them.
+		let mut chain = ChainStore::new(runtime, LongestChain, usize::MAX);
+		let block =
+			build(chain.best_state(), 1, "bob", vec![set_authorities("root", 0, &["charlie"])]);
+		assert!(chain.import(block, &InherentData::new()).is_ok());
+		assert_eq!(chain.best_state().aura.authorities(), &["charlie".to_string()]);
+		let old_author = build(chain.best_state(), 2, "alice", vec![]);
+		let err = Err("BadSeal");
+		assert_eq!(chain.import(old_author, &InherentData::new()), err);
+		let block = build(chain.best_state(), 2, "charlie", vec![]);
+		assert!(chain.import(block, &InherentData::new()).is_ok());
+	}
+}
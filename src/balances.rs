@@ -1,32 +1,78 @@
-use crate::support::DispatchResult;
-use num::traits::{CheckedAdd, CheckedSub, Zero};
-use std::{collections::BTreeMap, marker::PhantomData};
+use crate::support::{
+	self, DispatchResult, GetStorageVersion, StorageDoubleMap, StorageMap, StorageValue,
+	StorageVersion,
+};
+use num::traits::{CheckedAdd, CheckedSub, Saturating, Zero};
 
 /// Configuration trait for the Balances pallet.
 /// Tightly coupled to the System pallet by inheriting its configuration.
-pub trait Config: crate::system::Config {
+///
+/// A runtime can include several instances of this pallet, such as a native token and a reward
+/// token, each with its own `Config`. `I` is the instance, which defaults to `()`.
+pub trait Config<I = ()>: crate::system::Config {
 	/// The type used to represent the balance of an account.
-	type Balance: Zero + CheckedAdd + CheckedSub + Copy;
+	type Balance: Zero + CheckedAdd + CheckedSub + Saturating + Copy + Ord;
 }
 
-/// The Balances pallet, for managing account balances.
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Pallet<T: Config> {
-	// A mapping from account IDs to their balances.
-	balances: BTreeMap<T::AccountId, T::Balance>,
-	// A marker for the generic type `T`.
-	_phantom: PhantomData<T>,
+/// An identifier for a lock on an account's funds, so that several pallets can lock funds
+/// independently.
+pub type LockIdentifier = [u8; 8];
+
+/// The events emitted by the Balances pallet.
+#[macros::event]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Event<T: Config<I>, I = ()> {
+	/// `amount` was transferred from `from` to `to`.
+	Transfer { from: T::AccountId, to: T::AccountId, amount: T::Balance },
+	/// `owner` allowed `spender` to transfer up to `amount` on their behalf.
+	Approval { owner: T::AccountId, spender: T::AccountId, amount: T::Balance },
 }
 
-impl<T: Config> Pallet<T> {
-	/// Constructs a new instance of this pallet.
-	pub fn new() -> Self {
-		Self { balances: BTreeMap::new(), _phantom: PhantomData }
-	}
+/// The errors returned by the Balances pallet.
+#[macros::error]
+pub enum Error {
+	/// The account does not have enough free funds.
+	InsufficientBalance,
+	/// The funds are locked, and cannot be moved below the locked balance of the account.
+	FundsLocked,
+	/// A balance overflowed.
+	Overflow,
+	/// The spender is not allowed to transfer this much on behalf of the owner.
+	InsufficientAllowance,
+	/// The account does not have enough reserved funds.
+	InsufficientReserved,
+}
+
+/// The Balances pallet, for managing account balances.
+#[macros::storage]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Pallet<T: Config<I>, I = ()> {
+	/// A mapping from account IDs to their balances.
+	balances: StorageMap<T::AccountId, T::Balance>,
+	/// A mapping from account IDs to the part of their funds which is reserved.
+	reserved: StorageMap<T::AccountId, T::Balance>,
+	/// A mapping from account IDs and lock identifiers to the locks on their funds.
+	locks: StorageDoubleMap<T::AccountId, LockIdentifier, T::Balance>,
+	/// A mapping from an owner and a spender to the amount the spender may transfer on behalf of
+	/// the owner.
+	allowances: StorageDoubleMap<T::AccountId, T::AccountId, T::Balance>,
+	/// The events emitted by this pallet, in the order they were emitted.
+	events: StorageValue<Vec<Event<T, I>>>,
+	/// The total of the free and reserved balances of all accounts. Added in storage version 1.
+	#[getter(total_issuance)]
+	#[default(T::Balance::zero())]
+	total_issuance: StorageValue<T::Balance>,
+	/// The version of the layout of this pallet's storage.
+	#[default(Self::STORAGE_VERSION)]
+	storage_version: StorageValue<StorageVersion>,
+}
 
+impl<T: Config<I>, I> Pallet<T, I> {
 	/// Set the balance of an account.
 	pub fn set_balance(&mut self, who: &T::AccountId, amount: T::Balance) {
-		self.balances.insert(who.clone(), amount);
+		let old = self.balances.insert(who.clone(), amount).unwrap_or(T::Balance::zero());
+		self.total_issuance
+			.mutate(|total| *total = total.saturating_sub(old).saturating_add(amount));
 	}
 
 	/// Get the balance of an account.
@@ -34,15 +80,155 @@ impl<T: Config> Pallet<T> {
 	pub fn balance(&self, who: &T::AccountId) -> T::Balance {
 		*self.balances.get(who).unwrap_or(&T::Balance::zero())
 	}
+
+	/// Get the reserved balance of an account.
+	/// Reserved funds are not part of `balance` and cannot be transferred.
+	pub fn reserved_balance(&self, who: &T::AccountId) -> T::Balance {
+		*self.reserved.get(who).unwrap_or(&T::Balance::zero())
+	}
+
+	/// Get the amount `spender` may transfer on behalf of `owner`.
+	/// Returns zero if there is no allowance.
+	pub fn allowance(&self, owner: &T::AccountId, spender: &T::AccountId) -> T::Balance {
+		*self.allowances.get(owner, spender).unwrap_or(&T::Balance::zero())
+	}
+
+	/// Get the locked balance of an account.
+	/// Locks overlap, so this is the largest of the account's locks. The free balance of an
+	/// account cannot be withdrawn below its locked balance.
+	pub fn locked_balance(&self, who: &T::AccountId) -> T::Balance {
+		self.locks
+			.iter_prefix(who)
+			.map(|(_, amount)| *amount)
+			.max()
+			.unwrap_or(T::Balance::zero())
+	}
+
+	/// Set the lock `id` on the funds of an account to `amount`, replacing any previous amount.
+	pub fn set_lock(&mut self, id: LockIdentifier, who: &T::AccountId, amount: T::Balance) {
+		self.locks.insert(who.clone(), id, amount);
+	}
+
+	/// Remove the lock `id` from the funds of an account.
+	pub fn remove_lock(&mut self, id: LockIdentifier, who: &T::AccountId) {
+		self.locks.remove(who, &id);
+	}
+
+	/// Move `amount` from the free balance of an account to its reserved balance.
+	pub fn reserve(&mut self, who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+		let new_balance =
+			self.balance(who).checked_sub(&amount).ok_or(Error::InsufficientBalance)?;
+		if new_balance < self.locked_balance(who) {
+			return Err(Error::FundsLocked.into());
+		}
+		let new_reserved =
+			self.reserved_balance(who).checked_add(&amount).ok_or(Error::Overflow)?;
+
+		self.balances.insert(who.clone(), new_balance);
+		self.reserved.insert(who.clone(), new_reserved);
+
+		Ok(())
+	}
+
+	/// Move `amount` from the reserved balance of an account back to its free balance.
+	pub fn unreserve(&mut self, who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+		let new_reserved = self
+			.reserved_balance(who)
+			.checked_sub(&amount)
+			.ok_or(Error::InsufficientReserved)?;
+		let new_balance = self.balance(who).checked_add(&amount).ok_or(Error::Overflow)?;
+
+		self.balances.insert(who.clone(), new_balance);
+		self.reserved.insert(who.clone(), new_reserved);
+
+		Ok(())
+	}
+
+	/// Remove `amount` from the free balance of an account, such as to pay a transaction fee.
+	pub fn withdraw(&mut self, who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+		let new_balance =
+			self.balance(who).checked_sub(&amount).ok_or(Error::InsufficientBalance)?;
+		if new_balance < self.locked_balance(who) {
+			return Err(Error::FundsLocked.into());
+		}
+		self.balances.insert(who.clone(), new_balance);
+		self.total_issuance.mutate(|total| *total = total.saturating_sub(amount));
+		Ok(())
+	}
+}
+
+impl<T: Config<I>, I> GetStorageVersion for Pallet<T, I> {
+	const STORAGE_VERSION: StorageVersion = 1;
+
+	fn on_chain_storage_version(&self) -> StorageVersion {
+		*self.storage_version.get()
+	}
+}
+
+/// The migrations of the storage of the Balances pallet.
+pub mod migrations {
+	use super::{Config, Pallet};
+	use crate::support::{GetStorageVersion, HasPallet, OnRuntimeUpgrade};
+	use num::traits::{Saturating, Zero};
+
+	/// Migrate from version 0 to version 1, which tracks the total issuance.
+	pub struct MigrateToV1;
+
+	impl MigrateToV1 {
+		// Add up the free and reserved balances of all accounts.
+		fn total_balance<T: Config>(balances: &Pallet<T>) -> T::Balance {
+			balances
+				.balances
+				.values()
+				.chain(balances.reserved.values())
+				.fold(T::Balance::zero(), |total, balance| total.saturating_add(*balance))
+		}
+	}
+
+	impl<T> OnRuntimeUpgrade<T> for MigrateToV1
+	where
+		T: Config + HasPallet<Pallet<T>>,
+	{
+		#[cfg(any(test, feature = "try-runtime"))]
+		type State = Option<T::Balance>;
+
+		fn on_runtime_upgrade(runtime: &mut T) {
+			let balances: &mut Pallet<T> = runtime.pallet_mut();
+			if balances.on_chain_storage_version() != 0 {
+				return;
+			}
+			balances.total_issuance.put(Self::total_balance(balances));
+			balances.storage_version.put(1);
+		}
+
+		#[cfg(any(test, feature = "try-runtime"))]
+		fn pre_upgrade(runtime: &T) -> Result<Option<T::Balance>, &'static str> {
+			let balances: &Pallet<T> = runtime.pallet();
+			if balances.on_chain_storage_version() != 0 {
+				return Ok(None);
+			}
+			Ok(Some(Self::total_balance(balances)))
+		}
+
+		#[cfg(any(test, feature = "try-runtime"))]
+		fn post_upgrade(runtime: &T, total: Option<T::Balance>) -> crate::support::DispatchResult {
+			let balances: &Pallet<T> = runtime.pallet();
+			if balances.on_chain_storage_version() != 1 {
+				return Err("balances storage is not at version 1".into());
+			}
+			if total.is_some_and(|total| balances.total_issuance() != total) {
+				return Err("total issuance does not match the balances of all accounts".into());
+			}
+			Ok(())
+		}
+	}
 }
 
 /// The dispatchable functions of the Balances pallet.
 #[macros::call]
-impl<T: Config> Pallet<T>
-where
-	T: Config,
-{
+impl<T: Config<I>, I> Pallet<T, I> {
 	/// Transfer `amount` from one account to another.
+	#[call_index(0)]
 	pub fn transfer(
 		&mut self,
 		caller: T::AccountId,
@@ -52,21 +238,104 @@ where
 		let caller_balance = self.balance(&caller);
 		let to_balance = self.balance(&to);
 
-		let new_caller_balance = caller_balance.checked_sub(&amount).ok_or("Not enough funds.")?;
-		let new_to_balance = to_balance.checked_add(&amount).ok_or("Overflow")?;
+		let new_caller_balance =
+			caller_balance.checked_sub(&amount).ok_or(Error::InsufficientBalance)?;
+		if new_caller_balance < self.locked_balance(&caller) {
+			return Err(Error::FundsLocked.into());
+		}
+		// A transfer to oneself moves nothing, rather than crediting the balance read before the
+		// debit.
+		if caller == to {
+			self.deposit_event(Event::Transfer { from: caller, to, amount });
+			return Ok(());
+		}
+		let new_to_balance = to_balance.checked_add(&amount).ok_or(Error::Overflow)?;
 
-		self.balances.insert(caller, new_caller_balance);
-		self.balances.insert(to, new_to_balance);
+		self.balances.insert(caller.clone(), new_caller_balance);
+		self.balances.insert(to.clone(), new_to_balance);
+		self.deposit_event(Event::Transfer { from: caller, to, amount });
 
 		Ok(())
 	}
+
+	/// Allow `spender` to transfer up to `amount` on behalf of the `caller`.
+	///
+	/// This replaces any previous allowance of the `spender`.
+	#[call_index(1)]
+	pub fn approve(
+		&mut self,
+		caller: T::AccountId,
+		spender: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		if amount.is_zero() {
+			self.allowances.remove(&caller, &spender);
+		} else {
+			self.allowances.insert(caller.clone(), spender.clone(), amount);
+		}
+		self.deposit_event(Event::Approval { owner: caller, spender, amount });
+
+		Ok(())
+	}
+
+	/// Transfer `amount` from `owner` to `to`, using an allowance given to the `caller` by
+	/// `owner`.
+	#[call_index(2)]
+	pub fn transfer_from(
+		&mut self,
+		caller: T::AccountId,
+		owner: T::AccountId,
+		to: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let new_allowance = self
+			.allowance(&owner, &caller)
+			.checked_sub(&amount)
+			.ok_or(Error::InsufficientAllowance)?;
+
+		self.transfer(owner.clone(), to, amount)?;
+
+		if new_allowance.is_zero() {
+			self.allowances.remove(&owner, &caller);
+		} else {
+			self.allowances.insert(owner, caller, new_allowance);
+		}
+
+		Ok(())
+	}
+}
+
+// Events are only kept for the block which emitted them.
+impl<T: Config<I>, I> support::Hooks<T::BlockNumber> for Pallet<T, I> {
+	fn on_initialize(&mut self, _block_number: T::BlockNumber) {
+		self.events.put(Vec::new());
+	}
+}
+
+impl<T: Config<I>, I> support::GetPalletMetadata for Pallet<T, I> {
+	fn event_metadata() -> Vec<support::VariantMetadata> {
+		<Event<T, I> as support::GetEventMetadata>::event_metadata()
+	}
+
+	fn error_metadata() -> Vec<support::VariantMetadata> {
+		<Error as support::GetErrorMetadata>::error_metadata()
+	}
 }
 
 #[cfg(test)]
 mod tests {
-	use crate::{balances, system};
+	use crate::{
+		balances,
+		block_builder::{BlockBuilder, BlockLimits, Inclusion},
+		support::{
+			self, Dispatch, GetRuntimeEvents, GetRuntimeMetadata, GetStorageVersion, InherentData,
+			KillStorage, OnRuntimeUpgrade, ReadStorage,
+		},
+		system,
+	};
 
 	// Mock struct for testing purposes.
+	#[derive(Debug, PartialEq)]
 	struct TestConfig;
 
 	// The System pallet's `Config` is a dependency for the Balances `Config`.
@@ -97,7 +366,7 @@ mod tests {
 
 		assert_eq!(
 			balances.transfer("alice".to_string(), "bob".to_string(), 51),
-			Err("Not enough funds.")
+			Err(balances::Error::InsufficientBalance.into())
 		);
 
 		balances.set_balance(&"alice".to_string(), 100);
@@ -107,7 +376,382 @@ mod tests {
 
 		assert_eq!(
 			balances.transfer("alice".to_string(), "bob".to_string(), 51),
-			Err("Not enough funds.")
+			Err(balances::Error::InsufficientBalance.into())
+		);
+	}
+
+	#[test]
+	fn reserve_balance() {
+		let mut balances = balances::Pallet::<TestConfig>::new();
+		let alice = "alice".to_string();
+
+		assert_eq!(balances.reserve(&alice, 1), Err(balances::Error::InsufficientBalance.into()));
+
+		balances.set_balance(&alice, 100);
+		assert_eq!(balances.reserve(&alice, 40), Ok(()));
+		assert_eq!(balances.balance(&alice), 60);
+		assert_eq!(balances.reserved_balance(&alice), 40);
+
+		// Reserved funds cannot be transferred.
+		assert_eq!(
+			balances.transfer(alice.clone(), "bob".to_string(), 61),
+			Err(balances::Error::InsufficientBalance.into())
 		);
+
+		assert_eq!(
+			balances.unreserve(&alice, 41),
+			Err(balances::Error::InsufficientReserved.into())
+		);
+		assert_eq!(balances.unreserve(&alice, 40), Ok(()));
+		assert_eq!(balances.balance(&alice), 100);
+		assert_eq!(balances.reserved_balance(&alice), 0);
+	}
+
+	#[test]
+	fn lock_balance() {
+		let mut balances = balances::Pallet::<TestConfig>::new();
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+
+		balances.set_balance(&alice, 100);
+		balances.set_lock(*b"first   ", &alice, 30);
+		balances.set_lock(*b"second  ", &alice, 60);
+		assert_eq!(balances.locked_balance(&alice), 60);
+
+		assert_eq!(
+			balances.transfer(alice.clone(), bob.clone(), 41),
+			Err(balances::Error::FundsLocked.into())
+		);
+		assert_eq!(balances.reserve(&alice, 41), Err(balances::Error::FundsLocked.into()));
+		assert_eq!(balances.withdraw(&alice, 41), Err(balances::Error::FundsLocked.into()));
+		assert_eq!(balances.transfer(alice.clone(), bob.clone(), 40), Ok(()));
+
+		balances.remove_lock(*b"second  ", &alice);
+		assert_eq!(balances.locked_balance(&alice), 30);
+		assert_eq!(balances.transfer(alice.clone(), bob.clone(), 30), Ok(()));
+		assert_eq!(
+			balances.transfer(alice.clone(), bob, 1),
+			Err(balances::Error::FundsLocked.into())
+		);
+
+		balances.remove_lock(*b"first   ", &alice);
+		assert_eq!(balances.locked_balance(&alice), 0);
+	}
+
+	#[test]
+	fn approve_and_transfer_from() {
+		let mut balances = balances::Pallet::<TestConfig>::new();
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let charlie = "charlie".to_string();
+
+		balances.set_balance(&alice, 100);
+		assert_eq!(
+			balances.transfer_from(bob.clone(), alice.clone(), charlie.clone(), 10),
+			Err(balances::Error::InsufficientAllowance.into())
+		);
+
+		assert_eq!(balances.approve(alice.clone(), bob.clone(), 60), Ok(()));
+		assert_eq!(balances.allowance(&alice, &bob), 60);
+		assert_eq!(balances.transfer_from(bob.clone(), alice.clone(), charlie.clone(), 40), Ok(()));
+		assert_eq!(balances.allowance(&alice, &bob), 20);
+		// The key of an allowance is the owner and the spender.
+		let key = r#"["alice", "bob"]"#;
+		assert_eq!(balances.read_storage_entry("allowances", key).unwrap(), "20");
+		assert_eq!(balances.read_storage_entry("allowances", r#""alice""#), None);
+		assert_eq!(balances.balance(&alice), 60);
+		assert_eq!(balances.balance(&charlie), 40);
+		assert_eq!(
+			balances.transfer_from(bob.clone(), alice.clone(), charlie.clone(), 21),
+			Err(balances::Error::InsufficientAllowance.into())
+		);
+
+		// The allowance is not used up when the transfer fails.
+		balances.set_balance(&alice, 10);
+		assert_eq!(
+			balances.transfer_from(bob.clone(), alice.clone(), charlie.clone(), 20),
+			Err(balances::Error::InsufficientBalance.into())
+		);
+		assert_eq!(balances.allowance(&alice, &bob), 20);
+
+		assert_eq!(
+			balances.events(),
+			&[
+				balances::Event::Approval { owner: alice.clone(), spender: bob, amount: 60 },
+				balances::Event::Transfer { from: alice, to: charlie, amount: 40 },
+			]
+		);
+	}
+
+	#[test]
+	fn transfer_to_self_keeps_issuance() {
+		let mut balances = balances::Pallet::<TestConfig>::new();
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		balances.set_balance(&alice, 100);
+
+		assert_eq!(balances.transfer(alice.clone(), alice.clone(), 30), Ok(()));
+		assert_eq!(
+			balances.transfer(alice.clone(), alice.clone(), 101),
+			Err(balances::Error::InsufficientBalance.into())
+		);
+		assert_eq!(balances.approve(alice.clone(), bob.clone(), 60), Ok(()));
+		assert_eq!(balances.transfer_from(bob.clone(), alice.clone(), alice.clone(), 40), Ok(()));
+
+		// The allowance is spent, but no funds are created.
+		assert_eq!(balances.allowance(&alice, &bob), 20);
+		assert_eq!(balances.balance(&alice), 100);
+		assert_eq!(balances.total_issuance(), 100);
+	}
+
+	mod types {
+		pub type Extrinsic = crate::support::Extrinsic<String, super::RuntimeCall, u32, u128>;
+		pub type Block = crate::support::Block<
+			crate::support::Header<u32>,
+			crate::support::UncheckedExtrinsic<Extrinsic, super::RuntimeCall>,
+		>;
+	}
+
+	// A minimal runtime which migrates the Balances pallet when it is upgraded, with a second
+	// instance of the pallet for a reward token.
+	#[macros::runtime]
+	#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+	struct Runtime {
+		system: system::Pallet<Self>,
+		#[pallet_index(5)]
+		balances: balances::Pallet<Self>,
+		#[pallet_index(6)]
+		#[event]
+		rewards: balances::Pallet<Self, support::Instance1>,
+	}
+
+	impl system::Config for Runtime {
+		type AccountId = String;
+		type BlockNumber = u32;
+		type Nonce = u32;
+
+		const VERSION: support::RuntimeVersion =
+			support::RuntimeVersion { spec_name: "test", spec_version: 1, transaction_version: 1 };
+
+		// The migrations are checked, as with the `try-runtime` feature.
+		fn on_runtime_upgrade(&mut self) -> support::DispatchResult {
+			balances::migrations::MigrateToV1::try_on_runtime_upgrade(self)
+		}
+	}
+
+	impl balances::Config for Runtime {
+		type Balance = u128;
+	}
+
+	impl balances::Config<support::Instance1> for Runtime {
+		type Balance = u64;
+	}
+
+	impl support::ChargeTransaction<types::Extrinsic> for Runtime {}
+
+	// The state of a runtime from before the total issuance was tracked.
+	fn v0_state() -> Runtime {
+		let mut runtime = Runtime::new();
+		runtime.balances.set_balance(&"alice".to_string(), 100);
+		runtime.balances.set_balance(&"bob".to_string(), 50);
+		runtime.balances.reserve(&"bob".to_string(), 20).unwrap();
+		runtime.balances.total_issuance.put(0);
+		runtime.balances.storage_version.put(0);
+		runtime
+	}
+
+	#[test]
+	fn migrate_to_v1_once() {
+		let limits = BlockLimits { max_weight: u64::MAX, max_size: usize::MAX };
+		let mut runtime = v0_state();
+		let mut author = runtime.clone();
+		let block =
+			BlockBuilder::new(&mut author, 0, &InherentData::new(), limits).unwrap().build();
+		assert_eq!(runtime.execute_block(block), Ok(()));
+		assert_eq!(runtime.system.last_runtime_upgrade(), Some(1));
+		assert_eq!(runtime.balances.on_chain_storage_version(), 1);
+		assert_eq!(runtime.balances.total_issuance(), 150);
+
+		// The migrations only run in the first block after an upgrade.
+		runtime.balances.storage_version.put(0);
+		let mut author = runtime.clone();
+		let block =
+			BlockBuilder::new(&mut author, 0, &InherentData::new(), limits).unwrap().build();
+		assert_eq!(runtime.execute_block(block), Ok(()));
+		assert_eq!(runtime.balances.on_chain_storage_version(), 0);
+	}
+
+	#[test]
+	fn try_migrate_to_v1() {
+		let mut runtime = v0_state();
+		assert_eq!(balances::migrations::MigrateToV1::try_on_runtime_upgrade(&mut runtime), Ok(()));
+		assert_eq!(runtime.balances.total_issuance(), 150);
+		// State which is already migrated is left as it is.
+		runtime.balances.set_balance(&"charlie".to_string(), 10);
+		assert_eq!(balances::migrations::MigrateToV1::try_on_runtime_upgrade(&mut runtime), Ok(()));
+		assert_eq!(runtime.balances.total_issuance(), 160);
+
+		let mut runtime = v0_state();
+		runtime.balances.storage_version.put(2);
+		assert_eq!(
+			balances::migrations::MigrateToV1::try_on_runtime_upgrade(&mut runtime),
+			Err("balances storage is not at version 1".into())
+		);
+	}
+
+	#[test]
+	fn failed_migration_checks_fail_set_code() {
+		let mut runtime = v0_state();
+		runtime.system.set_root(Some("root".to_string()));
+		runtime.balances.storage_version.put(2);
+		let call = RuntimeCall::system(system::Call::set_code { spec_version: 1 });
+		assert_eq!(
+			runtime.dispatch("root".to_string(), call),
+			Err("balances storage is not at version 1".into())
+		);
+		assert_eq!(runtime.system.last_runtime_upgrade(), None);
+	}
+
+	#[test]
+	fn call_metadata() {
+		let metadata = Runtime::metadata();
+		let pallet = &metadata.pallets[1];
+		assert_eq!(pallet.name, "balances");
+
+		let transfer = &pallet.calls[0];
+		assert_eq!(transfer.name, "transfer");
+		assert_eq!(transfer.docs, ["Transfer `amount` from one account to another."]);
+		let args = transfer.fields.iter().map(|arg| (arg.name, arg.ty)).collect::<Vec<_>>();
+		assert_eq!(args, [("to", "alloc::string::String"), ("amount", "u128")]);
+
+		let json = metadata.to_json();
+		assert!(json.contains("\"name\": \"transfer_from\""));
+		assert!(json.contains("\"type\": \"u128\""));
+	}
+
+	#[test]
+	fn event_and_error_metadata() {
+		let metadata = Runtime::metadata();
+		let pallet = &metadata.pallets[1];
+		let transfer = &pallet.events[0];
+		assert_eq!((transfer.index, transfer.name), (0, "Transfer"));
+		assert_eq!(transfer.docs, ["`amount` was transferred from `from` to `to`."]);
+		let insufficient_allowance = &pallet.errors[3];
+		assert_eq!(
+			(insufficient_allowance.index, insufficient_allowance.name),
+			(3, "InsufficientAllowance")
+		);
+		assert_eq!(
+			insufficient_allowance.docs,
+			["The spender is not allowed to transfer this much on behalf of the owner."]
+		);
+
+		// Errors are encoded with the indices of their pallet and of the error in the pallet, and
+		// each instance is its own pallet.
+		let mut runtime = Runtime::new();
+		let (alice, bob) = ("alice".to_string(), "bob".to_string());
+		let error = |index| {
+			Err(support::DispatchError::Module {
+				error: support::ModuleError { index, error: 3 },
+				message: "InsufficientAllowance",
+			})
+		};
+		let call = RuntimeCall::balances(balances::Call::transfer_from {
+			owner: bob.clone(),
+			to: bob.clone(),
+			amount: 1,
+		});
+		assert_eq!(runtime.dispatch(alice.clone(), call), error(5));
+		let call = RuntimeCall::rewards(balances::Call::transfer_from {
+			owner: bob.clone(),
+			to: bob,
+			amount: 1,
+		});
+		assert_eq!(runtime.dispatch(alice, call), error(6));
+	}
+
+	#[test]
+	fn storage_metadata() {
+		let metadata = Runtime::metadata();
+		let storage = &metadata.pallets[1].storage;
+		assert!(storage.iter().any(|item| item.name == "total_issuance" &&
+			item.ty == "rust_state_machine::support::StorageValue<u128>"));
+		let storage_version = storage.iter().find(|item| item.name == "storage_version");
+		assert_eq!(
+			storage_version.map(|item| item.docs.as_slice()),
+			Some(&["The version of the layout of this pallet's storage."][..])
+		);
+	}
+
+	#[test]
+	fn call_and_pallet_indices() {
+		let metadata = Runtime::metadata();
+		let pallet = &metadata.pallets[1];
+		assert_eq!((pallet.name, pallet.index), ("balances", 5));
+		let indices = pallet.calls.iter().map(|call| (call.index, call.name)).collect::<Vec<_>>();
+		assert_eq!(indices, [(0, "transfer"), (1, "approve"), (2, "transfer_from")]);
+
+		// Calls are encoded with the indices of their pallet and call, not their declaration order.
+		let call =
+			RuntimeCall::balances(balances::Call::transfer { to: "bob".to_string(), amount: 10 });
+		assert_eq!(support::hash(&call), support::hash(&(5u8, 0u8, "bob".to_string(), 10u128)));
+	}
+
+	#[test]
+	fn events_are_kept_for_one_block() {
+		let limits = BlockLimits { max_weight: u64::MAX, max_size: usize::MAX };
+		let mut runtime = Runtime::new();
+		let alice = "alice".to_string();
+		runtime.rewards.set_balance(&alice, 10);
+
+		let call =
+			RuntimeCall::rewards(balances::Call::transfer { to: "bob".to_string(), amount: 4 });
+		let mut author = runtime.clone();
+		let mut builder = BlockBuilder::new(&mut author, 0, &InherentData::new(), limits).unwrap();
+		let extrinsic = support::Extrinsic::new_signed(alice.clone(), call, 0, 0);
+		assert_eq!(builder.push(extrinsic), Inclusion::Included(Ok(())));
+		assert_eq!(runtime.execute_block(builder.build()), Ok(()));
+		let event = balances::Event::Transfer { from: alice, to: "bob".to_string(), amount: 4 };
+		assert_eq!(runtime.rewards.events(), &[event]);
+
+		// The next block starts with no events.
+		let mut author = runtime.clone();
+		let block =
+			BlockBuilder::new(&mut author, 0, &InherentData::new(), limits).unwrap().build();
+		assert_eq!(runtime.execute_block(block), Ok(()));
+		assert!(runtime.rewards.events().is_empty());
+	}
+
+	#[test]
+	fn instances() {
+		let mut runtime = Runtime::new();
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		runtime.balances.set_balance(&alice, 100);
+		runtime.rewards.set_balance(&alice, 10);
+
+		// Each instance has its own calls, state and events.
+		let call = RuntimeCall::rewards(balances::Call::transfer { to: bob.clone(), amount: 4 });
+		assert_eq!(runtime.dispatch(alice.clone(), call.clone()), Ok(()));
+		assert_eq!(runtime.rewards.balance(&bob), 4);
+		assert_eq!(runtime.rewards.total_issuance(), 10);
+		assert_eq!(runtime.balances.balance(&bob), 0);
+		assert_eq!(runtime.balances.total_issuance(), 100);
+		assert!(runtime.balances.events().is_empty());
+		let event = balances::Event::Transfer { from: alice.clone(), to: bob.clone(), amount: 4 };
+		assert_eq!(runtime.events(), [RuntimeEvent::rewards(event)]);
+		assert_eq!(call.to_string(), r#"rewards.transfer(to: "bob", amount: 4)"#);
+		let native = RuntimeCall::balances(balances::Call::transfer { to: bob.clone(), amount: 4 });
+		assert_eq!(native.to_string(), r#"balances.transfer(to: "bob", amount: 4)"#);
+
+		// Instances are told apart by the name and index of their field in the runtime.
+		let metadata = Runtime::metadata();
+		let pallet = &metadata.pallets[2];
+		assert_eq!((pallet.name, pallet.index), ("rewards", 6));
+		assert_eq!(pallet.calls[0].fields[1].ty, "u64");
+		assert_eq!(support::hash(&call), support::hash(&(6u8, 0u8, bob, 4u64)));
+		assert_eq!(runtime.kill_storage(6), Ok(()));
+		assert_eq!(runtime.rewards.total_issuance(), 0);
+		assert_eq!(runtime.balances.total_issuance(), 100);
 	}
 }
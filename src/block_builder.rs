@@ -0,0 +1,266 @@
+use crate::support::{
+	self, Block, BuildBlock, Digest, DispatchResult, GetWeight, Header, InherentData, Slot, Weight,
+};
+
+/// The limits on the work done in a block, and on its size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockLimits {
+	/// The maximum total weight of the calls in a block.
+	pub max_weight: Weight,
+	/// The maximum total encoded size of the extrinsics in a block.
+	pub max_size: usize,
+}
+
+/// The result of pushing an extrinsic to a `BlockBuilder`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Inclusion<Extrinsic> {
+	/// The extrinsic was included, and its call returned this result.
+	Included(DispatchResult),
+	/// The extrinsic is invalid and was left out, without changing the state.
+	Invalid(&'static str),
+	/// The block is full. The extrinsic is handed back, so it can go in a later block.
+	Exhausted(Extrinsic),
+}
+
+/// Builds a block on top of the current state of a runtime, one extrinsic at a time.
+///
+/// Extrinsics are applied to the runtime as they are pushed, so once the block is built the
+/// runtime is in the state after it. The builder goes through the same `BuildBlock` steps as
+/// `execute_block`, so executing the block on the state the builder started from gives the same
+/// header and state.
+pub struct BlockBuilder<'a, R: BuildBlock> {
+	runtime: &'a mut R,
+	header: Header<R::BlockNumber>,
+	extrinsics: Vec<R::Extrinsic>,
+	limits: BlockLimits,
+	weight: Weight,
+	size: usize,
+	// Set once an extrinsic does not fit, after which no more are included.
+	full: bool,
+}
+
+impl<'a, R> BlockBuilder<'a, R>
+where
+	R: BuildBlock,
+	R::Extrinsic: Clone + GetWeight + core::hash::Hash,
+{
+	/// Start a new block, authored in `slot`, on top of the current state of `runtime`.
+	///
+	/// The block starts with the inherents the runtime creates from the author's inherent `data`.
+	/// Inherents are always included, so they count towards the limits of the block, but are not
+	/// limited by them. Returns an error if an inherent is invalid, in which case `runtime` is left
+	/// part way through the block, so blocks should be built on a copy of the state they start
+	/// from.
+	pub fn new(
+		runtime: &'a mut R,
+		slot: Slot,
+		data: &InherentData,
+		limits: BlockLimits,
+	) -> Result<Self, &'static str> {
+		let header = runtime.initialize_block(Digest { slot, seal: None });
+		let (mut weight, mut size) = (0, 0);
+		let inherents = runtime.inherent_extrinsics(data);
+		for inherent in &inherents {
+			runtime.apply_extrinsic(inherent.clone())??;
+			weight = inherent.weight().saturating_add(weight);
+			size = support::encoded_size(inherent).saturating_add(size);
+		}
+		Ok(Self { runtime, header, extrinsics: inherents, limits, weight, size, full: false })
+	}
+
+	/// Apply `extrinsic` on top of the block so far, and include it if it is valid and fits.
+	///
+	/// Once an extrinsic does not fit, the block is full and no more are included, so that later
+	/// extrinsics of the same sender are not included ahead of it.
+	pub fn push(&mut self, extrinsic: impl Into<R::Extrinsic>) -> Inclusion<R::Extrinsic> {
+		let extrinsic = extrinsic.into();
+		let weight = self.weight.saturating_add(extrinsic.weight());
+		let size = self.size.saturating_add(support::encoded_size(&extrinsic));
+		if self.full || weight > self.limits.max_weight || size > self.limits.max_size {
+			self.full = true;
+			return Inclusion::Exhausted(extrinsic);
+		}
+
+		match self.runtime.apply_extrinsic(extrinsic.clone()) {
+			Ok(result) => {
+				self.weight = weight;
+				self.size = size;
+				self.extrinsics.push(extrinsic);
+				Inclusion::Included(result)
+			},
+			Err(e) => Inclusion::Invalid(e),
+		}
+	}
+
+	/// Finish the block, computing the roots of its header. The block is not sealed.
+	pub fn build(self) -> Block<Header<R::BlockNumber>, R::Extrinsic> {
+		let mut header = self.header;
+		header.extrinsics_root = support::hash(&self.extrinsics);
+		let header = self.runtime.finalize_block(header);
+		Block { header, extrinsics: self.extrinsics }
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{BlockBuilder, BlockLimits, Inclusion};
+	use crate::{
+		balances,
+		support::{self, InherentData},
+		system,
+	};
+
+	mod types {
+		pub type Extrinsic = crate::support::Extrinsic<String, super::RuntimeCall, u32, u128>;
+		pub type Block = crate::support::Block<
+			crate::support::Header<u32>,
+			crate::support::UncheckedExtrinsic<Extrinsic, super::RuntimeCall>,
+		>;
+	}
+
+	// A minimal runtime to build blocks on.
+	#[macros::runtime]
+	#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+	struct Runtime {
+		system: system::Pallet<Self>,
+		balances: balances::Pallet<Self>,
+	}
+
+	impl system::Config for Runtime {
+		type AccountId = String;
+		type BlockNumber = u32;
+		type Nonce = u32;
+	}
+
+	impl balances::Config for Runtime {
+		type Balance = u128;
+	}
+
+	impl support::ChargeTransaction<types::Extrinsic> for Runtime {}
+
+	const LIMITS: BlockLimits = BlockLimits { max_weight: u64::MAX, max_size: usize::MAX };
+
+	fn transfer(caller: &str, nonce: u32, amount: u128) -> types::Extrinsic {
+		let call =
+			RuntimeCall::balances(balances::Call::transfer { to: "bob".to_string(), amount });
+		support::Extrinsic::new_signed(caller.to_string(), call, nonce, 0)
+	}
+
+	fn genesis() -> Runtime {
+		let mut runtime = Runtime::new();
+		runtime.balances.set_balance(&"alice".to_string(), 100);
+		runtime
+	}
+
+	#[test]
+	fn built_block_executes_identically() {
+		let mut runtime = genesis();
+		let mut author = runtime.clone();
+		let mut builder = BlockBuilder::new(&mut author, 0, &InherentData::new(), LIMITS).unwrap();
+
+		assert!(matches!(builder.push(transfer("alice", 0, 30)), Inclusion::Included(Ok(()))));
+		assert_eq!(
+			builder.push(transfer("alice", 1, 500)),
+			Inclusion::Included(Err(support::DispatchError::Module {
+				error: support::ModuleError { index: 1, error: 0 },
+				message: "InsufficientBalance",
+			}))
+		);
+		assert!(matches!(
+			builder.push(transfer("alice", 3, 10)),
+			Inclusion::Invalid("nonce is ahead of the account nonce")
+		));
+		let mut forged = transfer("alice", 2, 10);
+		forged.caller = "charlie".to_string();
+		assert!(matches!(builder.push(forged), Inclusion::Invalid("invalid signature")));
+
+		let block = builder.build();
+		assert_eq!(block.header.block_number, 1);
+		let included = [transfer("alice", 0, 30), transfer("alice", 1, 500)];
+		assert_eq!(block.extrinsics, included.map(support::UncheckedExtrinsic::Signed));
+		assert_eq!(runtime.execute_block(block), Ok(()));
+		assert_eq!(runtime, author);
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 30);
+
+		// The next block is built on top of the one just executed.
+		let block =
+			BlockBuilder::new(&mut author, 0, &InherentData::new(), LIMITS).unwrap().build();
+		assert_eq!(block.header.parent_hash, runtime.system.parent_hash());
+		assert_eq!(runtime.execute_block(block), Ok(()));
+	}
+
+	#[test]
+	fn stops_at_limits() {
+		let mut runtime = genesis();
+		let limits = BlockLimits { max_weight: 2 * support::DEFAULT_CALL_WEIGHT, ..LIMITS };
+		let mut builder = BlockBuilder::new(&mut runtime, 0, &InherentData::new(), limits).unwrap();
+		assert!(matches!(builder.push(transfer("alice", 0, 1)), Inclusion::Included(Ok(()))));
+		assert!(matches!(builder.push(transfer("alice", 1, 1)), Inclusion::Included(Ok(()))));
+		// The extrinsic which does not fit is handed back as it was pushed.
+		let extrinsic = transfer("alice", 2, 1);
+		let exhausted =
+			Inclusion::Exhausted(support::UncheckedExtrinsic::Signed(extrinsic.clone()));
+		assert_eq!(builder.push(extrinsic), exhausted);
+		assert_eq!(builder.build().extrinsics.len(), 2);
+
+		let size = support::encoded_size(&transfer("alice", 2, 1));
+		let limits = BlockLimits { max_size: 2 * size, ..LIMITS };
+		let mut builder = BlockBuilder::new(&mut runtime, 0, &InherentData::new(), limits).unwrap();
+		assert!(matches!(builder.push(transfer("alice", 2, 1)), Inclusion::Included(Ok(()))));
+		let call =
+			balances::Call::transfer { to: "a much longer account name".to_string(), amount: 1 };
+		let large =
+			support::Extrinsic::new_signed("alice".to_string(), RuntimeCall::balances(call), 3, 0);
+		assert!(matches!(builder.push(large), Inclusion::Exhausted(_)));
+		// Once the block is full, nothing else is included, even if it would fit.
+		assert!(matches!(builder.push(transfer("alice", 3, 1)), Inclusion::Exhausted(_)));
+		assert_eq!(builder.build().extrinsics.len(), 1);
+	}
+
+	#[test]
+	fn rejects_tampered_blocks() {
+		let runtime = genesis();
+		let mut author = runtime.clone();
+		let mut builder = BlockBuilder::new(&mut author, 0, &InherentData::new(), LIMITS).unwrap();
+		builder.push(transfer("alice", 0, 30));
+		builder.push(transfer("alice", 1, 30));
+		let block = builder.build();
+
+		// Invalid blocks are rejected without touching the state, even once they are executed.
+		let mut rejected = runtime.clone();
+		let mut tampered =
+			types::Block { header: block.header.clone(), extrinsics: block.extrinsics.clone() };
+		tampered.extrinsics.pop();
+		assert_eq!(
+			rejected.execute_block(tampered),
+			Err("block roots do not match the result of executing it".into())
+		);
+		assert_eq!(rejected, runtime);
+
+		let mut replayed =
+			types::Block { header: block.header.clone(), extrinsics: block.extrinsics };
+		replayed
+			.extrinsics
+			.push(support::UncheckedExtrinsic::Signed(transfer("alice", 0, 30)));
+		assert_eq!(rejected.execute_block(replayed), Err("nonce is stale".into()));
+		assert_eq!(rejected, runtime);
+
+		let mut header = block.header.clone();
+		header.parent_hash = 1;
+		let orphan = types::Block { header, extrinsics: vec![] };
+		assert_eq!(
+			rejected.execute_block(orphan),
+			Err("parent hash does not match the last block".into())
+		);
+		assert_eq!(rejected, runtime);
+
+		let mut header = block.header;
+		header.block_number = 2;
+		let skipped = types::Block { header, extrinsics: vec![] };
+		assert_eq!(
+			rejected.execute_block(skipped),
+			Err("block number does not match what is expected".into())
+		);
+		assert_eq!(rejected, runtime);
+	}
+}
@@ -0,0 +1,115 @@
+use crate::{
+	proof_of_existence,
+	runtime::{types, Runtime},
+	vesting,
+};
+use serde::{Deserialize, Serialize};
+
+/// A vesting schedule of the runtime.
+pub type VestingInfo = vesting::VestingInfo<types::Balance, types::BlockNumber>;
+
+/// The description of a chain, from which every node of the chain builds the same genesis state.
+///
+/// Chain specs are stored as JSON, so that they can be written by hand and shared between nodes.
+/// Every field but the name can be left out, and starts empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChainSpec {
+	/// The name of the chain.
+	pub name: String,
+	/// The account holding the root key, if any.
+	pub root: Option<types::AccountId>,
+	/// The accounts which author blocks in turns.
+	pub authorities: Vec<types::AccountId>,
+	/// The balances of the native token which exist from the start.
+	pub balances: Vec<(types::AccountId, types::Balance)>,
+	/// The balances of the reward token which exist from the start.
+	pub rewards: Vec<(types::AccountId, types::Balance)>,
+	/// The claims which exist from the start, with their owners.
+	pub claims: Vec<(types::Content, types::AccountId)>,
+	/// The vesting schedules which exist from the start, locking part of the balances above.
+	pub vesting: Vec<(types::AccountId, VestingInfo)>,
+}
+
+impl ChainSpec {
+	/// The chain used for development, where Alice holds the root key, and authors blocks in turns
+	/// with Bob.
+	pub fn development() -> Self {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let dave = "dave".to_string();
+		Self {
+			name: "Development".to_string(),
+			root: Some(alice.clone()),
+			authorities: vec![alice.clone(), bob.clone()],
+			// Dave has a little more than his vesting schedule locks, to pay fees.
+			balances: vec![(alice.clone(), 100), (dave.clone(), 103)],
+			rewards: vec![(bob, 50)],
+			claims: vec![("Genesis claim".to_string(), alice)],
+			vesting: vec![(dave, VestingInfo { locked: 100, per_block: 20, starting_block: 2 })],
+		}
+	}
+
+	/// Build the genesis state of the chain.
+	/// Returns an error if the spec is invalid, such as when a claim is made twice.
+	pub fn build(&self) -> Result<Runtime, &'static str> {
+		let mut runtime = Runtime::new();
+		runtime.system.set_root(self.root.clone());
+		runtime.aura.initialize_authorities(self.authorities.clone())?;
+		for (who, amount) in &self.balances {
+			runtime.balances.set_balance(who, *amount);
+		}
+		for (who, amount) in &self.rewards {
+			runtime.rewards.set_balance(who, *amount);
+		}
+		runtime.proof_of_existence =
+			proof_of_existence::Pallet::from_genesis(&proof_of_existence::GenesisConfig {
+				claims: self.claims.clone(),
+				..Default::default()
+			})?;
+		for (who, schedule) in &self.vesting {
+			vesting::Pallet::add_vesting_schedule(&mut runtime, who, *schedule)?;
+		}
+		Ok(runtime)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::ChainSpec;
+
+	#[test]
+	fn development() {
+		let spec = ChainSpec::development();
+		let runtime = spec.build().unwrap();
+		assert_eq!(runtime.system.root(), Some(&"alice".to_string()));
+		assert_eq!(runtime.aura.authorities(), ["alice".to_string(), "bob".to_string()]);
+		assert_eq!(runtime.balances.balance(&"dave".to_string()), 103);
+		assert_eq!(runtime.rewards.balance(&"bob".to_string()), 50);
+		assert_eq!(
+			runtime.proof_of_existence.get_claim(&"Genesis claim".to_string()),
+			Some(&"alice".to_string())
+		);
+
+		// A spec survives being written as JSON and read back.
+		let json = serde_json::to_string(&spec).unwrap();
+		assert_eq!(serde_json::from_str::<ChainSpec>(&json).unwrap(), spec);
+	}
+
+	#[test]
+	fn partial_spec() {
+		let spec = serde_json::from_str::<ChainSpec>(
+			r#"{ "name": "Local", "authorities": ["alice"], "balances": [["alice", 10]] }"#,
+		)
+		.unwrap();
+		assert_eq!(spec.root, None);
+		let runtime = spec.build().unwrap();
+		assert_eq!(runtime.balances.balance(&"alice".to_string()), 10);
+		assert_eq!(runtime.balances.total_issuance(), 10);
+
+		// The same claim cannot be made twice at genesis.
+		let claim = ("data".to_string(), "alice".to_string());
+		let spec = ChainSpec { claims: vec![claim.clone(), claim], ..spec };
+		assert!(spec.build().is_err());
+	}
+}
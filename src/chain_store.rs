@@ -0,0 +1,409 @@
+use crate::support::{self, Block, ExecuteBlock, Hash, Header, InherentData, VerifyHeader};
+use std::collections::BTreeMap;
+
+/// A rule for choosing the best chain among the forks of the block tree.
+pub trait ForkChoice<BlockNumber> {
+	/// Returns true if the chain ending at `candidate` should replace the best chain, which ends
+	/// at `best`.
+	fn prefer(&self, candidate: &Header<BlockNumber>, best: &Header<BlockNumber>) -> bool;
+}
+
+/// A fork choice rule which prefers the longest chain. Between chains of equal length, the one
+/// imported first stays the best.
+pub struct LongestChain;
+
+impl<BlockNumber: PartialOrd> ForkChoice<BlockNumber> for LongestChain {
+	fn prefer(&self, candidate: &Header<BlockNumber>, best: &Header<BlockNumber>) -> bool {
+		candidate.block_number > best.block_number
+	}
+}
+
+/// The outcome of importing a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportOutcome {
+	/// The block is the new best block. The blocks of the old best chain which are no longer part
+	/// of the best chain were `retracted`, newest first, and the blocks of the new best chain
+	/// which were not part of the old one were `enacted`, oldest first.
+	NewBest { retracted: Vec<Hash>, enacted: Vec<Hash> },
+	/// The block was added to a fork which is not the best chain.
+	SideFork,
+}
+
+/// A store of every imported block, kept as a tree of forks keyed by block hash.
+///
+/// The state after each block is kept as a snapshot of the runtime, so blocks can be imported on
+/// top of any known block, and switching to another fork needs no changes to be reverted.
+///
+/// Blocks become final once they are `finality_depth` blocks behind the best block, or when they
+/// are finalized explicitly. Final blocks are never reverted, so the forks which do not build on
+/// the finalized block are pruned, and only the state of the finalized block and its descendants
+/// is kept.
+pub struct ChainStore<R: ExecuteBlock + VerifyHeader, F> {
+	// The imported blocks, by hash.
+	blocks: BTreeMap<Hash, Block<Header<R::BlockNumber>, R::Extrinsic>>,
+	// The state after each block, by block hash. The genesis state is kept under the genesis hash.
+	states: BTreeMap<Hash, R>,
+	// The hash of the last block of the best chain.
+	best_hash: Hash,
+	// The hash of the last final block.
+	finalized_hash: Hash,
+	// How many blocks behind the best block a block of the best chain becomes final.
+	finality_depth: usize,
+	// The rule used to choose the best chain.
+	fork_choice: F,
+}
+
+impl<R, F> ChainStore<R, F>
+where
+	R: ExecuteBlock + VerifyHeader + Clone,
+	R::BlockNumber: Clone + core::hash::Hash,
+	R::Extrinsic: Clone,
+	F: ForkChoice<R::BlockNumber>,
+{
+	/// Start a chain from the `genesis` state, which the first block is built on. The genesis
+	/// state is final.
+	pub fn new(genesis: R, fork_choice: F, finality_depth: usize) -> Self {
+		Self {
+			blocks: BTreeMap::new(),
+			states: BTreeMap::from([(support::GENESIS_HASH, genesis)]),
+			best_hash: support::GENESIS_HASH,
+			finalized_hash: support::GENESIS_HASH,
+			finality_depth,
+			fork_choice,
+		}
+	}
+
+	/// Get the hash of the last block of the best chain.
+	pub fn best_hash(&self) -> Hash {
+		self.best_hash
+	}
+
+	/// Get the state after the last block of the best chain.
+	pub fn best_state(&self) -> &R {
+		&self.states[&self.best_hash]
+	}
+
+	/// Get the hash of the last final block, or the genesis hash if no block is final yet.
+	pub fn finalized_head(&self) -> Hash {
+		self.finalized_hash
+	}
+
+	/// Get an imported block. Blocks of pruned forks are no longer kept.
+	pub fn block(&self, hash: &Hash) -> Option<&Block<Header<R::BlockNumber>, R::Extrinsic>> {
+		self.blocks.get(hash)
+	}
+
+	/// Get the blocks of the best chain, oldest first, starting with the first block after the
+	/// genesis state.
+	pub fn best_chain(&self) -> Vec<&Block<Header<R::BlockNumber>, R::Extrinsic>> {
+		let mut chain = self
+			.ancestry(self.best_hash)
+			.iter()
+			.filter_map(|hash| self.block(hash))
+			.collect::<Vec<_>>();
+		chain.reverse();
+		chain
+	}
+
+	/// Get the state after an imported block, or the genesis state. Only the states of the
+	/// finalized block and its descendants are kept.
+	pub fn state(&self, hash: &Hash) -> Option<&R> {
+		self.states.get(hash)
+	}
+
+	/// Verify `block` and execute it on top of the state after its parent, and add it to the tree.
+	/// Its inherents are checked against the inherent `data` of this node.
+	///
+	/// The block becomes the best block if the fork choice rule prefers it over the current best
+	/// block, in which case the block `finality_depth` blocks behind it is finalized. The parent
+	/// must already be imported, and be the finalized block or one of its descendants.
+	pub fn import(
+		&mut self,
+		block: Block<Header<R::BlockNumber>, R::Extrinsic>,
+		data: &InherentData,
+	) -> Result<ImportOutcome, &'static str> {
+		let hash = block.header.hash();
+		let parent_hash = block.header.parent_hash;
+		if self.blocks.contains_key(&hash) {
+			return Err("block is already imported");
+		}
+		if !self.states.contains_key(&parent_hash) {
+			if self.blocks.contains_key(&parent_hash) || parent_hash == support::GENESIS_HASH {
+				return Err("block does not build on the finalized block");
+			}
+			return Err("parent block is unknown");
+		}
+		let parent = &self.states[&parent_hash];
+		let parent_slot =
+			self.blocks.get(&parent_hash).map_or(0, |parent| parent.header.digest.slot);
+		parent.verify_header(&block.header, &block.extrinsics, parent_slot)?;
+		parent.check_inherents(&block, data)?;
+		let mut state = parent.clone();
+		state.execute_block(block.clone())?;
+
+		let is_best = match self.blocks.get(&self.best_hash) {
+			Some(best) => self.fork_choice.prefer(&block.header, &best.header),
+			None => true,
+		};
+		self.blocks.insert(hash, block);
+		self.states.insert(hash, state);
+		if !is_best {
+			return Ok(ImportOutcome::SideFork);
+		}
+
+		let (retracted, enacted) = self.tree_route(self.best_hash, hash);
+		self.best_hash = hash;
+		if let Some(final_hash) = self.ancestry(hash).get(self.finality_depth) {
+			self.finalize(*final_hash)?;
+		}
+		Ok(ImportOutcome::NewBest { retracted, enacted })
+	}
+
+	/// Mark the block `hash` of the best chain, and all its ancestors, as final.
+	///
+	/// The blocks which are neither ancestors nor descendants of the finalized block are pruned
+	/// along with their states, as are the states of its ancestors, which can no longer be built
+	/// on. Returns the hashes of the pruned blocks.
+	pub fn finalize(&mut self, hash: Hash) -> Result<Vec<Hash>, &'static str> {
+		if self.ancestry(self.finalized_hash).contains(&hash) {
+			return Ok(Vec::new());
+		}
+		let canonical = self.ancestry(hash);
+		if !self.ancestry(self.best_hash).contains(&hash) {
+			return Err("block is not in the best chain");
+		}
+
+		let pruned = self
+			.blocks
+			.keys()
+			.filter(|block| !canonical.contains(block) && !self.ancestry(**block).contains(&hash))
+			.copied()
+			.collect::<Vec<_>>();
+		for block in &pruned {
+			self.blocks.remove(block);
+			self.states.remove(block);
+		}
+		for ancestor in &canonical[1..] {
+			self.states.remove(ancestor);
+		}
+		self.finalized_hash = hash;
+		Ok(pruned)
+	}
+
+	/// Get the hashes of `hash` and its ancestors, newest first, ending at the genesis hash.
+	fn ancestry(&self, mut hash: Hash) -> Vec<Hash> {
+		let mut ancestry = vec![hash];
+		while let Some(block) = self.blocks.get(&hash) {
+			hash = block.header.parent_hash;
+			ancestry.push(hash);
+		}
+		ancestry
+	}
+
+	/// Get the blocks to retract and enact to move from the chain ending at `from` to the chain
+	/// ending at `to`.
+	fn tree_route(&self, from: Hash, to: Hash) -> (Vec<Hash>, Vec<Hash>) {
+		let from_ancestry = self.ancestry(from);
+		let to_ancestry = self.ancestry(to);
+		let common = to_ancestry.iter().find(|hash| from_ancestry.contains(hash)).copied();
+		let before_common = |ancestry: Vec<Hash>| {
+			ancestry
+				.into_iter()
+				.take_while(|hash| Some(*hash) != common)
+				.collect::<Vec<_>>()
+		};
+
+		let retracted = before_common(from_ancestry);
+		let mut enacted = before_common(to_ancestry);
+		enacted.reverse();
+		(retracted, enacted)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{ChainStore, ForkChoice, ImportOutcome, LongestChain};
+	use crate::{
+		balances,
+		block_builder::{BlockBuilder, BlockLimits},
+		support::{self, InherentData},
+		system,
+	};
+
+	mod types {
+		pub type Extrinsic = crate::support::Extrinsic<String, super::RuntimeCall, u32, u128>;
+		pub type Block = crate::support::Block<
+			crate::support::Header<u32>,
+			crate::support::UncheckedExtrinsic<Extrinsic, super::RuntimeCall>,
+		>;
+	}
+
+	// A minimal runtime to import blocks into.
+	#[macros::runtime]
+	#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+	struct Runtime {
+		system: system::Pallet<Self>,
+		balances: balances::Pallet<Self>,
+	}
+
+	impl system::Config for Runtime {
+		type AccountId = String;
+		type BlockNumber = u32;
+		type Nonce = u32;
+	}
+
+	impl balances::Config for Runtime {
+		type Balance = u128;
+	}
+
+	impl support::ChargeTransaction<types::Extrinsic> for Runtime {}
+
+	impl support::VerifyHeader for Runtime {}
+
+	fn genesis() -> Runtime {
+		let mut runtime = Runtime::new();
+		runtime.balances.set_balance(&"alice".to_string(), 100);
+		runtime
+	}
+
+	// Build a block on top of `parent` in which Alice transfers `amount` to Bob.
+	fn build(parent: &Runtime, amount: u128) -> types::Block {
+		let limits = BlockLimits { max_weight: u64::MAX, max_size: usize::MAX };
+		let mut state = parent.clone();
+		let mut builder = BlockBuilder::new(&mut state, 0, &InherentData::new(), limits).unwrap();
+		let nonce = parent.system.nonce(&"alice".to_string());
+		let call =
+			RuntimeCall::balances(balances::Call::transfer { to: "bob".to_string(), amount });
+		builder.push(support::Extrinsic::new_signed("alice".to_string(), call, nonce, 0));
+		builder.build()
+	}
+
+	fn bob_balance<F: ForkChoice<u32>>(chain: &ChainStore<Runtime, F>) -> u128 {
+		chain.best_state().balances.balance(&"bob".to_string())
+	}
+
+	#[test]
+	fn reorgs_to_longest_chain() {
+		let mut chain = ChainStore::new(genesis(), LongestChain, usize::MAX);
+
+		let a1 = build(chain.best_state(), 10);
+		let b1 = build(chain.best_state(), 20);
+		let (a1_hash, b1_hash) = (a1.header.hash(), b1.header.hash());
+		let enacted = vec![a1_hash];
+		assert_eq!(
+			chain.import(a1, &InherentData::new()),
+			Ok(ImportOutcome::NewBest { retracted: vec![], enacted })
+		);
+		assert_eq!(chain.import(b1, &InherentData::new()), Ok(ImportOutcome::SideFork));
+		assert_eq!(chain.best_hash(), a1_hash);
+		assert_eq!(bob_balance(&chain), 10);
+
+		// Extending the side fork makes it the longest chain, and its state the best state.
+		let b2 = build(chain.state(&b1_hash).unwrap(), 5);
+		let b2_hash = b2.header.hash();
+		assert_eq!(
+			chain.import(b2, &InherentData::new()),
+			Ok(ImportOutcome::NewBest {
+				retracted: vec![a1_hash],
+				enacted: vec![b1_hash, b2_hash]
+			})
+		);
+		assert_eq!(bob_balance(&chain), 25);
+		assert_eq!(chain.best_state().system.block_number(), 2);
+		assert!(chain.block(&a1_hash).is_some());
+	}
+
+	#[test]
+	fn rejects_unknown_and_invalid_blocks() {
+		let mut chain = ChainStore::new(genesis(), LongestChain, usize::MAX);
+		let block = build(chain.best_state(), 10);
+
+		let mut orphan = block.clone();
+		orphan.header.parent_hash = 1;
+		assert_eq!(chain.import(orphan, &InherentData::new()), Err("parent block is unknown"));
+
+		let mut tampered = block.clone();
+		tampered.header.state_root = 1;
+		assert_eq!(
+			chain.import(tampered, &InherentData::new()),
+			Err("block roots do not match the result of executing it")
+		);
+		assert_eq!(chain.best_hash(), support::GENESIS_HASH);
+
+		assert!(chain.import(block.clone(), &InherentData::new()).is_ok());
+		assert_eq!(chain.import(block, &InherentData::new()), Err("block is already imported"));
+	}
+
+	// A fork choice rule which never leaves the first chain it follows.
+	struct FirstChain;
+
+	impl ForkChoice<u32> for FirstChain {
+		fn prefer(&self, candidate: &support::Header<u32>, best: &support::Header<u32>) -> bool {
+			candidate.parent_hash == best.hash()
+		}
+	}
+
+	#[test]
+	fn fork_choice_is_pluggable() {
+		let mut chain = ChainStore::new(genesis(), FirstChain, usize::MAX);
+		let a1 = build(chain.best_state(), 10);
+		let b1 = build(chain.best_state(), 20);
+		let b1_hash = b1.header.hash();
+		assert!(chain.import(a1, &InherentData::new()).is_ok());
+		assert!(chain.import(b1, &InherentData::new()).is_ok());
+
+		let b2 = build(chain.state(&b1_hash).unwrap(), 5);
+		assert_eq!(chain.import(b2, &InherentData::new()), Ok(ImportOutcome::SideFork));
+		assert_eq!(bob_balance(&chain), 10);
+	}
+
+	#[test]
+	fn finalizes_at_depth_and_prunes_forks() {
+		let mut chain = ChainStore::new(genesis(), LongestChain, 1);
+		let a1 = build(chain.best_state(), 10);
+		let b1 = build(chain.best_state(), 20);
+		let (a1_hash, b1_hash) = (a1.header.hash(), b1.header.hash());
+		chain.import(a1, &InherentData::new()).unwrap();
+		chain.import(b1, &InherentData::new()).unwrap();
+		let b2 = build(chain.state(&b1_hash).unwrap(), 5);
+		assert_eq!(chain.finalized_head(), support::GENESIS_HASH);
+
+		// Building on top of `a1` finalizes it, which prunes the fork of `b1`.
+		let a2 = build(chain.best_state(), 1);
+		chain.import(a2, &InherentData::new()).unwrap();
+		assert_eq!(chain.finalized_head(), a1_hash);
+		assert!(chain.block(&b1_hash).is_none());
+		assert!(chain.state(&b1_hash).is_none());
+		assert!(chain.state(&support::GENESIS_HASH).is_none());
+		assert!(chain.block(&a1_hash).is_some());
+		assert_eq!(chain.import(b2, &InherentData::new()), Err("parent block is unknown"));
+
+		// A fork below the finalized block is rejected, even though the chain would be longer.
+		let below = build(&genesis(), 30);
+		assert_eq!(
+			chain.import(below, &InherentData::new()),
+			Err("block does not build on the finalized block")
+		);
+		assert_eq!(bob_balance(&chain), 11);
+	}
+
+	#[test]
+	fn finalizes_explicitly() {
+		let mut chain = ChainStore::new(genesis(), LongestChain, usize::MAX);
+		let a1 = build(chain.best_state(), 10);
+		let b1 = build(chain.best_state(), 20);
+		let (a1_hash, b1_hash) = (a1.header.hash(), b1.header.hash());
+		chain.import(a1, &InherentData::new()).unwrap();
+		chain.import(b1, &InherentData::new()).unwrap();
+		let a2 = build(chain.best_state(), 1);
+		let a2_hash = a2.header.hash();
+		chain.import(a2, &InherentData::new()).unwrap();
+
+		assert_eq!(chain.finalize(b1_hash), Err("block is not in the best chain"));
+		assert_eq!(chain.finalize(a2_hash), Ok(vec![b1_hash]));
+		assert_eq!(chain.finalized_head(), a2_hash);
+		// Finalizing an ancestor of the finalized block changes nothing.
+		assert_eq!(chain.finalize(a1_hash), Ok(vec![]));
+		assert_eq!(chain.finalized_head(), a2_hash);
+	}
+}
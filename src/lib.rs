@@ -0,0 +1,25 @@
+//! A simple blockchain runtime, built from pallets such as balances and proof of existence.
+//!
+//! This library provides the `support` types and traits, the pallets, the tools a node uses to
+//! build, import and pool blocks, the `runtime` of this node, and the `node` itself, which keeps
+//! its chain in a database on disk. Other crates can build their own runtimes from the pallets
+//! with `macros::runtime`, giving it the paths of this crate, such as `#[macros::runtime(system =
+//! rust_state_machine::system, support = rust_state_machine::support)]`.
+pub mod assets;
+pub mod aura;
+pub mod balances;
+pub mod block_builder;
+pub mod chain_spec;
+pub mod chain_store;
+pub mod multisig;
+pub mod node;
+pub mod proof_of_existence;
+pub mod proxy;
+pub mod runtime;
+pub mod support;
+pub mod system;
+pub mod timestamp;
+pub mod transaction_pool;
+pub mod vesting;
+
+pub use macros;
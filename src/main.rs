@@ -1,140 +1,183 @@
 /// Author: Huzefa Ehsan
 /// Project: Rust State Machine Exercise by Shawn Tabrizi (dotcodeschool.com)
 /// Date: 2025-06-28
-/// Description: A simple runtime simulation in Rust that demonstrates a proof of existence
-/// system with basic account balances and block execution.
-mod balances;
-mod proof_of_existence;
-mod support;
-mod system;
-
-// Import the `Dispatch` trait to satisfy the trait bounds of the macros.
-use support::Dispatch;
-
-/// Concrete types used throughout the runtime.
-mod types {
-	pub type AccountId = String;
-	pub type Balance = u128;
-	pub type BlockNumber = u32;
-	pub type Nonce = u32;
-	pub type Content = &'static str;
-
-	pub type Extrinsic = crate::support::Extrinsic<AccountId, crate::RuntimeCall>;
-	pub type Header = crate::support::Header<BlockNumber>;
-	pub type Block = crate::support::Block<Header, Extrinsic>;
+/// Description: A command line node for the runtime of the `rust_state_machine` library, which
+/// keeps its chain in a database on disk, and submits transactions, produces blocks, imports
+/// and exports blocks, and reads the state of the chain.
+use clap::{Parser, Subcommand};
+use rust_state_machine::{
+	chain_spec::ChainSpec,
+	node::{self, Node},
+	runtime::{types, RuntimeCall},
+	support,
+};
+use std::{path::PathBuf, process::ExitCode};
+
+/// A node of a simple blockchain, keeping its chain in a database on disk.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+	/// The directory of the database.
+	#[arg(long, global = true, default_value = "chain-data")]
+	base_path: PathBuf,
+	#[command(subcommand)]
+	command: Command,
 }
 
-/// The main runtime struct.
-///
-/// The `#[macros::runtime]` attribute automatically generates the `RuntimeCall` enum,
-/// the `new()` and `execute_block()` functions, and the `Dispatch` trait implementation.
-#[macros::runtime]
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Runtime {
-	system: system::Pallet<Self>,
-	balances: balances::Pallet<Self>,
-	proof_of_existence: proof_of_existence::Pallet<Self>,
+#[derive(Subcommand)]
+enum Command {
+	/// Create a new chain from a chain spec.
+	Init {
+		/// The JSON file of the chain spec. The development chain is used if none is given.
+		#[arg(long)]
+		chain_spec: Option<PathBuf>,
+	},
+	/// Encode an extrinsic, and submit it to the pool of transactions.
+	Submit {
+		/// The account which signs the extrinsic.
+		#[arg(long)]
+		signer: types::AccountId,
+		/// The nonce of the extrinsic. Defaults to the nonce after the transactions of the signer
+		/// in the pool.
+		#[arg(long)]
+		nonce: Option<types::Nonce>,
+		/// The tip paid on top of the base fee, to prioritise the extrinsic.
+		#[arg(long, default_value_t = 0)]
+		tip: types::Balance,
+		/// The call as JSON, such as `{"balances": {"transfer": {"to": "bob", "amount": 10}}}`.
+		call: String,
+	},
+	/// Build a block of the ready transactions of the pool on top of the best block, and import
+	/// it.
+	ProduceBlock {
+		/// The slot of the block. Defaults to the slot after the best block.
+		#[arg(long)]
+		slot: Option<support::Slot>,
+	},
+	/// Import blocks from a JSON file, as written by `export-blocks`.
+	ImportBlocks {
+		/// The JSON file of the blocks.
+		file: PathBuf,
+	},
+	/// Write the blocks of the best chain as JSON.
+	ExportBlocks {
+		/// The file to write the blocks to. Defaults to the standard output.
+		#[arg(long)]
+		output: Option<PathBuf>,
+	},
+	/// Read the state of the best block.
+	#[command(subcommand)]
+	State(StateCommand),
+	/// Remove the database, along with the chain and transactions it holds.
+	PurgeChain,
 }
 
-/// Implements the `system::Config` trait for the `Runtime`.
-impl system::Config for Runtime {
-	type AccountId = types::AccountId;
-	type BlockNumber = types::BlockNumber;
-	type Nonce = types::Nonce;
+#[derive(Subcommand)]
+enum StateCommand {
+	/// Print a storage item of a pallet, such as `state get balances balances`, or only the entry
+	/// of a map at a key, such as `state get balances balances '"alice"'`.
+	Get {
+		/// The name of the pallet in the runtime.
+		pallet: String,
+		/// The name of the storage item.
+		key: String,
+		/// The key of the entry to print, as JSON, if the storage item is a map. The key of a
+		/// double map is an array of its two keys.
+		map_key: Option<String>,
+	},
 }
 
-/// Implements the `balances::Config` trait for the `Runtime`.
-impl balances::Config for Runtime {
-	type Balance = types::Balance;
+/// Run a command against the database at `base_path`.
+fn run(base_path: PathBuf, command: Command) -> Result<(), String> {
+	match command {
+		Command::Init { chain_spec } => {
+			let spec = match chain_spec {
+				Some(file) => node::read_json(&file)?,
+				None => ChainSpec::development(),
+			};
+			Node::init(&base_path, spec)?;
+			println!("Initialized the chain at {}", base_path.display());
+		},
+		Command::Submit { signer, nonce, tip, call } => {
+			let mut node = Node::open(&base_path)?;
+			let call = serde_json::from_str::<RuntimeCall>(&call)
+				.map_err(|e| format!("invalid call: {}", e))?;
+			let nonce = nonce.unwrap_or_else(|| next_nonce(&node, &signer));
+			let extrinsic = support::Extrinsic::new_signed(signer, call, nonce, tip);
+			let encoded = serde_json::to_string(&extrinsic).expect("extrinsics are serializable");
+			let hash = node.submit(extrinsic)?;
+			println!("{}", encoded);
+			println!("Submitted extrinsic {:#x}", hash);
+		},
+		Command::ProduceBlock { slot } => {
+			let mut node = Node::open(&base_path)?;
+			let block = node.produce_block(slot)?;
+			println!(
+				"Imported block #{} ({:#x}) in slot {}, with {} extrinsics",
+				block.header.block_number,
+				block.header.hash(),
+				block.header.digest.slot,
+				block.extrinsics.len(),
+			);
+		},
+		Command::ImportBlocks { file } => {
+			let mut node = Node::open(&base_path)?;
+			let imported = node.import_blocks(node::read_json(&file)?)?;
+			let best = node.chain().best_state().system.block_number();
+			println!("Imported {} blocks, the best block is #{}", imported, best);
+		},
+		Command::ExportBlocks { output } => {
+			let node = Node::open(&base_path)?;
+			let blocks = node.export_blocks();
+			match output {
+				Some(file) => node::write_json(&file, &blocks)?,
+				None => println!(
+					"{}",
+					serde_json::to_string_pretty(&blocks).expect("blocks are serializable")
+				),
+			}
+		},
+		Command::State(StateCommand::Get { pallet, key, map_key: None }) => {
+			let node = Node::open(&base_path)?;
+			let value = node
+				.read_storage(&pallet, &key)
+				.ok_or_else(|| format!("no storage item `{}` in pallet `{}`", key, pallet))?;
+			println!("{}", value);
+		},
+		Command::State(StateCommand::Get { pallet, key, map_key: Some(map_key) }) => {
+			let node = Node::open(&base_path)?;
+			let value = node.read_storage_entry(&pallet, &key, &map_key).ok_or_else(|| {
+				format!("no entry {} in storage map `{}` of pallet `{}`", map_key, key, pallet)
+			})?;
+			println!("{}", value);
+		},
+		Command::PurgeChain => {
+			Node::purge(&base_path)?;
+			println!("Removed the chain at {}", base_path.display());
+		},
+	}
+	Ok(())
 }
 
-/// Implements the `proof_of_existence::Config` trait for the `Runtime`.
-impl proof_of_existence::Config for Runtime {
-	type Content = types::Content;
+/// Get the nonce which follows the account nonce of `signer`, and its transactions in the pool.
+fn next_nonce(node: &Node, signer: &types::AccountId) -> types::Nonce {
+	let account_nonce = node.chain().best_state().system.nonce(signer);
+	node.pool()
+		.transactions()
+		.into_iter()
+		.filter(|extrinsic| &extrinsic.caller == signer)
+		.map(|extrinsic| extrinsic.nonce + 1)
+		.fold(account_nonce, types::Nonce::max)
 }
 
-// The `enum RuntimeCall`, `impl Runtime`, and `impl support::Dispatch for Runtime`
-// are now all generated automatically by the `#[macros::runtime]` attribute.
-
-/// The main entry point for the runtime simulation.
-fn main() {
-	// Instantiate the runtime.
-	let mut runtime = Runtime::new();
-	let alice = "alice".to_string();
-	let bob = "bob".to_string();
-	let charlie = "charlie".to_string();
-
-	// Set up the genesis state.
-	runtime.balances.set_balance(&alice, 100);
-
-	// Construct block 1: Balance transfers.
-	let block_1 = types::Block {
-		header: support::Header { block_number: 1 },
-		extrinsics: vec![
-			support::Extrinsic {
-				caller: alice.clone(),
-				call: RuntimeCall::balances(balances::Call::transfer {
-					to: bob.clone(),
-					amount: 30,
-				}),
-			},
-			support::Extrinsic {
-				caller: alice.clone(),
-				call: RuntimeCall::balances(balances::Call::transfer { to: charlie, amount: 20 }),
-			},
-		],
-	};
-
-	// Construct block 2: Proof of Existence claims.
-	let block_2 = types::Block {
-		header: support::Header { block_number: 2 },
-		extrinsics: vec![
-			support::Extrinsic {
-				caller: alice.clone(),
-				call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
-					claim: "Hello, world!",
-				}),
-			},
-			support::Extrinsic {
-				caller: bob.clone(),
-				call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
-					claim: "Hello, world!",
-				}),
-			},
-		],
-	};
-
-	// Construct block 3: Claim revocation and re-claim.
-	let block_3 = types::Block {
-		header: support::Header { block_number: 3 },
-		extrinsics: vec![
-			support::Extrinsic {
-				caller: alice.clone(),
-				call: RuntimeCall::proof_of_existence(proof_of_existence::Call::revoke_claim {
-					claim: "Hello, world!",
-				}),
-			},
-			support::Extrinsic {
-				caller: bob.clone(),
-				call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
-					claim: "Hello, world!",
-				}),
-			},
-		],
-	};
-
-	// Execute the blocks.
-	runtime.execute_block(block_1).expect("invalid block");
-	runtime.execute_block(block_2).expect("invalid block");
-	runtime.execute_block(block_3).expect("invalid block");
-
-	// Print the final runtime state for verification.
-	println!("{:#?}", runtime);
-
-	// Verify the final state.
-	assert_eq!(runtime.system.block_number(), 3);
-	assert_eq!(runtime.system.nonce(&alice), 4);
-	assert_eq!(runtime.system.nonce(&bob), 2);
-	assert_eq!(runtime.balances.balance(&alice), 50);
-	assert_eq!(runtime.proof_of_existence.get_claim(&"Hello, world!"), Some(&bob));
+/// The main entry point of the node.
+fn main() -> ExitCode {
+	let cli = Cli::parse();
+	match run(cli.base_path, cli.command) {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(e) => {
+			eprintln!("Error: {}", e);
+			ExitCode::FAILURE
+		},
+	}
 }
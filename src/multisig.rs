@@ -0,0 +1,486 @@
+use crate::{
+	balances,
+	support::{self, Dispatch, DispatchResult, FromHash, GetWeight, HasPallet, StorageMap},
+};
+use core::hash::Hash;
+
+/// Configuration trait for the Multisig pallet.
+/// Multisig accounts are derived from hashes of their signatories, and pay deposits in `balances`.
+pub trait Config: balances::Config<AccountId: FromHash + Hash> {
+	/// The overarching call type, which is dispatched once an operation is approved.
+	type RuntimeCall: Hash + GetWeight;
+	/// The amount reserved from the depositor while an operation is pending.
+	const DEPOSIT: Self::Balance;
+	/// The maximum number of signatories of a multisig, including the caller.
+	const MAX_SIGNATORIES: usize;
+}
+
+/// A pending multisig operation, waiting for enough approvals to be dispatched.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Multisig<AccountId, Balance> {
+	/// The account which opened the operation, and which holds its deposit.
+	pub depositor: AccountId,
+	/// The amount reserved from the depositor.
+	pub deposit: Balance,
+	/// The signatories which have approved the operation so far, kept sorted.
+	pub approvals: Vec<AccountId>,
+}
+
+/// The errors returned by the Multisig pallet.
+#[macros::error]
+pub enum Error {
+	/// The threshold is less than two.
+	ThresholdTooLow,
+	/// The signatories are not all different.
+	DuplicateSignatories,
+	/// There are more signatories than `MAX_SIGNATORIES`.
+	TooManySignatories,
+	/// The threshold is greater than the number of signatories.
+	ThresholdTooHigh,
+	/// The caller has already approved the operation.
+	AlreadyApproved,
+	/// There is no pending operation for the call.
+	NoSuchOperation,
+	/// The caller is not the depositor of the operation.
+	NotDepositor,
+}
+
+/// The Multisig pallet.
+/// Allows a set of signatories to control an account together, by approving calls until a
+/// threshold of them agree.
+#[macros::storage]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Pallet<T: Config> {
+	/// A mapping from a multisig account and the hash of a call to its pending operation.
+	#[allow(clippy::type_complexity)]
+	multisigs: StorageMap<(T::AccountId, support::Hash), Multisig<T::AccountId, T::Balance>>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Get the pending operation of a multisig account for a call hash, if it exists.
+	pub fn multisig(
+		&self,
+		account: &T::AccountId,
+		call_hash: support::Hash,
+	) -> Option<&Multisig<T::AccountId, T::Balance>> {
+		self.multisigs.get(&(account.clone(), call_hash))
+	}
+
+	/// Derive the account controlled by a set of signatories and a threshold.
+	///
+	/// The signatories are sorted first, so any ordering of the same set gives the same account.
+	pub fn multi_account_id(signatories: &[T::AccountId], threshold: u16) -> T::AccountId {
+		let mut signatories = signatories.to_vec();
+		signatories.sort();
+		T::AccountId::from_hash(support::hash(&(b"multisig", signatories, threshold)))
+	}
+
+	/// Check the parameters of an operation, and return the multisig account it is for.
+	fn ensure_multisig(
+		caller: &T::AccountId,
+		threshold: u16,
+		other_signatories: &[T::AccountId],
+	) -> Result<T::AccountId, support::DispatchError> {
+		if threshold < 2 {
+			return Err(Error::ThresholdTooLow.into());
+		}
+		let mut signatories = other_signatories.to_vec();
+		signatories.push(caller.clone());
+		signatories.sort();
+		signatories.dedup();
+		if signatories.len() != other_signatories.len() + 1 {
+			return Err(Error::DuplicateSignatories.into());
+		}
+		if signatories.len() > T::MAX_SIGNATORIES {
+			return Err(Error::TooManySignatories.into());
+		}
+		if usize::from(threshold) > signatories.len() {
+			return Err(Error::ThresholdTooHigh.into());
+		}
+		Ok(Self::multi_account_id(&signatories, threshold))
+	}
+}
+
+/// The dispatchable functions of the Multisig pallet.
+///
+/// These take the whole runtime, rather than `&mut self`, since they reserve deposits in
+/// `balances` and dispatch approved calls to any pallet. Approved calls are dispatched on a copy of
+/// the runtime, which is why it must implement `Clone`.
+#[macros::call]
+impl<T: Config> Pallet<T>
+where
+	T: Clone
+		+ HasPallet<Self>
+		+ HasPallet<balances::Pallet<T>>
+		+ Dispatch<Caller = T::AccountId, Call = T::RuntimeCall>,
+{
+	/// Approve the call with `call_hash` on behalf of the multisig of `caller`,
+	/// `other_signatories` and `threshold`.
+	///
+	/// The first approval opens the operation and reserves a deposit from the `caller`.
+	pub fn approve_as_multi(
+		runtime: &mut T,
+		caller: T::AccountId,
+		threshold: u16,
+		other_signatories: Vec<T::AccountId>,
+		call_hash: support::Hash,
+	) -> DispatchResult {
+		let account = Self::ensure_multisig(&caller, threshold, &other_signatories)?;
+		let multisig: &Self = runtime.pallet();
+
+		match multisig.multisig(&account, call_hash) {
+			Some(operation) => {
+				let mut operation = operation.clone();
+				let index = match operation.approvals.binary_search(&caller) {
+					Ok(_) => return Err(Error::AlreadyApproved.into()),
+					Err(index) => index,
+				};
+				operation.approvals.insert(index, caller);
+				let multisig: &mut Self = runtime.pallet_mut();
+				multisig.multisigs.insert((account, call_hash), operation);
+			},
+			None => {
+				let balances: &mut balances::Pallet<T> = runtime.pallet_mut();
+				balances.reserve(&caller, T::DEPOSIT)?;
+				let operation = Multisig {
+					depositor: caller.clone(),
+					deposit: T::DEPOSIT,
+					approvals: vec![caller],
+				};
+				let multisig: &mut Self = runtime.pallet_mut();
+				multisig.multisigs.insert((account, call_hash), operation);
+			},
+		}
+
+		Ok(())
+	}
+
+	/// Approve `call` on behalf of the multisig of `caller`, `other_signatories` and `threshold`,
+	/// and dispatch it from the multisig account if this approval reaches the `threshold`.
+	///
+	/// Once dispatched, the operation is removed and its deposit is returned. If the dispatch
+	/// fails, none of the changes made by the call are kept, and the operation is kept so that it
+	/// can be retried or cancelled.
+	#[call(weight = support::DEFAULT_CALL_WEIGHT + call.weight())]
+	pub fn as_multi(
+		runtime: &mut T,
+		caller: T::AccountId,
+		threshold: u16,
+		other_signatories: Vec<T::AccountId>,
+		call: Box<T::RuntimeCall>,
+	) -> DispatchResult {
+		let account = Self::ensure_multisig(&caller, threshold, &other_signatories)?;
+		let call_hash = support::hash(&call);
+		let multisig: &Self = runtime.pallet();
+
+		let approvals = match multisig.multisig(&account, call_hash) {
+			Some(operation) if operation.approvals.binary_search(&caller).is_ok() =>
+				operation.approvals.len(),
+			Some(operation) => operation.approvals.len() + 1,
+			None => 1,
+		};
+		if approvals < usize::from(threshold) {
+			return Self::approve_as_multi(runtime, caller, threshold, other_signatories, call_hash);
+		}
+
+		// The call is dispatched on a copy of the runtime, which replaces the runtime only if the
+		// call succeeds, since a call can fail after it has changed the state.
+		let mut dispatched = runtime.clone();
+		dispatched.dispatch(account.clone(), *call)?;
+		*runtime = dispatched;
+
+		let multisig: &mut Self = runtime.pallet_mut();
+		let operation = multisig.multisigs.remove(&(account, call_hash)).unwrap();
+		let balances: &mut balances::Pallet<T> = runtime.pallet_mut();
+		balances.unreserve(&operation.depositor, operation.deposit)?;
+
+		Ok(())
+	}
+
+	/// Cancel the pending operation with `call_hash`, returning the deposit.
+	///
+	/// Only the `caller` who opened the operation can cancel it.
+	pub fn cancel_as_multi(
+		runtime: &mut T,
+		caller: T::AccountId,
+		threshold: u16,
+		other_signatories: Vec<T::AccountId>,
+		call_hash: support::Hash,
+	) -> DispatchResult {
+		let account = Self::ensure_multisig(&caller, threshold, &other_signatories)?;
+		let multisig: &mut Self = runtime.pallet_mut();
+
+		let key = (account, call_hash);
+		let operation = multisig.multisigs.get(&key).ok_or(Error::NoSuchOperation)?;
+		if operation.depositor != caller {
+			return Err(Error::NotDepositor.into());
+		}
+		let operation = multisig.multisigs.remove(&key).unwrap();
+
+		let balances: &mut balances::Pallet<T> = runtime.pallet_mut();
+		balances.unreserve(&operation.depositor, operation.deposit)?;
+
+		Ok(())
+	}
+}
+
+impl<T: Config> support::Hooks<T::BlockNumber> for Pallet<T> {}
+
+impl<T: Config> support::GetPalletMetadata for Pallet<T> {
+	fn error_metadata() -> Vec<support::VariantMetadata> {
+		<Error as support::GetErrorMetadata>::error_metadata()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		balances,
+		block_builder::{BlockBuilder, BlockLimits, Inclusion},
+		multisig,
+		support::{self, Dispatch, InherentData},
+		system,
+	};
+
+	mod types {
+		pub type Extrinsic = crate::support::Extrinsic<String, super::RuntimeCall, u32, u128>;
+		pub type Block = crate::support::Block<
+			crate::support::Header<u32>,
+			crate::support::UncheckedExtrinsic<Extrinsic, super::RuntimeCall>,
+		>;
+	}
+
+	// A pallet whose only call fails after it has written to its storage.
+	mod failing {
+		use crate::{
+			support::{self, DispatchResult, StorageValue},
+			system,
+		};
+
+		pub trait Config: system::Config {}
+
+		#[macros::storage]
+		#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+		pub struct Pallet<T: Config> {
+			#[getter(written)]
+			written: StorageValue<bool>,
+		}
+
+		#[macros::call]
+		impl<T: Config> Pallet<T> {
+			pub fn write_then_fail(&mut self, _caller: T::AccountId) -> DispatchResult {
+				self.written.put(true);
+				Err("failed after writing".into())
+			}
+		}
+
+		impl<T: Config> support::Hooks<T::BlockNumber> for Pallet<T> {}
+
+		impl<T: Config> support::GetPalletMetadata for Pallet<T> {}
+	}
+
+	// A minimal runtime to dispatch multisig operations against.
+	#[macros::runtime]
+	#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+	struct Runtime {
+		system: system::Pallet<Self>,
+		balances: balances::Pallet<Self>,
+		multisig: multisig::Pallet<Self>,
+		failing: failing::Pallet<Self>,
+	}
+
+	impl system::Config for Runtime {
+		type AccountId = String;
+		type BlockNumber = u32;
+		type Nonce = u32;
+	}
+
+	impl balances::Config for Runtime {
+		type Balance = u128;
+	}
+
+	impl failing::Config for Runtime {}
+
+	impl support::ChargeTransaction<types::Extrinsic> for Runtime {}
+
+	impl multisig::Config for Runtime {
+		type RuntimeCall = RuntimeCall;
+		const DEPOSIT: u128 = 10;
+		const MAX_SIGNATORIES: usize = 3;
+	}
+
+	fn transfer(to: &str, amount: u128) -> RuntimeCall {
+		RuntimeCall::balances(balances::Call::transfer { to: to.to_string(), amount })
+	}
+
+	fn as_multi(
+		runtime: &mut Runtime,
+		caller: &str,
+		others: &[&str],
+		call: RuntimeCall,
+	) -> support::DispatchResult {
+		let other_signatories = others.iter().map(|who| who.to_string()).collect();
+		let call =
+			multisig::Call::as_multi { threshold: 2, other_signatories, call: Box::new(call) };
+		runtime.dispatch(caller.to_string(), RuntimeCall::multisig(call))
+	}
+
+	// An error of the multisig pallet, as the runtime returns it.
+	fn error(error: multisig::Error) -> support::DispatchError {
+		support::DispatchError::from(error).in_pallet(2)
+	}
+
+	#[test]
+	fn multi_account_id_is_deterministic() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+
+		let account =
+			multisig::Pallet::<Runtime>::multi_account_id(&[alice.clone(), bob.clone()], 2);
+		assert_eq!(
+			account,
+			multisig::Pallet::<Runtime>::multi_account_id(&[bob.clone(), alice.clone()], 2)
+		);
+		assert_ne!(account, multisig::Pallet::<Runtime>::multi_account_id(&[alice, bob], 1));
+	}
+
+	#[test]
+	fn as_multi_dispatches_at_threshold() {
+		let mut runtime = Runtime::new();
+		let account = multisig::Pallet::<Runtime>::multi_account_id(
+			&["alice".to_string(), "bob".to_string(), "charlie".to_string()],
+			2,
+		);
+		runtime.balances.set_balance(&"alice".to_string(), 100);
+		runtime.balances.set_balance(&account, 50);
+
+		// The first approval reserves the deposit, but does not dispatch the call.
+		assert_eq!(
+			as_multi(&mut runtime, "alice", &["bob", "charlie"], transfer("dave", 20)),
+			Ok(())
+		);
+		assert_eq!(runtime.balances.reserved_balance(&"alice".to_string()), 10);
+		assert_eq!(
+			as_multi(&mut runtime, "alice", &["bob", "charlie"], transfer("dave", 20)),
+			Err(error(multisig::Error::AlreadyApproved))
+		);
+		assert_eq!(runtime.balances.balance(&"dave".to_string()), 0);
+
+		// The second approval reaches the threshold, dispatches and returns the deposit.
+		assert_eq!(
+			as_multi(&mut runtime, "charlie", &["alice", "bob"], transfer("dave", 20)),
+			Ok(())
+		);
+		assert_eq!(runtime.balances.balance(&"dave".to_string()), 20);
+		assert_eq!(runtime.balances.balance(&account), 30);
+		assert_eq!(runtime.balances.balance(&"alice".to_string()), 100);
+		assert_eq!(runtime.balances.reserved_balance(&"alice".to_string()), 0);
+		assert_eq!(runtime.multisig.multisig(&account, support::hash(&transfer("dave", 20))), None);
+	}
+
+	#[test]
+	fn failed_dispatch_keeps_operation() {
+		let mut runtime = Runtime::new();
+		let account = multisig::Pallet::<Runtime>::multi_account_id(
+			&["alice".to_string(), "bob".to_string()],
+			2,
+		);
+		runtime.balances.set_balance(&"alice".to_string(), 100);
+
+		let other_signatories = vec!["alice".to_string()];
+		let call = Box::new(transfer("dave", 20));
+		let extrinsic = support::Extrinsic::new_signed(
+			"bob".to_string(),
+			RuntimeCall::multisig(multisig::Call::as_multi {
+				threshold: 2,
+				other_signatories,
+				call,
+			}),
+			0,
+			0,
+		);
+
+		assert_eq!(as_multi(&mut runtime, "alice", &["bob"], transfer("dave", 20)), Ok(()));
+		// The multisig account has no funds, so the transfer fails.
+		let mut author = runtime.clone();
+		let limits = BlockLimits { max_weight: u64::MAX, max_size: usize::MAX };
+		let mut builder = BlockBuilder::new(&mut author, 0, &InherentData::new(), limits).unwrap();
+		let insufficient = support::DispatchError::Module {
+			error: support::ModuleError { index: 1, error: 0 },
+			message: "InsufficientBalance",
+		};
+		assert_eq!(builder.push(extrinsic), Inclusion::Included(Err(insufficient)));
+		assert_eq!(runtime.execute_block(builder.build()), Ok(()));
+		assert_eq!(runtime.balances.balance(&"dave".to_string()), 0);
+		let operation = runtime.multisig.multisig(&account, support::hash(&transfer("dave", 20)));
+		assert_eq!(operation.map(|operation| operation.approvals.len()), Some(1));
+	}
+
+	#[test]
+	fn failed_dispatch_changes_nothing() {
+		let mut runtime = Runtime::new();
+		let account = multisig::Pallet::<Runtime>::multi_account_id(
+			&["alice".to_string(), "bob".to_string()],
+			2,
+		);
+		runtime.balances.set_balance(&"alice".to_string(), 100);
+		let write_then_fail = || RuntimeCall::failing(failing::Call::write_then_fail {});
+
+		assert_eq!(as_multi(&mut runtime, "alice", &["bob"], write_then_fail()), Ok(()));
+		assert_eq!(
+			as_multi(&mut runtime, "bob", &["alice"], write_then_fail()),
+			Err("failed after writing".into())
+		);
+		assert!(!runtime.failing.written());
+		let operation = runtime.multisig.multisig(&account, support::hash(&write_then_fail()));
+		assert_eq!(operation.map(|operation| operation.approvals.len()), Some(1));
+		assert_eq!(runtime.balances.reserved_balance(&"alice".to_string()), 10);
+	}
+
+	#[test]
+	fn approve_and_cancel_as_multi() {
+		let mut runtime = Runtime::new();
+		let call_hash = support::hash(&transfer("dave", 20));
+		let approve = |others: &[&str]| multisig::Call::approve_as_multi {
+			threshold: 2,
+			other_signatories: others.iter().map(|who| who.to_string()).collect(),
+			call_hash,
+		};
+		let cancel = |others: &[&str]| multisig::Call::cancel_as_multi {
+			threshold: 2,
+			other_signatories: others.iter().map(|who| who.to_string()).collect(),
+			call_hash,
+		};
+		runtime.balances.set_balance(&"alice".to_string(), 100);
+
+		assert_eq!(
+			runtime.dispatch("alice".to_string(), RuntimeCall::multisig(approve(&["alice"]))),
+			Err(error(multisig::Error::DuplicateSignatories))
+		);
+		assert_eq!(
+			runtime.dispatch("bob".to_string(), RuntimeCall::multisig(approve(&["alice"]))),
+			Err(support::DispatchError::Module {
+				error: support::ModuleError { index: 1, error: 0 },
+				message: "InsufficientBalance",
+			})
+		);
+		assert_eq!(
+			runtime.dispatch("alice".to_string(), RuntimeCall::multisig(approve(&["bob"]))),
+			Ok(())
+		);
+		assert_eq!(runtime.balances.reserved_balance(&"alice".to_string()), 10);
+		assert_eq!(
+			runtime.dispatch("bob".to_string(), RuntimeCall::multisig(cancel(&["alice"]))),
+			Err(error(multisig::Error::NotDepositor))
+		);
+		assert_eq!(
+			runtime.dispatch("alice".to_string(), RuntimeCall::multisig(cancel(&["bob"]))),
+			Ok(())
+		);
+		assert_eq!(runtime.balances.reserved_balance(&"alice".to_string()), 0);
+		assert_eq!(
+			runtime.dispatch("alice".to_string(), RuntimeCall::multisig(cancel(&["bob"]))),
+			Err(error(multisig::Error::NoSuchOperation))
+		);
+	}
+}
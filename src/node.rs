@@ -0,0 +1,364 @@
+use crate::{
+	block_builder,
+	chain_spec::ChainSpec,
+	chain_store,
+	runtime::{types, Runtime, RuntimeCall, SLOT_DURATION},
+	support::{self, ReadRuntimeStorage},
+	timestamp, transaction_pool,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+	fs,
+	path::{Path, PathBuf},
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The limits on the blocks built by a node.
+pub const BLOCK_LIMITS: block_builder::BlockLimits =
+	block_builder::BlockLimits { max_weight: 1_000_000, max_size: 64 * 1024 };
+
+/// How many blocks behind the best block a block becomes final.
+pub const FINALITY_DEPTH: usize = 2;
+
+/// The maximum number of transactions in the pool of a node.
+pub const POOL_CAPACITY: usize = 1024;
+
+/// The chain of blocks a node has imported, choosing the longest fork as the best chain.
+pub type Chain = chain_store::ChainStore<Runtime, chain_store::LongestChain>;
+
+/// The transactions a node has received, waiting to be included in a block.
+pub type Pool =
+	transaction_pool::TransactionPool<types::AccountId, RuntimeCall, types::Nonce, types::Balance>;
+
+/// The file of the database holding the chain spec.
+const CHAIN_SPEC_FILE: &str = "chain_spec.json";
+/// The file of the database holding the imported blocks.
+const BLOCKS_FILE: &str = "blocks.json";
+/// The file of the database holding the transactions of the pool.
+const POOL_FILE: &str = "pool.json";
+
+/// The inherent data of a node whose clock is at the start of `slot`.
+pub fn inherent_data(slot: support::Slot) -> support::InherentData {
+	let mut data = support::InherentData::new();
+	data.put(timestamp::INHERENT_IDENTIFIER, slot * SLOT_DURATION);
+	data
+}
+
+/// The time of the clock of the node, in milliseconds since the Unix epoch.
+fn local_time() -> types::Moment {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map_or(0, |time| time.as_millis() as u64)
+}
+
+/// A node of a chain, whose blocks and transactions are kept in a database on disk.
+///
+/// The database is a directory holding the chain spec, the blocks in the order they were imported,
+/// and the transactions of the pool, each as a JSON file. The state is not stored: opening the
+/// database builds the genesis state from the chain spec, and imports the blocks again.
+pub struct Node {
+	path: PathBuf,
+	spec: ChainSpec,
+	chain: Chain,
+	pool: Pool,
+	// The blocks of the chain, in the order they were imported.
+	blocks: Vec<types::Block>,
+}
+
+impl Node {
+	/// Create a database at `path` for the chain described by `spec`, and open it.
+	/// Returns an error if there is already a database at `path`.
+	pub fn init(path: &Path, spec: ChainSpec) -> Result<Self, String> {
+		if path.join(CHAIN_SPEC_FILE).exists() {
+			return Err(format!("a chain already exists at {}", path.display()));
+		}
+		spec.build().map_err(|e| format!("invalid chain spec: {}", e))?;
+		fs::create_dir_all(path)
+			.map_err(|e| format!("could not create {}: {}", path.display(), e))?;
+		write_json(&path.join(CHAIN_SPEC_FILE), &spec)?;
+		let node = Self::new(path, spec)?;
+		node.save()?;
+		Ok(node)
+	}
+
+	/// Open the database at `path`, importing its blocks on top of the genesis state and
+	/// submitting its transactions to the pool again.
+	pub fn open(path: &Path) -> Result<Self, String> {
+		let spec_file = path.join(CHAIN_SPEC_FILE);
+		if !spec_file.exists() {
+			return Err(format!("no chain exists at {}, run `init` first", path.display()));
+		}
+		let mut node = Self::new(path, read_json(&spec_file)?)?;
+		for block in read_json::<Vec<types::Block>>(&path.join(BLOCKS_FILE))? {
+			node.import_block(block)?;
+		}
+		for extrinsic in read_json::<Vec<types::Extrinsic>>(&path.join(POOL_FILE))? {
+			// Transactions which are no longer valid are dropped, as they would be by `maintain`.
+			let _ = node.pool.submit(node.chain.best_state(), extrinsic);
+		}
+		Ok(node)
+	}
+
+	/// Remove the database at `path`, along with the chain and transactions it holds.
+	pub fn purge(path: &Path) -> Result<(), String> {
+		if !path.join(CHAIN_SPEC_FILE).exists() {
+			return Err(format!("no chain exists at {}", path.display()));
+		}
+		fs::remove_dir_all(path).map_err(|e| format!("could not remove {}: {}", path.display(), e))
+	}
+
+	/// Start a node with no blocks, from the genesis state of `spec`.
+	fn new(path: &Path, spec: ChainSpec) -> Result<Self, String> {
+		let genesis = spec.build().map_err(|e| format!("invalid chain spec: {}", e))?;
+		Ok(Self {
+			path: path.to_path_buf(),
+			spec,
+			chain: Chain::new(genesis, chain_store::LongestChain, FINALITY_DEPTH),
+			pool: Pool::new(POOL_CAPACITY),
+			blocks: Vec::new(),
+		})
+	}
+
+	/// Get the spec of the chain.
+	pub fn spec(&self) -> &ChainSpec {
+		&self.spec
+	}
+
+	/// Get the chain of imported blocks.
+	pub fn chain(&self) -> &Chain {
+		&self.chain
+	}
+
+	/// Get the transactions waiting to be included in a block.
+	pub fn pool(&self) -> &Pool {
+		&self.pool
+	}
+
+	/// Validate `extrinsic` against the state of the best block, and add it to the pool.
+	/// Returns the hash of the transaction.
+	pub fn submit(&mut self, extrinsic: types::Extrinsic) -> Result<support::Hash, String> {
+		let hash = self.pool.submit(self.chain.best_state(), extrinsic)?;
+		self.save()?;
+		Ok(hash)
+	}
+
+	/// Build a block on top of the best block in `slot`, or else in the slot after the best block,
+	/// as the author of the slot would, and import it.
+	///
+	/// The block includes the ready transactions of the pool. Transactions which are invalid are
+	/// dropped, and those which do not fit stay in the pool for a later block. Returns the block.
+	pub fn produce_block(&mut self, slot: Option<support::Slot>) -> Result<types::Block, String> {
+		let best_slot = self
+			.chain
+			.block(&self.chain.best_hash())
+			.map_or(0, |best| best.header.digest.slot);
+		let slot = slot.unwrap_or(best_slot + 1);
+		let parent = self.chain.best_state();
+		let author = parent.aura.slot_author(slot).ok_or("there are no authorities")?.clone();
+
+		let mut state = parent.clone();
+		let mut builder =
+			block_builder::BlockBuilder::new(&mut state, slot, &inherent_data(slot), BLOCK_LIMITS)?;
+		let mut exhausted = Vec::new();
+		for extrinsic in self.pool.take_ready() {
+			if let block_builder::Inclusion::Exhausted(extrinsic) = builder.push(extrinsic) {
+				exhausted.push(extrinsic);
+			}
+		}
+		let mut block = builder.build();
+		block.header.seal(&author);
+
+		self.import_block(block.clone())?;
+		for extrinsic in exhausted {
+			if let support::UncheckedExtrinsic::Signed(extrinsic) = extrinsic {
+				let _ = self.pool.submit(self.chain.best_state(), extrinsic);
+			}
+		}
+		self.save()?;
+		Ok(block)
+	}
+
+	/// Import `blocks` built by other nodes, in order, skipping those already imported.
+	/// Returns the number of blocks imported.
+	pub fn import_blocks(&mut self, blocks: Vec<types::Block>) -> Result<usize, String> {
+		let mut imported = 0;
+		for block in blocks {
+			if self.chain.block(&block.header.hash()).is_some() {
+				continue;
+			}
+			let number = block.header.block_number;
+			self.import_block(block).map_err(|e| format!("block {}: {}", number, e))?;
+			imported += 1;
+		}
+		self.save()?;
+		Ok(imported)
+	}
+
+	/// Get the blocks of the best chain, oldest first, as they would be imported by another node.
+	pub fn export_blocks(&self) -> Vec<&types::Block> {
+		self.chain.best_chain()
+	}
+
+	/// Read the storage item named `item` of the pallet named `pallet`, in the state of the best
+	/// block.
+	pub fn read_storage(&self, pallet: &str, item: &str) -> Option<String> {
+		self.chain.best_state().read_storage(pallet, item)
+	}
+
+	/// Read the entry at `key`, given as JSON, of the map named `item` of the pallet named
+	/// `pallet`, in the state of the best block.
+	pub fn read_storage_entry(&self, pallet: &str, item: &str, key: &str) -> Option<String> {
+		self.chain.best_state().read_storage_entry(pallet, item, key)
+	}
+
+	/// Import `block` on top of the chain, and revalidate the pool against the new best state.
+	///
+	/// The block is checked against the clock of the node, so that a block whose time is too far
+	/// ahead of it is rejected. Blocks imported long after they were built are in the past.
+	fn import_block(&mut self, block: types::Block) -> Result<(), String> {
+		let mut data = support::InherentData::new();
+		data.put(timestamp::INHERENT_IDENTIFIER, local_time());
+		self.chain.import(block.clone(), &data)?;
+		self.blocks.push(block);
+		self.pool.maintain(self.chain.best_state());
+		Ok(())
+	}
+
+	/// Write the blocks and transactions of the node to its database. Blocks of pruned forks are
+	/// no longer written.
+	fn save(&self) -> Result<(), String> {
+		let blocks = self
+			.blocks
+			.iter()
+			.filter(|block| self.chain.block(&block.header.hash()).is_some())
+			.collect::<Vec<_>>();
+		write_json(&self.path.join(BLOCKS_FILE), &blocks)?;
+		write_json(&self.path.join(POOL_FILE), &self.pool.transactions())
+	}
+}
+
+/// Read a value from the JSON file at `path`.
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+	let json = fs::read_to_string(path)
+		.map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+	serde_json::from_str(&json).map_err(|e| format!("could not decode {}: {}", path.display(), e))
+}
+
+/// Write a value to the JSON file at `path`, replacing its contents.
+pub fn write_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<(), String> {
+	let json = serde_json::to_string_pretty(value).expect("values are always serializable");
+	fs::write(path, json).map_err(|e| format!("could not write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Node;
+	use crate::{
+		balances,
+		chain_spec::ChainSpec,
+		runtime::{types, RuntimeCall, SLOT_DURATION},
+		support,
+	};
+	use std::path::PathBuf;
+
+	// A database in a directory of its own, removed when the test ends.
+	struct TempDir(PathBuf);
+
+	impl TempDir {
+		fn new(name: &str) -> Self {
+			let path = std::env::temp_dir().join(format!(
+				"rust-state-machine-{}-{}",
+				name,
+				std::process::id()
+			));
+			let _ = std::fs::remove_dir_all(&path);
+			Self(path)
+		}
+	}
+
+	impl Drop for TempDir {
+		fn drop(&mut self) {
+			let _ = std::fs::remove_dir_all(&self.0);
+		}
+	}
+
+	fn transfer(nonce: u32, to: &str, amount: u128) -> types::Extrinsic {
+		let call = balances::Call::transfer { to: to.to_string(), amount };
+		support::Extrinsic::new_signed("alice".to_string(), RuntimeCall::balances(call), nonce, 0)
+	}
+
+	#[test]
+	fn produces_blocks_and_reopens() {
+		let dir = TempDir::new("reopen");
+		let mut node = Node::init(&dir.0, ChainSpec::development()).unwrap();
+		assert!(Node::init(&dir.0, ChainSpec::development()).is_err());
+
+		node.submit(transfer(0, "bob", 30)).unwrap();
+		// This transaction waits in the future queue for the one before it.
+		node.submit(transfer(2, "bob", 5)).unwrap();
+		let block = node.produce_block(None).unwrap();
+		assert_eq!(block.header.block_number, 1);
+		assert_eq!(node.pool().len(), 1);
+
+		// The state is rebuilt from the blocks, and the pool from its transactions.
+		let mut node = Node::open(&dir.0).unwrap();
+		assert_eq!(node.read_storage("system", "block_number").unwrap(), "1");
+		assert_eq!(node.chain().best_state().balances.balance(&"bob".to_string()), 30);
+		assert_eq!(node.pool().len(), 1);
+		assert_eq!(node.read_storage("balances", "unknown"), None);
+		assert_eq!(node.read_storage("unknown", "balances"), None);
+		assert_eq!(node.read_storage_entry("balances", "balances", "\"bob\"").unwrap(), "30");
+		assert_eq!(node.read_storage_entry("balances", "balances", "\"nobody\""), None);
+		assert_eq!(node.read_storage_entry("balances", "balances", "bob"), None);
+		assert_eq!(node.read_storage_entry("system", "block_number", "\"bob\""), None);
+
+		node.submit(transfer(1, "charlie", 20)).unwrap();
+		node.produce_block(Some(4)).unwrap();
+		assert!(node.pool().is_empty());
+		assert_eq!(node.chain().best_state().balances.balance(&"bob".to_string()), 35);
+
+		Node::purge(&dir.0).unwrap();
+		assert!(Node::open(&dir.0).is_err());
+	}
+
+	#[test]
+	fn rejects_blocks_ahead_of_the_clock() {
+		let dir = TempDir::new("clock");
+		let mut node = Node::init(&dir.0, ChainSpec::development()).unwrap();
+		let slot = super::local_time() / SLOT_DURATION;
+
+		// A block further ahead of the clock of the node than the drift allowed is not imported.
+		assert_eq!(node.produce_block(Some(slot + 10)), Err("TooFarInFuture".to_string()));
+		assert_eq!(node.chain().best_state().system.block_number(), 0);
+		assert_eq!(node.produce_block(Some(slot)).unwrap().header.digest.slot, slot);
+	}
+
+	#[test]
+	fn exports_and_imports_blocks() {
+		let (from, to) = (TempDir::new("export"), TempDir::new("import"));
+		let mut node = Node::init(&from.0, ChainSpec::development()).unwrap();
+		node.submit(transfer(0, "bob", 30)).unwrap();
+		for _ in 0..4 {
+			node.produce_block(None).unwrap();
+		}
+		let blocks = node.export_blocks().into_iter().cloned().collect::<Vec<_>>();
+		assert_eq!(blocks.len(), 4);
+
+		let mut other = Node::init(&to.0, ChainSpec::development()).unwrap();
+		assert_eq!(other.import_blocks(blocks[..2].to_vec()).unwrap(), 2);
+		// Blocks already imported are skipped.
+		assert_eq!(other.import_blocks(blocks.clone()).unwrap(), 2);
+		assert_eq!(other.chain().best_hash(), node.chain().best_hash());
+		assert_eq!(
+			other.read_storage("balances", "balances"),
+			node.read_storage("balances", "balances")
+		);
+
+		// A chain with another genesis state cannot import the blocks.
+		let spec = ChainSpec { name: "Other".to_string(), ..ChainSpec::development() };
+		let spec = ChainSpec { balances: vec![("alice".to_string(), 1_000)], ..spec };
+		let other = TempDir::new("other");
+		let mut other = Node::init(&other.0, spec).unwrap();
+		assert!(other.import_blocks(blocks).is_err());
+	}
+}
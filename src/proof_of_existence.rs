@@ -1,71 +1,153 @@
-use crate::support::DispatchResult;
-use core::fmt::Debug;
-use std::{collections::BTreeMap, marker::PhantomData};
-
-/// Configuration trait for the Proof of Existence pallet.
-pub trait Config: crate::system::Config {
-	/// The type that represents the content that can be claimed.
-	type Content: Debug + Ord + Clone;
-}
+pub use pallet::*;
 
 /// The Proof of Existence pallet.
 /// Allows accounts to claim the existence of some data.
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Pallet<T: Config> {
-	/// A mapping from the content to the account that claimed it.
-	claims: BTreeMap<T::Content, T::AccountId>,
-	/// A marker for the generic type `T`.
-	_phantom: PhantomData<T>,
-}
+#[macros::pallet]
+pub mod pallet {
+	use crate::support::{self, DispatchResult, StorageMap, StorageValue};
+	use core::fmt::Debug;
+	use num::traits::Zero;
 
-impl<T: Config> Pallet<T> {
-	/// Constructs a new instance of this pallet.
-	pub fn new() -> Self {
-		Self { claims: BTreeMap::new(), _phantom: PhantomData }
+	/// Configuration trait for the Proof of Existence pallet.
+	/// Claims are stamped with the time of their block, kept by the Timestamp pallet.
+	///
+	/// A runtime can keep several registries of claims, as instances of this pallet. `I` is the
+	/// instance, which defaults to `()`.
+	#[pallet::config]
+	pub trait Config<I = ()>: crate::timestamp::Config {
+		/// The type that represents the content that can be claimed.
+		type Content: Debug + Ord + Clone;
 	}
 
-	/// Get the owner of a claim, if it exists.
-	pub fn get_claim(&self, claim: &T::Content) -> Option<&T::AccountId> {
-		self.claims.get(claim)
+	/// The state of the Proof of Existence pallet.
+	#[pallet::storage]
+	#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+	pub struct Pallet<T: Config<I>, I = ()> {
+		/// A mapping from the content to the account that claimed it, and the time of the claim.
+		claims: StorageMap<T::Content, (T::AccountId, T::Moment)>,
+		/// The time of the current block.
+		#[default(T::Moment::zero())]
+		now: StorageValue<T::Moment>,
+		/// The events emitted by this pallet in the current block.
+		events: StorageValue<Vec<Event<T, I>>>,
 	}
-}
 
-/// The dispatchable functions of the Proof of Existence pallet.
-#[macros::call]
-impl<T: Config> Pallet<T>
-where
-	T: Config,
-{
-	/// Create a new claim on behalf of the `caller`.
-	///
-	/// Returns an error if the claim has already been made.
-	pub fn create_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
-		if self.claims.contains_key(&claim) {
-			return Err("this content is already claimed");
+	/// The events emitted by the Proof of Existence pallet.
+	#[pallet::event]
+	#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+	pub enum Event<T: Config<I>, I = ()> {
+		/// `who` claimed `claim`.
+		ClaimCreated { who: T::AccountId, claim: T::Content },
+		/// `who` revoked their claim on `claim`.
+		ClaimRevoked { who: T::AccountId, claim: T::Content },
+	}
+
+	/// The errors returned by the Proof of Existence pallet.
+	#[pallet::error]
+	pub enum Error {
+		/// The content is already claimed.
+		AlreadyClaimed,
+		/// The content is not claimed.
+		NoSuchClaim,
+		/// The caller is not the owner of the claim.
+		NotClaimOwner,
+	}
+
+	/// The genesis state of the Proof of Existence pallet.
+	#[pallet::genesis_config]
+	pub struct GenesisConfig<T: Config<I>, I = ()> {
+		/// The claims which exist from the start, with their owners.
+		pub claims: Vec<(T::Content, T::AccountId)>,
+		/// A marker for the instance of the pallet.
+		pub _phantom: core::marker::PhantomData<I>,
+	}
+
+	// This is not derived, since it would need `T` and `I` to implement `Default`.
+	impl<T: Config<I>, I> Default for GenesisConfig<T, I> {
+		fn default() -> Self {
+			Self { claims: Vec::new(), _phantom: core::marker::PhantomData }
 		}
-		self.claims.insert(claim, caller);
-		Ok(())
 	}
 
-	/// Revoke an existing claim.
-	///
-	/// This function will return an error if the caller is not the owner of the
-	/// claim, or if the claim does not exist.
-	pub fn revoke_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
-		let owner = self.get_claim(&claim).ok_or("claim does not exist")?;
-		if *owner != caller {
-			return Err("not the owner of the claim");
+	impl<T: Config<I>, I> support::BuildGenesisConfig<Pallet<T, I>> for GenesisConfig<T, I> {
+		fn build(&self, pallet: &mut Pallet<T, I>) -> DispatchResult {
+			for (claim, owner) in self.claims.iter().cloned() {
+				pallet.create_claim(owner, claim)?;
+			}
+			Ok(())
+		}
+	}
+
+	// Events are only kept for the block which emitted them.
+	#[pallet::hooks]
+	impl<T: Config<I>, I> support::Hooks<T::BlockNumber> for Pallet<T, I> {
+		fn on_initialize(&mut self, _block_number: T::BlockNumber) {
+			self.events.put(Vec::new());
+		}
+	}
+
+	impl<T: Config<I>, I> Pallet<T, I> {
+		/// Get the owner of a claim, if it exists.
+		pub fn get_claim(&self, claim: &T::Content) -> Option<&T::AccountId> {
+			self.claims.get(claim).map(|(owner, _)| owner)
+		}
+
+		/// Get the time at which a claim was made, if it exists.
+		pub fn claimed_at(&self, claim: &T::Content) -> Option<T::Moment> {
+			self.claims.get(claim).map(|(_, moment)| *moment)
+		}
+
+		/// Set the time of the current block, which new claims are stamped with.
+		///
+		/// This is not a dispatchable call. The runtime calls it whenever the timestamp is set.
+		pub fn set_now(&mut self, now: T::Moment) {
+			self.now.put(now);
+		}
+	}
+
+	/// The dispatchable functions of the Proof of Existence pallet.
+	#[pallet::call]
+	impl<T: Config<I>, I> Pallet<T, I> {
+		/// Create a new claim on behalf of the `caller`.
+		///
+		/// Returns an error if the claim has already been made.
+		#[call_index(0)]
+		pub fn create_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
+			if self.claims.contains_key(&claim) {
+				return Err(Error::AlreadyClaimed.into());
+			}
+			self.claims.insert(claim.clone(), (caller.clone(), *self.now.get()));
+			self.deposit_event(Event::ClaimCreated { who: caller, claim });
+			Ok(())
+		}
+
+		/// Revoke an existing claim.
+		///
+		/// This function will return an error if the caller is not the owner of the
+		/// claim, or if the claim does not exist.
+		#[call_index(1)]
+		pub fn revoke_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
+			let owner = self.get_claim(&claim).ok_or(Error::NoSuchClaim)?;
+			if *owner != caller {
+				return Err(Error::NotClaimOwner.into());
+			}
+			self.claims.remove(&claim);
+			self.deposit_event(Event::ClaimRevoked { who: caller, claim });
+			Ok(())
 		}
-		self.claims.remove(&claim);
-		Ok(())
 	}
 }
 
 #[cfg(test)]
 mod test {
-	use crate::{proof_of_existence as poe, system};
+	use crate::{
+		proof_of_existence as poe,
+		support::{GetPalletMetadata, Hooks},
+		system, timestamp,
+	};
 
 	// Mock struct for testing purposes.
+	#[derive(Debug, PartialEq)]
 	struct TestConfig;
 
 	impl poe::Config for TestConfig {
@@ -78,6 +160,12 @@ mod test {
 		type Nonce = u32;
 	}
 
+	impl timestamp::Config for TestConfig {
+		type Moment = u64;
+		const MINIMUM_PERIOD: u64 = 1;
+		const MAX_DRIFT: u64 = 0;
+	}
+
 	#[test]
 	fn basic_proof_of_existence() {
 		let mut poe_pallet = poe::Pallet::<TestConfig>::new();
@@ -86,9 +174,61 @@ mod test {
 		assert_eq!(poe_pallet.get_claim(&"Hello, world!"), Some(&"alice"));
 		assert_eq!(
 			poe_pallet.create_claim("bob", "Hello, world!"),
-			Err("this content is already claimed")
+			Err(poe::Error::AlreadyClaimed.into())
 		);
+		assert_eq!(
+			poe_pallet.revoke_claim("bob", "Hello, world!"),
+			Err(poe::Error::NotClaimOwner.into())
+		);
+		assert_eq!(poe_pallet.claimed_at(&"Hello, world!"), Some(0));
 		assert_eq!(poe_pallet.revoke_claim("alice", "Hello, world!"), Ok(()));
+		poe_pallet.set_now(42);
 		assert_eq!(poe_pallet.create_claim("bob", "Hello, world!"), Ok(()));
+		assert_eq!(poe_pallet.claimed_at(&"Hello, world!"), Some(42));
+		assert_eq!(
+			poe_pallet.revoke_claim("bob", "Goodbye, world!"),
+			Err(poe::Error::NoSuchClaim.into())
+		);
+	}
+
+	#[test]
+	fn genesis_claims_and_events() {
+		let claims = vec![("Hello, world!", "alice"), ("Goodbye, world!", "bob")];
+		let mut poe_pallet = poe::Pallet::<TestConfig>::from_genesis(&poe::GenesisConfig {
+			claims,
+			..Default::default()
+		})
+		.unwrap();
+		assert_eq!(poe_pallet.get_claim(&"Goodbye, world!"), Some(&"bob"));
+
+		// Events are cleared at the start of every block.
+		assert_eq!(poe_pallet.events().len(), 2);
+		poe_pallet.on_initialize(1);
+		assert_eq!(poe_pallet.revoke_claim("alice", "Hello, world!"), Ok(()));
+		let revoked = poe::Event::ClaimRevoked { who: "alice", claim: "Hello, world!" };
+		assert_eq!(poe_pallet.events(), &[revoked]);
+
+		// Two genesis claims on the same content are invalid.
+		let claims = vec![("Hello, world!", "alice"), ("Hello, world!", "bob")];
+		let genesis = poe::GenesisConfig::<TestConfig> { claims, ..Default::default() };
+		assert_eq!(poe::Pallet::from_genesis(&genesis), Err("AlreadyClaimed"));
+	}
+
+	#[test]
+	fn event_metadata() {
+		let events = poe::Pallet::<TestConfig>::event_metadata();
+		let names = events.iter().map(|event| (event.index, event.name)).collect::<Vec<_>>();
+		assert_eq!(names, [(0, "ClaimCreated"), (1, "ClaimRevoked")]);
+		assert_eq!(events[1].docs, ["`who` revoked their claim on `claim`."]);
+		let fields = events[0].fields.iter().map(|field| (field.name, field.ty));
+		assert_eq!(fields.collect::<Vec<_>>(), [("who", "&str"), ("claim", "&str")]);
+	}
+
+	#[test]
+	fn error_metadata() {
+		let errors = poe::Pallet::<TestConfig>::error_metadata();
+		let names = errors.iter().map(|error| (error.index, error.name)).collect::<Vec<_>>();
+		assert_eq!(names, [(0, "AlreadyClaimed"), (1, "NoSuchClaim"), (2, "NotClaimOwner")]);
+		assert_eq!(errors[2].docs, ["The caller is not the owner of the claim."]);
 	}
 }
@@ -0,0 +1,503 @@
+use crate::{
+	support::{self, Dispatch, DispatchResult, GetWeight, HasPallet, InstanceFilter, StorageMap},
+	system,
+};
+use core::hash::Hash;
+use num::traits::Zero;
+
+/// Configuration trait for the Proxy pallet.
+pub trait Config: system::Config<AccountId: Hash, BlockNumber: Ord + Hash> {
+	/// The overarching call type, which proxies dispatch on behalf of their real account.
+	type RuntimeCall: Hash + GetWeight;
+	/// The kinds of proxy, each of which filters the calls a proxy of that kind may make.
+	type ProxyType: InstanceFilter<Self::RuntimeCall> + Clone + Ord + Hash;
+	/// The maximum number of proxies an account can have.
+	const MAX_PROXIES: usize;
+	/// The maximum number of pending announcements a proxy can have.
+	const MAX_PENDING: usize;
+}
+
+/// A proxy which is allowed to make calls on behalf of an account.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProxyDefinition<AccountId, ProxyType, BlockNumber> {
+	/// The account which can make calls on behalf of the real account.
+	pub delegate: AccountId,
+	/// The kind of proxy, which filters the calls it can make.
+	pub proxy_type: ProxyType,
+	/// The number of blocks a call must be announced for before it can be made. If zero, calls
+	/// can be made immediately.
+	pub delay: BlockNumber,
+}
+
+/// A call announced by a proxy, to be made once its delay has passed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Announcement<AccountId, BlockNumber> {
+	/// The account the call will be made on behalf of.
+	pub real: AccountId,
+	/// The hash of the announced call.
+	pub call_hash: support::Hash,
+	/// The block number at which the call was announced.
+	pub height: BlockNumber,
+}
+
+/// The errors returned by the Proxy pallet.
+#[macros::error]
+pub enum Error {
+	/// The caller is not a proxy of the account.
+	NotProxy,
+	/// The proxy is already registered.
+	Duplicate,
+	/// The account has `MAX_PROXIES` proxies already.
+	TooManyProxies,
+	/// The proxy is not registered.
+	NoSuchProxy,
+	/// The call was not announced by the proxy.
+	NoSuchAnnouncement,
+	/// The proxy has a delay, so its calls must be announced first.
+	Unannounced,
+	/// The call is not allowed by the type of the proxy.
+	Unproxyable,
+	/// The proxy has `MAX_PENDING` announcements already.
+	TooManyAnnouncements,
+	/// The delay of the proxy has not passed since the call was announced.
+	AnnouncementNotDue,
+}
+
+/// The Proxy pallet.
+/// Allows accounts to delegate a limited set of calls to other accounts, such as hot keys acting
+/// on behalf of a cold account.
+#[macros::storage]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Pallet<T: Config> {
+	/// A mapping from an account to the proxies which can act on its behalf.
+	#[allow(clippy::type_complexity)]
+	proxies:
+		StorageMap<T::AccountId, Vec<ProxyDefinition<T::AccountId, T::ProxyType, T::BlockNumber>>>,
+	/// A mapping from a proxy to the calls it has announced.
+	#[allow(clippy::type_complexity)]
+	announcements: StorageMap<T::AccountId, Vec<Announcement<T::AccountId, T::BlockNumber>>>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Get the proxies of an account.
+	pub fn proxies(
+		&self,
+		real: &T::AccountId,
+	) -> &[ProxyDefinition<T::AccountId, T::ProxyType, T::BlockNumber>] {
+		self.proxies.get(real).map(Vec::as_slice).unwrap_or(&[])
+	}
+
+	/// Get the calls announced by a proxy.
+	pub fn announcements(
+		&self,
+		delegate: &T::AccountId,
+	) -> &[Announcement<T::AccountId, T::BlockNumber>] {
+		self.announcements.get(delegate).map(Vec::as_slice).unwrap_or(&[])
+	}
+
+	/// Find a proxy of `real` with the account `delegate`, and with the type `force_proxy_type` if
+	/// given.
+	#[allow(clippy::type_complexity)]
+	fn find_proxy(
+		&self,
+		real: &T::AccountId,
+		delegate: &T::AccountId,
+		force_proxy_type: Option<&T::ProxyType>,
+	) -> Result<&ProxyDefinition<T::AccountId, T::ProxyType, T::BlockNumber>, support::DispatchError>
+	{
+		self.proxies(real)
+			.iter()
+			.find(|proxy| {
+				proxy.delegate == *delegate &&
+					force_proxy_type.is_none_or(|proxy_type| proxy.proxy_type == *proxy_type)
+			})
+			.ok_or(Error::NotProxy.into())
+	}
+}
+
+/// The dispatchable functions of the Proxy pallet.
+///
+/// Some of these take the whole runtime, since they read the block number from `system` or
+/// dispatch calls to any pallet.
+#[macros::call]
+impl<T: Config> Pallet<T>
+where
+	T: HasPallet<Self>
+		+ HasPallet<system::Pallet<T>>
+		+ Dispatch<Caller = T::AccountId, Call = T::RuntimeCall>,
+{
+	/// Register `delegate` as a proxy of the `caller`.
+	pub fn add_proxy(
+		&mut self,
+		caller: T::AccountId,
+		delegate: T::AccountId,
+		proxy_type: T::ProxyType,
+		delay: T::BlockNumber,
+	) -> DispatchResult {
+		let proxy = ProxyDefinition { delegate, proxy_type, delay };
+		let proxies = self.proxies(&caller);
+		if proxies.contains(&proxy) {
+			return Err(Error::Duplicate.into());
+		}
+		if proxies.len() >= T::MAX_PROXIES {
+			return Err(Error::TooManyProxies.into());
+		}
+		self.proxies
+			.mutate(caller, |proxies| proxies.get_or_insert_with(Vec::new).push(proxy));
+		Ok(())
+	}
+
+	/// Unregister `delegate` as a proxy of the `caller`.
+	pub fn remove_proxy(
+		&mut self,
+		caller: T::AccountId,
+		delegate: T::AccountId,
+		proxy_type: T::ProxyType,
+		delay: T::BlockNumber,
+	) -> DispatchResult {
+		let proxy = ProxyDefinition { delegate, proxy_type, delay };
+		self.proxies.mutate(caller, |proxies| {
+			let definitions = proxies.as_mut().ok_or(Error::NoSuchProxy)?;
+			let index = definitions.iter().position(|p| *p == proxy).ok_or(Error::NoSuchProxy)?;
+			definitions.remove(index);
+			if definitions.is_empty() {
+				*proxies = None;
+			}
+			Ok(())
+		})
+	}
+
+	/// Dispatch `call` on behalf of `real`, where the `caller` is a proxy of `real` without a
+	/// delay.
+	///
+	/// If `force_proxy_type` is given, only a proxy of that type is used.
+	#[call(weight = support::DEFAULT_CALL_WEIGHT + call.weight())]
+	pub fn proxy(
+		runtime: &mut T,
+		caller: T::AccountId,
+		real: T::AccountId,
+		force_proxy_type: Option<T::ProxyType>,
+		call: Box<T::RuntimeCall>,
+	) -> DispatchResult {
+		let proxy: &Self = runtime.pallet();
+		let definition = proxy.find_proxy(&real, &caller, force_proxy_type.as_ref())?;
+		if !definition.delay.is_zero() {
+			return Err(Error::Unannounced.into());
+		}
+		if !definition.proxy_type.filter(&call) {
+			return Err(Error::Unproxyable.into());
+		}
+		runtime.dispatch(real, *call)
+	}
+
+	/// Announce the call with `call_hash`, which the `caller` will make on behalf of `real` once
+	/// the delay of its proxy has passed.
+	pub fn announce(
+		runtime: &mut T,
+		caller: T::AccountId,
+		real: T::AccountId,
+		call_hash: support::Hash,
+	) -> DispatchResult {
+		let system: &system::Pallet<T> = runtime.pallet();
+		let height = system.block_number();
+
+		let proxy: &mut Self = runtime.pallet_mut();
+		proxy.find_proxy(&real, &caller, None)?;
+		if proxy.announcements(&caller).len() >= T::MAX_PENDING {
+			return Err(Error::TooManyAnnouncements.into());
+		}
+		let announcement = Announcement { real, call_hash, height };
+		proxy.announcements.mutate(caller, |announcements| {
+			announcements.get_or_insert_with(Vec::new).push(announcement)
+		});
+		Ok(())
+	}
+
+	/// Remove a call announced by the `caller`.
+	pub fn remove_announcement(
+		&mut self,
+		caller: T::AccountId,
+		real: T::AccountId,
+		call_hash: support::Hash,
+	) -> DispatchResult {
+		self.announcements.mutate(caller, |announcements| {
+			let pending = announcements.as_mut().ok_or(Error::NoSuchAnnouncement)?;
+			let index = pending
+				.iter()
+				.position(|a| a.real == real && a.call_hash == call_hash)
+				.ok_or(Error::NoSuchAnnouncement)?;
+			pending.remove(index);
+			if pending.is_empty() {
+				*announcements = None;
+			}
+			Ok(())
+		})
+	}
+
+	/// Dispatch `call` on behalf of `real`, where it was announced by the proxy `delegate` and the
+	/// delay of the proxy has passed.
+	///
+	/// Anyone can make this call once the announcement is due.
+	#[call(weight = support::DEFAULT_CALL_WEIGHT + call.weight())]
+	pub fn proxy_announced(
+		runtime: &mut T,
+		_caller: T::AccountId,
+		delegate: T::AccountId,
+		real: T::AccountId,
+		force_proxy_type: Option<T::ProxyType>,
+		call: Box<T::RuntimeCall>,
+	) -> DispatchResult {
+		let call_hash = support::hash(&call);
+		let system: &system::Pallet<T> = runtime.pallet();
+		let now = system.block_number();
+
+		let proxy: &Self = runtime.pallet();
+		let definition = proxy.find_proxy(&real, &delegate, force_proxy_type.as_ref())?;
+		let announcement = proxy
+			.announcements(&delegate)
+			.iter()
+			.find(|a| a.real == real && a.call_hash == call_hash)
+			.ok_or(Error::NoSuchAnnouncement)?;
+		let mut due = announcement.height;
+		due += definition.delay;
+		if due > now {
+			return Err(Error::AnnouncementNotDue.into());
+		}
+		if !definition.proxy_type.filter(&call) {
+			return Err(Error::Unproxyable.into());
+		}
+
+		runtime.dispatch(real.clone(), *call)?;
+
+		let proxy: &mut Self = runtime.pallet_mut();
+		proxy.remove_announcement(delegate, real, call_hash)
+	}
+}
+
+impl<T: Config> support::Hooks<T::BlockNumber> for Pallet<T> {}
+
+impl<T: Config> support::GetPalletMetadata for Pallet<T> {
+	fn error_metadata() -> Vec<support::VariantMetadata> {
+		<Error as support::GetErrorMetadata>::error_metadata()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		balances,
+		block_builder::{BlockBuilder, BlockLimits},
+		proof_of_existence, proxy,
+		support::{self, Dispatch, InherentData},
+		system, timestamp,
+	};
+
+	mod types {
+		pub type Extrinsic = crate::support::Extrinsic<String, super::RuntimeCall, u32, u128>;
+		pub type Block = crate::support::Block<
+			crate::support::Header<u32>,
+			crate::support::UncheckedExtrinsic<Extrinsic, super::RuntimeCall>,
+		>;
+	}
+
+	// A minimal runtime to dispatch proxy calls against.
+	#[macros::runtime]
+	#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+	struct Runtime {
+		system: system::Pallet<Self>,
+		balances: balances::Pallet<Self>,
+		proof_of_existence: proof_of_existence::Pallet<Self>,
+		proxy: proxy::Pallet<Self>,
+	}
+
+	impl system::Config for Runtime {
+		type AccountId = String;
+		type BlockNumber = u32;
+		type Nonce = u32;
+	}
+
+	impl balances::Config for Runtime {
+		type Balance = u128;
+	}
+
+	impl support::ChargeTransaction<types::Extrinsic> for Runtime {}
+
+	impl timestamp::Config for Runtime {
+		type Moment = u64;
+		const MINIMUM_PERIOD: u64 = 1;
+		const MAX_DRIFT: u64 = 0;
+	}
+
+	impl proof_of_existence::Config for Runtime {
+		type Content = String;
+	}
+
+	impl proxy::Config for Runtime {
+		type RuntimeCall = RuntimeCall;
+		type ProxyType = ProxyType;
+		const MAX_PROXIES: usize = 2;
+		const MAX_PENDING: usize = 2;
+	}
+
+	#[derive(
+		Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+	)]
+	enum ProxyType {
+		Any,
+		ProofOfExistence,
+	}
+
+	impl support::InstanceFilter<RuntimeCall> for ProxyType {
+		fn filter(&self, call: &RuntimeCall) -> bool {
+			match self {
+				ProxyType::Any => true,
+				ProxyType::ProofOfExistence => matches!(call, RuntimeCall::proof_of_existence(_)),
+			}
+		}
+	}
+
+	fn claim(claim: &str) -> RuntimeCall {
+		RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+			claim: claim.to_string(),
+		})
+	}
+
+	fn transfer(to: &str, amount: u128) -> RuntimeCall {
+		RuntimeCall::balances(balances::Call::transfer { to: to.to_string(), amount })
+	}
+
+	fn proxy(real: &str, call: RuntimeCall) -> RuntimeCall {
+		RuntimeCall::proxy(proxy::Call::proxy {
+			real: real.to_string(),
+			force_proxy_type: None,
+			call: Box::new(call),
+		})
+	}
+
+	// Build a block of `extrinsics` on a copy of the runtime, then execute it.
+	fn build_and_execute(
+		runtime: &mut Runtime,
+		extrinsics: Vec<types::Extrinsic>,
+	) -> support::DispatchResult {
+		let mut author = runtime.clone();
+		let limits = BlockLimits { max_weight: u64::MAX, max_size: usize::MAX };
+		let mut builder = BlockBuilder::new(&mut author, 0, &InherentData::new(), limits).unwrap();
+		for extrinsic in extrinsics {
+			builder.push(extrinsic);
+		}
+		runtime.execute_block(builder.build())
+	}
+
+	fn add_proxy(delegate: &str, proxy_type: ProxyType, delay: u32) -> RuntimeCall {
+		RuntimeCall::proxy(proxy::Call::add_proxy {
+			delegate: delegate.to_string(),
+			proxy_type,
+			delay,
+		})
+	}
+
+	// An error of the proxy pallet, as the runtime returns it.
+	fn error(error: proxy::Error) -> support::DispatchError {
+		support::DispatchError::from(error).in_pallet(3)
+	}
+
+	#[test]
+	fn add_and_remove_proxies() {
+		let mut runtime = Runtime::new();
+		let alice = "alice".to_string();
+
+		assert_eq!(runtime.dispatch(alice.clone(), add_proxy("bob", ProxyType::Any, 0)), Ok(()));
+		assert_eq!(
+			runtime.dispatch(alice.clone(), add_proxy("bob", ProxyType::Any, 0)),
+			Err(error(proxy::Error::Duplicate))
+		);
+		assert_eq!(
+			runtime.dispatch(alice.clone(), add_proxy("bob", ProxyType::ProofOfExistence, 0)),
+			Ok(())
+		);
+		assert_eq!(
+			runtime.dispatch(alice.clone(), add_proxy("charlie", ProxyType::Any, 0)),
+			Err(error(proxy::Error::TooManyProxies))
+		);
+		assert_eq!(runtime.proxy.proxies(&alice).len(), 2);
+
+		let remove = RuntimeCall::proxy(proxy::Call::remove_proxy {
+			delegate: "bob".to_string(),
+			proxy_type: ProxyType::Any,
+			delay: 0,
+		});
+		assert_eq!(runtime.dispatch(alice.clone(), remove), Ok(()));
+		assert_eq!(runtime.proxy.proxies(&alice).len(), 1);
+	}
+
+	#[test]
+	fn proxy_calls_are_filtered() {
+		let mut runtime = Runtime::new();
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		runtime.balances.set_balance(&alice, 100);
+
+		assert_eq!(
+			runtime.dispatch(bob.clone(), proxy("alice", claim("data"))),
+			Err(error(proxy::Error::NotProxy))
+		);
+		assert_eq!(
+			runtime.dispatch(alice.clone(), add_proxy("bob", ProxyType::ProofOfExistence, 0)),
+			Ok(())
+		);
+
+		assert_eq!(runtime.dispatch(bob.clone(), proxy("alice", claim("data"))), Ok(()));
+		assert_eq!(runtime.proof_of_existence.get_claim(&"data".to_string()), Some(&alice));
+		assert_eq!(
+			runtime.dispatch(bob.clone(), proxy("alice", transfer("bob", 10))),
+			Err(error(proxy::Error::Unproxyable))
+		);
+		assert_eq!(runtime.balances.balance(&bob), 0);
+	}
+
+	#[test]
+	fn announced_proxy_calls_wait_for_delay() {
+		let mut runtime = Runtime::new();
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		runtime.balances.set_balance(&alice, 100);
+
+		let call = transfer("bob", 10);
+		let call_hash = support::hash(&call);
+		let announce = RuntimeCall::proxy(proxy::Call::announce { real: alice.clone(), call_hash });
+		let proxy_announced = || {
+			RuntimeCall::proxy(proxy::Call::proxy_announced {
+				delegate: bob.clone(),
+				real: alice.clone(),
+				force_proxy_type: None,
+				call: Box::new(transfer("bob", 10)),
+			})
+		};
+
+		assert_eq!(runtime.dispatch(alice.clone(), add_proxy("bob", ProxyType::Any, 2)), Ok(()));
+		assert_eq!(
+			runtime.dispatch(bob.clone(), proxy("alice", call)),
+			Err(error(proxy::Error::Unannounced))
+		);
+		assert_eq!(
+			runtime.dispatch(bob.clone(), proxy_announced()),
+			Err(error(proxy::Error::NoSuchAnnouncement))
+		);
+
+		// Announce at block 1, so the call is due at block 3.
+		let extrinsic = support::Extrinsic::new_signed(bob.clone(), announce, 0, 0);
+		assert_eq!(build_and_execute(&mut runtime, vec![extrinsic]), Ok(()));
+		assert_eq!(runtime.proxy.announcements(&bob).len(), 1);
+
+		assert_eq!(build_and_execute(&mut runtime, vec![]), Ok(()));
+		assert_eq!(
+			runtime.dispatch("charlie".to_string(), proxy_announced()),
+			Err(error(proxy::Error::AnnouncementNotDue))
+		);
+
+		assert_eq!(build_and_execute(&mut runtime, vec![]), Ok(()));
+		assert_eq!(runtime.dispatch("charlie".to_string(), proxy_announced()), Ok(()));
+		assert_eq!(runtime.balances.balance(&bob), 10);
+		assert!(runtime.proxy.announcements(&bob).is_empty());
+	}
+}
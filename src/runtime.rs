@@ -0,0 +1,304 @@
+use crate::{
+	assets, aura, balances, multisig, proof_of_existence, proxy,
+	support::{self, OnRuntimeUpgrade},
+	system, timestamp, vesting,
+};
+use serde::{Deserialize, Serialize};
+
+/// Concrete types used throughout the runtime.
+pub mod types {
+	pub type AccountId = String;
+	pub type Balance = u128;
+	pub type BlockNumber = u32;
+	pub type Nonce = u32;
+	pub type Content = String;
+	pub type AssetId = u32;
+	pub type Moment = u64;
+
+	pub type Extrinsic = crate::support::Extrinsic<AccountId, super::RuntimeCall, Nonce, Balance>;
+	pub type Header = crate::support::Header<BlockNumber>;
+	pub type Block = crate::support::Block<
+		Header,
+		crate::support::UncheckedExtrinsic<Extrinsic, super::RuntimeCall>,
+	>;
+}
+
+/// The main runtime struct.
+///
+/// The `#[macros::runtime]` attribute automatically generates the `RuntimeCall` enum,
+/// the `new()` and `execute_block()` functions, and the `Dispatch` trait implementation.
+#[macros::runtime]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Runtime {
+	#[pallet_index(0)]
+	#[event]
+	pub system: system::Pallet<Self>,
+	#[pallet_index(1)]
+	#[event]
+	pub balances: balances::Pallet<Self>,
+	#[pallet_index(2)]
+	#[event]
+	pub proof_of_existence: proof_of_existence::Pallet<Self>,
+	#[pallet_index(3)]
+	pub multisig: multisig::Pallet<Self>,
+	#[pallet_index(4)]
+	pub proxy: proxy::Pallet<Self>,
+	#[pallet_index(5)]
+	pub vesting: vesting::Pallet<Self>,
+	#[pallet_index(6)]
+	pub assets: assets::Pallet<Self>,
+	#[pallet_index(7)]
+	pub aura: aura::Pallet<Self>,
+	#[pallet_index(8)]
+	#[inherent]
+	pub timestamp: timestamp::Pallet<Self>,
+	#[pallet_index(9)]
+	#[event]
+	pub rewards: balances::Pallet<Self, support::Instance1>,
+	#[pallet_index(10)]
+	#[event]
+	pub notary: proof_of_existence::Pallet<Self, support::Instance1>,
+}
+
+/// Implements the `system::Config` trait for the `Runtime`.
+impl system::Config for Runtime {
+	type AccountId = types::AccountId;
+	type BlockNumber = types::BlockNumber;
+	type Nonce = types::Nonce;
+
+	const VERSION: support::RuntimeVersion = support::RuntimeVersion {
+		spec_name: "rust-state-machine",
+		spec_version: 1,
+		transaction_version: 1,
+	};
+
+	// Run the migrations of the pallets whose storage changed since the last version. With the
+	// `try-runtime` feature, the migrations also check the state before and after they run.
+	fn on_runtime_upgrade(&mut self) -> support::DispatchResult {
+		#[cfg(not(feature = "try-runtime"))]
+		balances::migrations::MigrateToV1::on_runtime_upgrade(self);
+		#[cfg(feature = "try-runtime")]
+		balances::migrations::MigrateToV1::try_on_runtime_upgrade(self)?;
+		Ok(())
+	}
+}
+
+/// Implements the `balances::Config` trait for the `Runtime`.
+impl balances::Config for Runtime {
+	type Balance = types::Balance;
+}
+
+/// Implements the `balances::Config` trait for the `Runtime`, for the reward token.
+impl balances::Config<support::Instance1> for Runtime {
+	type Balance = types::Balance;
+}
+
+/// Implements the `proof_of_existence::Config` trait for the `Runtime`.
+impl proof_of_existence::Config for Runtime {
+	type Content = types::Content;
+}
+
+/// Implements the `proof_of_existence::Config` trait for the `Runtime`, for the notary, a second
+/// registry of claims.
+impl proof_of_existence::Config<support::Instance1> for Runtime {
+	type Content = types::Content;
+}
+
+/// Implements the `multisig::Config` trait for the `Runtime`.
+impl multisig::Config for Runtime {
+	type RuntimeCall = RuntimeCall;
+	const DEPOSIT: types::Balance = 5;
+	const MAX_SIGNATORIES: usize = 10;
+}
+
+/// Implements the `proxy::Config` trait for the `Runtime`.
+impl proxy::Config for Runtime {
+	type RuntimeCall = RuntimeCall;
+	type ProxyType = ProxyType;
+	const MAX_PROXIES: usize = 8;
+	const MAX_PENDING: usize = 8;
+}
+
+/// Implements the `vesting::Config` trait for the `Runtime`.
+impl vesting::Config for Runtime {
+	const MIN_VESTED_TRANSFER: types::Balance = 10;
+	const MAX_VESTING_SCHEDULES: usize = 8;
+}
+
+/// Implements the `assets::Config` trait for the `Runtime`.
+impl assets::Config for Runtime {
+	type AssetId = types::AssetId;
+	type Balance = types::Balance;
+}
+
+/// Implements the `aura::Config` trait for the `Runtime`.
+impl aura::Config for Runtime {
+	const MAX_AUTHORITIES: usize = 8;
+}
+
+/// The time between two slots, in milliseconds.
+pub const SLOT_DURATION: types::Moment = 6_000;
+
+/// Implements the `timestamp::Config` trait for the `Runtime`.
+impl timestamp::Config for Runtime {
+	type Moment = types::Moment;
+	const MINIMUM_PERIOD: types::Moment = SLOT_DURATION / 2;
+	const MAX_DRIFT: types::Moment = 30_000;
+
+	fn on_timestamp_set(&mut self, now: types::Moment) {
+		self.proof_of_existence.set_now(now);
+		self.notary.set_now(now);
+	}
+}
+
+/// The fee charged for every extrinsic, on top of its tip.
+pub const BASE_FEE: types::Balance = 1;
+
+/// Charges every extrinsic the base fee plus its tip, and prioritises extrinsics by their tip.
+impl support::ChargeTransaction<types::Extrinsic> for Runtime {
+	fn validate_payment(&self, extrinsic: &types::Extrinsic) -> Result<u64, &'static str> {
+		if self.balances.balance(&extrinsic.caller) < BASE_FEE.saturating_add(extrinsic.tip) {
+			return Err("not enough funds to pay the fee");
+		}
+		Ok(u64::try_from(extrinsic.tip).unwrap_or(u64::MAX))
+	}
+
+	fn withdraw_fee(&mut self, extrinsic: &types::Extrinsic) -> support::DispatchResult {
+		self.balances
+			.withdraw(&extrinsic.caller, BASE_FEE.saturating_add(extrinsic.tip))
+	}
+}
+
+/// Blocks are authored in turns by the authorities of the Aura pallet, each in the slot of the
+/// time set by its timestamp inherent.
+impl support::VerifyHeader for Runtime {
+	fn verify_header(
+		&self,
+		header: &support::Header<types::BlockNumber>,
+		extrinsics: &[Self::Extrinsic],
+		parent_slot: support::Slot,
+	) -> support::DispatchResult {
+		let now = extrinsics
+			.iter()
+			.find_map(|extrinsic| match extrinsic {
+				support::UncheckedExtrinsic::Inherent(RuntimeCall::timestamp(
+					timestamp::Call::set { now },
+				)) => Some(*now),
+				_ => None,
+			})
+			.ok_or("block is missing a mandatory inherent")?;
+		if header.digest.slot != now / SLOT_DURATION {
+			return Err("slot does not match the timestamp of the block".into());
+		}
+		self.aura.verify_header(header, parent_slot)
+	}
+}
+
+/// The kinds of proxy an account can register, each allowing a different set of calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ProxyType {
+	/// Allows all calls.
+	Any,
+	/// Allows only calls which cannot move funds: remarks, claims, and unlocking vested funds.
+	NonTransfer,
+	/// Allows only Proof of Existence calls.
+	ProofOfExistence,
+}
+
+impl support::InstanceFilter<RuntimeCall> for ProxyType {
+	fn filter(&self, call: &RuntimeCall) -> bool {
+		match self {
+			ProxyType::Any => true,
+			ProxyType::NonTransfer => matches!(
+				call,
+				RuntimeCall::system(
+					system::Call::remark { .. } | system::Call::remark_with_event { .. }
+				) | RuntimeCall::proof_of_existence(_) |
+					RuntimeCall::notary(_) |
+					RuntimeCall::vesting(vesting::Call::vest {})
+			),
+			ProxyType::ProofOfExistence => matches!(call, RuntimeCall::proof_of_existence(_)),
+		}
+	}
+}
+
+// The `enum RuntimeCall`, `impl Runtime`, and `impl support::Dispatch for Runtime`
+// are now all generated automatically by the `#[macros::runtime]` attribute.
+
+#[cfg(test)]
+mod tests {
+	use super::{ProxyType, Runtime, RuntimeCall, SLOT_DURATION};
+	use crate::{
+		assets, aura, balances, proof_of_existence,
+		support::{self, InstanceFilter, VerifyHeader},
+		system, timestamp, vesting,
+	};
+
+	#[test]
+	fn non_transfer_proxies_cannot_move_funds() {
+		let allowed = [
+			RuntimeCall::system(system::Call::remark { remark: vec![1] }),
+			RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+				claim: "Hello".to_string(),
+			}),
+			RuntimeCall::notary(proof_of_existence::Call::create_claim {
+				claim: "Hello".to_string(),
+			}),
+			RuntimeCall::vesting(vesting::Call::vest {}),
+		];
+		for call in &allowed {
+			assert!(ProxyType::NonTransfer.filter(call), "{} is not allowed", call);
+		}
+
+		let transfers = [
+			RuntimeCall::balances(balances::Call::transfer { to: "bob".to_string(), amount: 10 }),
+			RuntimeCall::rewards(balances::Call::transfer { to: "bob".to_string(), amount: 10 }),
+			RuntimeCall::vesting(vesting::Call::vested_transfer {
+				target: "bob".to_string(),
+				schedule: vesting::VestingInfo { locked: 10, per_block: 1, starting_block: 0 },
+			}),
+			RuntimeCall::assets(assets::Call::transfer {
+				id: 1,
+				target: "bob".to_string(),
+				amount: 10,
+			}),
+			RuntimeCall::system(system::Call::set_code { spec_version: 2 }),
+		];
+		for call in &transfers {
+			assert!(!ProxyType::NonTransfer.filter(call), "{} is allowed", call);
+			assert!(ProxyType::Any.filter(call));
+		}
+	}
+
+	#[test]
+	fn slot_follows_timestamp() {
+		let runtime = Runtime::new();
+		let digest = support::Digest { slot: 2, seal: None };
+		let header = support::Header {
+			parent_hash: 0,
+			block_number: 1,
+			extrinsics_root: 0,
+			state_root: 0,
+			digest,
+		};
+		let set = |now| {
+			support::UncheckedExtrinsic::Inherent(RuntimeCall::timestamp(timestamp::Call::set {
+				now,
+			}))
+		};
+
+		assert_eq!(
+			runtime.verify_header(&header, &[], 1),
+			Err("block is missing a mandatory inherent".into())
+		);
+		assert_eq!(
+			runtime.verify_header(&header, &[set(3 * SLOT_DURATION)], 1),
+			Err("slot does not match the timestamp of the block".into())
+		);
+		// The slot matches the timestamp, so the header is checked against the authorities.
+		assert_eq!(
+			runtime.verify_header(&header, &[set(2 * SLOT_DURATION + 1)], 1),
+			Err(aura::Error::NoAuthorities.into())
+		);
+	}
+}
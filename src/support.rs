@@ -1,4 +1,13 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// The macros name serde through this module, so that their code only needs `support`.
+pub use serde;
+
 /// A generic representation of a blockchain block.
+///
+/// Blocks, and the extrinsics they hold, are encoded with serde, such as to JSON by the node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Block<Header, Extrinsic> {
 	/// The block header, containing metadata about the block.
 	pub header: Header,
@@ -6,23 +15,506 @@ pub struct Block<Header, Extrinsic> {
 	pub extrinsics: Vec<Extrinsic>,
 }
 
-/// A simplified block header containing only the block number.
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// A simplified block header.
+///
+/// The roots commit to the extrinsics of the block and to the state after executing them, so any
+/// node executing the block can check it reaches the same state as the block author.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Header<BlockNumber> {
+	/// The hash of the header of the previous block.
+	pub parent_hash: Hash,
 	pub block_number: BlockNumber,
+	/// The hash of the extrinsics of the block.
+	pub extrinsics_root: Hash,
+	/// The hash of the state after executing the block.
+	pub state_root: Hash,
+	/// The consensus data added by the block author.
+	pub digest: Digest,
+}
+
+impl<BlockNumber: core::hash::Hash> Header<BlockNumber> {
+	/// Get the hash of this header, which identifies the block.
+	///
+	/// The seal is left out, since it is a signature over this hash.
+	pub fn hash(&self) -> Hash {
+		let Self { parent_hash, block_number, extrinsics_root, state_root, digest } = self;
+		hash(&(parent_hash, block_number, extrinsics_root, state_root, digest.slot))
+	}
+
+	/// Seal this header as its `author`, by signing its hash.
+	pub fn seal<Author: core::hash::Hash + ?Sized>(&mut self, author: &Author) {
+		self.digest.seal = Some(Signature::sign(author, self.hash()));
+	}
+
+	/// Returns true if this header is sealed by `author`.
+	pub fn verify_seal<Author: core::hash::Hash + ?Sized>(&self, author: &Author) -> bool {
+		self.digest.seal.is_some_and(|seal| seal.verify(author, self.hash()))
+	}
+}
+
+/// A slot of time in which one block can be authored.
+pub type Slot = u64;
+
+/// The consensus data in a block header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Digest {
+	/// The slot in which the block was authored.
+	pub slot: Slot,
+	/// The signature of the block author over the header, added once the block is built.
+	pub seal: Option<Signature>,
 }
 
 /// An "extrinsic," representing an external message from outside the blockchain.
 ///
-/// Contains the caller and the specific call to be executed.
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Extrinsic<Caller, Call> {
+/// Contains the caller and the specific call to be executed, along with the caller's nonce, an
+/// optional tip to prioritise the extrinsic, and the caller's signature over all of these.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Extrinsic<Caller, Call, Nonce, Balance> {
 	pub caller: Caller,
 	pub call: Call,
+	pub nonce: Nonce,
+	pub tip: Balance,
+	pub signature: Signature,
+}
+
+impl<Caller, Call, Nonce, Balance> Extrinsic<Caller, Call, Nonce, Balance>
+where
+	Caller: core::hash::Hash,
+	Call: core::hash::Hash,
+	Nonce: core::hash::Hash,
+	Balance: core::hash::Hash,
+{
+	/// Construct an extrinsic signed by `caller`.
+	pub fn new_signed(caller: Caller, call: Call, nonce: Nonce, tip: Balance) -> Self {
+		let payload = hash(&(&caller, &call, &nonce, &tip));
+		let signature = Signature::sign(&caller, payload);
+		Self { caller, call, nonce, tip, signature }
+	}
+
+	/// Get the payload which the caller signs: everything in the extrinsic but the signature.
+	pub fn signing_payload(&self) -> Hash {
+		hash(&(&self.caller, &self.call, &self.nonce, &self.tip))
+	}
+
+	/// Returns true if the signature of this extrinsic is valid for its caller and payload.
+	pub fn verify(&self) -> bool {
+		self.signature.verify(&self.caller, self.signing_payload())
+	}
+}
+
+impl<Caller, Call: GetWeight, Nonce, Balance> GetWeight
+	for Extrinsic<Caller, Call, Nonce, Balance>
+{
+	fn weight(&self) -> Weight {
+		self.call.weight()
+	}
+}
+
+/// An extrinsic as it appears in a block: either signed by a caller, or an unsigned inherent.
+///
+/// Inherents carry data provided by the block author, such as the current time. They have no
+/// caller, nonce or fee, and are dispatched with `Dispatchable::dispatch_inherent`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum UncheckedExtrinsic<Signed, Call> {
+	/// A signed extrinsic, as submitted to the transaction pool.
+	Signed(Signed),
+	/// An inherent added by the block author.
+	Inherent(Call),
+}
+
+impl<Signed, Call> UncheckedExtrinsic<Signed, Call> {
+	/// Get the signed extrinsic, if this is not an inherent.
+	pub fn signed(&self) -> Option<&Signed> {
+		match self {
+			Self::Signed(extrinsic) => Some(extrinsic),
+			Self::Inherent(_) => None,
+		}
+	}
+}
+
+impl<Signed, Call> From<Signed> for UncheckedExtrinsic<Signed, Call> {
+	fn from(extrinsic: Signed) -> Self {
+		Self::Signed(extrinsic)
+	}
+}
+
+impl<Signed: GetWeight, Call: GetWeight> GetWeight for UncheckedExtrinsic<Signed, Call> {
+	fn weight(&self) -> Weight {
+		match self {
+			Self::Signed(extrinsic) => extrinsic.weight(),
+			Self::Inherent(call) => call.weight(),
+		}
+	}
+}
+
+/// A stand-in for a cryptographic signature over a payload.
+///
+/// This is just a hash of the signer and the payload, so anyone can produce it. It lets the runtime
+/// reject extrinsics whose contents were changed after signing, or which claim the wrong caller,
+/// but a real chain would use a scheme where only the holder of a secret key can sign.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Signature(Hash);
+
+impl Signature {
+	/// Sign `payload` as `signer`.
+	pub fn sign<Signer: core::hash::Hash + ?Sized>(signer: &Signer, payload: Hash) -> Self {
+		Self(hash(&(signer, payload)))
+	}
+
+	/// Returns true if this is the signature of `signer` over `payload`.
+	pub fn verify<Signer: core::hash::Hash + ?Sized>(
+		&self,
+		signer: &Signer,
+		payload: Hash,
+	) -> bool {
+		*self == Self::sign(signer, payload)
+	}
 }
 
 /// A result type for dispatchable functions within the runtime.
-pub type DispatchResult = Result<(), &'static str>;
+pub type DispatchResult = Result<(), DispatchError>;
+
+/// An error returned by a call.
+///
+/// The errors of the error enum of a pallet know their index in the pallet, but not the index of
+/// the pallet in the runtime, which the runtime adds when the call returns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum DispatchError {
+	/// An error described only by its message.
+	Other(&'static str),
+	/// An error of the error enum declared in `module`, whose pallet is not yet known.
+	Pallet {
+		/// The path of the module which declares the error enum.
+		module: &'static str,
+		/// The index of the error in the enum.
+		error: u8,
+		/// The name of the error.
+		message: &'static str,
+	},
+	/// An error of the error enum of a pallet of the runtime.
+	Module {
+		/// The indices of the pallet and of the error.
+		error: ModuleError,
+		/// The name of the error.
+		message: &'static str,
+	},
+}
+
+impl DispatchError {
+	/// Get the message of the error.
+	pub fn message(&self) -> &'static str {
+		match self {
+			Self::Other(message) | Self::Pallet { message, .. } | Self::Module { message, .. } =>
+				message,
+		}
+	}
+
+	/// Get the encoding of the error, if it is an error of a pallet of the runtime.
+	pub fn module_error(&self) -> Option<ModuleError> {
+		match self {
+			Self::Module { error, .. } => Some(*error),
+			_ => None,
+		}
+	}
+
+	/// Find the pallet of an error returned by a call of the pallet at index `called`, among the
+	/// `pallets` of the runtime, given as their indices and type names.
+	///
+	/// The pallet is the one declared in the module of the error enum, and the called pallet if
+	/// several instances of it are, so that errors of other pallets used by a call are not taken
+	/// for errors of the called pallet. Other errors are left as they are.
+	pub fn in_runtime(self, called: u8, pallets: &[(u8, &str)]) -> Self {
+		let Self::Pallet { module, .. } = self else {
+			return self;
+		};
+		let declared_in = |name: &str| {
+			name.strip_prefix(module).is_some_and(|name| name.starts_with("::Pallet<"))
+		};
+		let candidates = pallets
+			.iter()
+			.filter(|(_, name)| declared_in(name))
+			.map(|(index, _)| *index)
+			.collect::<Vec<_>>();
+		let index =
+			if candidates.contains(&called) { Some(called) } else { candidates.first().copied() };
+		match index {
+			Some(index) => self.in_pallet(index),
+			None => self,
+		}
+	}
+
+	/// Give an error of the error enum of a pallet the `index` of the pallet in the runtime.
+	/// Other errors are left as they are.
+	pub fn in_pallet(self, index: u8) -> Self {
+		match self {
+			Self::Pallet { error, message, .. } =>
+				Self::Module { error: ModuleError { index, error }, message },
+			_ => self,
+		}
+	}
+}
+
+impl From<&'static str> for DispatchError {
+	fn from(message: &'static str) -> Self {
+		Self::Other(message)
+	}
+}
+
+// The outer results of extrinsics, which make them invalid, only keep the message of an error.
+impl From<DispatchError> for &'static str {
+	fn from(error: DispatchError) -> Self {
+		error.message()
+	}
+}
+
+impl core::fmt::Display for DispatchError {
+	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+		f.write_str(self.message())
+	}
+}
+
+/// The result of applying an extrinsic to the runtime.
+///
+/// The outer error means the extrinsic is invalid, for example because of a bad signature or nonce,
+/// and could not be included in a block. Otherwise the inner result is the result of dispatching
+/// its call, and the extrinsic was included whether or not the call succeeded.
+pub type ApplyExtrinsicResult = Result<DispatchResult, &'static str>;
+
+/// A measure of the time it takes to execute a call, used to limit the work done in a block.
+pub type Weight = u64;
+
+/// The weight of calls which do not declare their own.
+pub const DEFAULT_CALL_WEIGHT: Weight = 10_000;
+
+/// A trait for calls which know their weight.
+///
+/// `#[macros::call]` gives every call the default weight, unless it declares its own with
+/// `#[call(weight = expr)]`, as calls which dispatch other calls do, to add their weight.
+pub trait GetWeight {
+	/// Get the weight of this call.
+	fn weight(&self) -> Weight;
+}
+
+/// The runtime API used to execute a block one extrinsic at a time.
+///
+/// The `#[macros::runtime]` attribute implements this, and builds `execute_block` from the same
+/// steps, so that a block built with them executes identically.
+pub trait BuildBlock {
+	/// The type used to represent the block number.
+	type BlockNumber;
+	/// The type of extrinsic in a block.
+	type Extrinsic;
+
+	/// Start a new block with the given `digest` on top of the current state, and return its
+	/// header without the roots.
+	fn initialize_block(&mut self, digest: Digest) -> Header<Self::BlockNumber>;
+	/// Apply an extrinsic to the current block. The state is unchanged if the extrinsic is invalid.
+	fn apply_extrinsic(&mut self, extrinsic: Self::Extrinsic) -> ApplyExtrinsicResult;
+	/// Finish the current block: set the state root of its `header`, and record it as the parent
+	/// of the next block.
+	fn finalize_block(&mut self, header: Header<Self::BlockNumber>) -> Header<Self::BlockNumber>;
+	/// Create the inherents of a new block from the author's inherent `data`.
+	fn inherent_extrinsics(&self, data: &InherentData) -> Vec<Self::Extrinsic>;
+	/// Check the inherents of `block` against the inherent `data` of the node importing it, on the
+	/// state of its parent.
+	fn check_inherents(
+		&self,
+		block: &Block<Header<Self::BlockNumber>, Self::Extrinsic>,
+		data: &InherentData,
+	) -> DispatchResult;
+}
+
+/// An identifier for a kind of inherent data, such as the current time.
+pub type InherentIdentifier = [u8; 8];
+
+/// The data a node provides for the inherents of a block, such as the current time.
+///
+/// The block author creates the inherents of a new block from its own data, and nodes importing
+/// the block check its inherents against theirs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InherentData(BTreeMap<InherentIdentifier, u64>);
+
+impl InherentData {
+	/// Create empty inherent data.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Provide the data identified by `identifier`.
+	pub fn put(&mut self, identifier: InherentIdentifier, value: u64) {
+		self.0.insert(identifier, value);
+	}
+
+	/// Get the data identified by `identifier`, if it is provided.
+	pub fn get(&self, identifier: &InherentIdentifier) -> Option<u64> {
+		self.0.get(identifier).copied()
+	}
+}
+
+/// A trait for pallets which provide inherents.
+///
+/// The `#[macros::runtime]` attribute uses this for the pallets marked with `#[inherent]`.
+pub trait ProvideInherent {
+	/// The call type of the pallet.
+	type Call;
+	/// True if every block must include an inherent of this pallet.
+	const IS_MANDATORY: bool;
+
+	/// Create the inherent of a new block from the author's inherent `data`, if there is one.
+	fn create_inherent(&self, data: &InherentData) -> Option<Self::Call>;
+
+	/// Check an inherent of this pallet in a block being imported, against the node's own inherent
+	/// `data`.
+	fn check_inherent(&self, _call: &Self::Call, _data: &InherentData) -> DispatchResult {
+		Ok(())
+	}
+}
+
+/// Functions called on every pallet as each block is built or executed.
+///
+/// The `#[macros::runtime]` attribute calls these on every pallet other than system, so every
+/// pallet must implement this, even if only with the default methods.
+pub trait Hooks<BlockNumber> {
+	/// Called at the start of block `block_number`, before its inherents and extrinsics.
+	fn on_initialize(&mut self, _block_number: BlockNumber) {}
+
+	/// Called at the end of block `block_number`, after its extrinsics, before the state root is
+	/// taken.
+	fn on_finalize(&mut self, _block_number: BlockNumber) {}
+}
+
+/// The initial state of a pallet, set up before the first block.
+///
+/// `#[macros::pallet]` uses this to construct a pallet from its `GenesisConfig`.
+pub trait BuildGenesisConfig<Pallet> {
+	/// Set up the genesis state of `pallet`, which is newly constructed.
+	fn build(&self, pallet: &mut Pallet) -> DispatchResult;
+}
+
+/// The runtime API used to import blocks built by other nodes.
+///
+/// The `#[macros::runtime]` attribute implements this with the generated `execute_block`.
+pub trait ExecuteBlock: BuildBlock {
+	/// Execute `block` on top of the current state, checking it is valid.
+	fn execute_block(
+		&mut self,
+		block: Block<Header<Self::BlockNumber>, Self::Extrinsic>,
+	) -> DispatchResult;
+}
+
+/// The runtime API used to check the consensus data of a block before it is executed.
+///
+/// This is called on the state of the parent of the block, with the extrinsics of the block, so
+/// that the slot can be checked against its inherents. By default every header is valid, so any
+/// node can author blocks in any slot.
+pub trait VerifyHeader: BuildBlock {
+	/// Check that `header`, whose block has `extrinsics`, may be built on top of the current
+	/// state, whose last block was authored in `parent_slot`.
+	fn verify_header(
+		&self,
+		_header: &Header<Self::BlockNumber>,
+		_extrinsics: &[Self::Extrinsic],
+		_parent_slot: Slot,
+	) -> DispatchResult {
+		Ok(())
+	}
+}
+
+/// The version of a runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RuntimeVersion {
+	/// The name of the runtime, which stays the same across upgrades.
+	pub spec_name: &'static str,
+	/// Increased whenever the behavior of the runtime changes. The migrations run once, at the
+	/// start of the first block executed by a runtime with a higher spec version.
+	pub spec_version: u32,
+	/// Increased whenever the format of extrinsics or their calls changes.
+	pub transaction_version: u32,
+}
+
+/// The version of the layout of a pallet's storage.
+pub type StorageVersion = u16;
+
+/// Implemented by pallets which version the layout of their storage, so that a migration can tell
+/// which layout the state is in.
+pub trait GetStorageVersion {
+	/// The version of the layout this code uses.
+	const STORAGE_VERSION: StorageVersion;
+
+	/// The version of the layout the state is in.
+	fn on_chain_storage_version(&self) -> StorageVersion;
+}
+
+/// A migration of the state of a runtime `T`, from the storage layout of an older version of the
+/// runtime to the current one.
+///
+/// With the `try-runtime` feature, and in tests, a migration can also check the state before and
+/// after it runs.
+pub trait OnRuntimeUpgrade<T> {
+	/// What `pre_upgrade` records about the state, for `post_upgrade` to check against.
+	#[cfg(any(test, feature = "try-runtime"))]
+	type State;
+
+	/// Migrate the state of `runtime`.
+	fn on_runtime_upgrade(runtime: &mut T);
+
+	/// Check the state before the migration, and record what `post_upgrade` needs.
+	#[cfg(any(test, feature = "try-runtime"))]
+	fn pre_upgrade(runtime: &T) -> Result<Self::State, &'static str>;
+
+	/// Check the state after the migration, against what `pre_upgrade` recorded.
+	#[cfg(any(test, feature = "try-runtime"))]
+	fn post_upgrade(runtime: &T, state: Self::State) -> DispatchResult;
+
+	/// Run the migration between its pre and post checks.
+	#[cfg(any(test, feature = "try-runtime"))]
+	fn try_on_runtime_upgrade(runtime: &mut T) -> DispatchResult {
+		let state = Self::pre_upgrade(runtime)?;
+		Self::on_runtime_upgrade(runtime);
+		Self::post_upgrade(runtime, state)
+	}
+}
+
+/// Information about a valid transaction, which the transaction pool uses to order it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValidTransaction {
+	/// Transactions with a higher priority are included in blocks first.
+	pub priority: u64,
+	/// True if the transaction's nonce is ahead of its sender's account nonce, so it can only be
+	/// included after the transactions before it.
+	pub future: bool,
+}
+
+/// The result of validating a transaction against the current state.
+pub type TransactionValidity = Result<ValidTransaction, &'static str>;
+
+/// The runtime API used to check transactions before they are included in a block.
+///
+/// The `#[macros::runtime]` attribute implements this by checking the signature and nonce of the
+/// extrinsic, and whether its caller can pay for it through `ChargeTransaction`.
+pub trait ValidateTransaction {
+	/// The type of extrinsic which is validated.
+	type Extrinsic;
+
+	/// Check whether `extrinsic` could be included in a block built on the current state, without
+	/// changing the state.
+	fn validate_transaction(&self, extrinsic: &Self::Extrinsic) -> TransactionValidity;
+}
+
+/// A trait for runtimes to charge a fee for each extrinsic included in a block.
+///
+/// By default extrinsics are free and all have the same priority.
+pub trait ChargeTransaction<Extrinsic> {
+	/// Check that the caller of `extrinsic` can pay for it, and return its priority.
+	fn validate_payment(&self, _extrinsic: &Extrinsic) -> Result<u64, &'static str> {
+		Ok(0)
+	}
+
+	/// Withdraw the fee of `extrinsic` from its caller, just before its call is dispatched.
+	fn withdraw_fee(&mut self, _extrinsic: &Extrinsic) -> DispatchResult {
+		Ok(())
+	}
+}
 
 /// A trait for dispatching extrinsics to the appropriate runtime function.
 pub trait Dispatch {
@@ -34,3 +526,475 @@ pub trait Dispatch {
 	/// Dispatches a `call` on behalf of a `caller`.
 	fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> DispatchResult;
 }
+
+/// A trait for calls which are dispatched with access to the whole `Runtime`.
+///
+/// `#[macros::call]` implements this for the `Call` of every pallet, reaching the pallet through
+/// `HasPallet`, or passing the whole runtime to calls which act on other pallets, or which dispatch
+/// nested calls.
+pub trait Dispatchable<Runtime> {
+	/// The type representing the caller of the call.
+	type Caller;
+
+	/// Dispatches this call on behalf of a `caller`.
+	fn dispatch(self, runtime: &mut Runtime, caller: Self::Caller) -> DispatchResult;
+
+	/// Dispatches this call as an inherent, without a caller. Most calls are not inherents.
+	fn dispatch_inherent(self, _runtime: &mut Runtime) -> DispatchResult
+	where
+		Self: Sized,
+	{
+		Err("call is not an inherent".into())
+	}
+}
+
+/// Write a call as `name(arg: value, ..)`, with the `Debug` of each argument.
+///
+/// This is the `Display` of the `Call` enums, which `#[macros::call]` generates. `RuntimeCall` adds
+/// the name of the pallet in front.
+pub fn fmt_call(
+	f: &mut core::fmt::Formatter,
+	name: &str,
+	args: &[(&str, &dyn core::fmt::Debug)],
+) -> core::fmt::Result {
+	write!(f, "{}(", name)?;
+	for (position, (arg, value)) in args.iter().enumerate() {
+		if position > 0 {
+			write!(f, ", ")?;
+		}
+		write!(f, "{}: {:?}", arg, value)?;
+	}
+	write!(f, ")")
+}
+
+/// A trait to reset the storage of a pallet of the `Runtime` to its initial state.
+///
+/// The `#[macros::runtime]` attribute implements this for the runtime, for every pallet other than
+/// system, whose storage holds the chain itself.
+pub trait KillStorage {
+	/// Reset the storage of the pallet with the index `pallet_index`.
+	fn kill_storage(&mut self, pallet_index: u8) -> DispatchResult;
+}
+
+/// A trait giving access to a pallet stored in the `Runtime`.
+///
+/// The `#[macros::runtime]` attribute implements this for every pallet in the runtime.
+pub trait HasPallet<Pallet> {
+	/// Get a reference to the pallet.
+	fn pallet(&self) -> &Pallet;
+	/// Get a mutable reference to the pallet.
+	fn pallet_mut(&mut self) -> &mut Pallet;
+}
+
+/// The second instance of a pallet which can be included in the `Runtime` several times, such as
+/// `balances::Pallet<Runtime, Instance1>`. The default instance of a pallet is `()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Instance1;
+
+/// A trait for filters which decide whether a call is allowed, such as the proxy types of the
+/// Proxy pallet.
+pub trait InstanceFilter<Call> {
+	/// Returns true if `call` is allowed by this filter.
+	fn filter(&self, call: &Call) -> bool;
+}
+
+/// A single value in the storage of a pallet, declared with `#[macros::storage]`.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct StorageValue<V>(V);
+
+// Storage is printed as what it holds, such as when it is read by name from the node.
+impl<V: core::fmt::Debug> core::fmt::Debug for StorageValue<V> {
+	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+		self.0.fmt(f)
+	}
+}
+
+impl<V> StorageValue<V> {
+	/// Create the value, starting at `value`.
+	pub fn new(value: V) -> Self {
+		Self(value)
+	}
+
+	/// Get the value.
+	pub fn get(&self) -> &V {
+		&self.0
+	}
+
+	/// Replace the value.
+	pub fn put(&mut self, value: V) {
+		self.0 = value;
+	}
+
+	/// Change the value in place, returning the result of `f`.
+	pub fn mutate<R>(&mut self, f: impl FnOnce(&mut V) -> R) -> R {
+		f(&mut self.0)
+	}
+}
+
+/// A map from keys to values in the storage of a pallet, declared with `#[macros::storage]`.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct StorageMap<K, V>(BTreeMap<K, V>);
+
+impl<K: core::fmt::Debug, V: core::fmt::Debug> core::fmt::Debug for StorageMap<K, V> {
+	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+		self.0.fmt(f)
+	}
+}
+
+impl<K, V> Default for StorageMap<K, V> {
+	fn default() -> Self {
+		Self(BTreeMap::new())
+	}
+}
+
+impl<K: Ord, V> StorageMap<K, V> {
+	/// Get the value of `key`, if there is one.
+	pub fn get(&self, key: &K) -> Option<&V> {
+		self.0.get(key)
+	}
+
+	/// Check if `key` has a value.
+	pub fn contains_key(&self, key: &K) -> bool {
+		self.0.contains_key(key)
+	}
+
+	/// Set the value of `key`, returning its previous value.
+	pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+		self.0.insert(key, value)
+	}
+
+	/// Remove the value of `key`, returning it.
+	pub fn remove(&mut self, key: &K) -> Option<V> {
+		self.0.remove(key)
+	}
+
+	/// Change the value of `key` in place, returning the result of `f`. `f` is given `None` if
+	/// `key` has no value, and the value is removed if `f` leaves `None`.
+	pub fn mutate<R>(&mut self, key: K, f: impl FnOnce(&mut Option<V>) -> R) -> R {
+		let mut value = self.0.remove(&key);
+		let result = f(&mut value);
+		if let Some(value) = value {
+			self.0.insert(key, value);
+		}
+		result
+	}
+
+	/// Iterate over the values, in the order of their keys.
+	pub fn values(&self) -> impl Iterator<Item = &V> {
+		self.0.values()
+	}
+}
+
+/// A map from pairs of keys to values in the storage of a pallet, declared with
+/// `#[macros::storage]`. The values sharing a first key can be iterated over together.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct StorageDoubleMap<K1, K2, V>(BTreeMap<K1, BTreeMap<K2, V>>);
+
+impl<K1, K2, V> core::fmt::Debug for StorageDoubleMap<K1, K2, V>
+where
+	K1: core::fmt::Debug,
+	K2: core::fmt::Debug,
+	V: core::fmt::Debug,
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+		self.0.fmt(f)
+	}
+}
+
+impl<K1, K2, V> Default for StorageDoubleMap<K1, K2, V> {
+	fn default() -> Self {
+		Self(BTreeMap::new())
+	}
+}
+
+impl<K1: Ord, K2: Ord, V> StorageDoubleMap<K1, K2, V> {
+	/// Get the value of `key1` and `key2`, if there is one.
+	pub fn get(&self, key1: &K1, key2: &K2) -> Option<&V> {
+		self.0.get(key1).and_then(|map| map.get(key2))
+	}
+
+	/// Set the value of `key1` and `key2`, returning its previous value.
+	pub fn insert(&mut self, key1: K1, key2: K2, value: V) -> Option<V> {
+		self.0.entry(key1).or_default().insert(key2, value)
+	}
+
+	/// Remove the value of `key1` and `key2`, returning it.
+	pub fn remove(&mut self, key1: &K1, key2: &K2) -> Option<V> {
+		let map = self.0.get_mut(key1)?;
+		let value = map.remove(key2);
+		if map.is_empty() {
+			self.0.remove(key1);
+		}
+		value
+	}
+
+	/// Iterate over the second keys and values under `key1`, in the order of the second keys.
+	pub fn iter_prefix(&self, key1: &K1) -> impl Iterator<Item = (&K2, &V)> {
+		self.0.get(key1).into_iter().flatten()
+	}
+}
+
+/// A description of a runtime, so that client tools can discover its pallets and calls without
+/// reading its source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RuntimeMetadata {
+	/// The pallets of the runtime, in the order of their indices.
+	pub pallets: Vec<PalletMetadata>,
+}
+
+impl RuntimeMetadata {
+	/// Serialize the metadata to JSON.
+	pub fn to_json(&self) -> String {
+		serde_json::to_string_pretty(self).expect("metadata is always serializable")
+	}
+}
+
+/// An error returned by a call, encoded as the index of the pallet of the call in the runtime and
+/// the index of the error in the pallet, so that client tools can decode it with the metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct ModuleError {
+	/// The index of the pallet in the runtime.
+	pub index: u8,
+	/// The index of the error in the metadata of the pallet.
+	pub error: u8,
+}
+
+/// A description of a pallet in a runtime.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PalletMetadata {
+	/// The name of the pallet in the runtime.
+	pub name: &'static str,
+	/// The index of the pallet in the runtime, which identifies its calls when they are encoded.
+	pub index: u8,
+	/// The calls of the pallet, with the indices which identify them when they are encoded.
+	pub calls: Vec<VariantMetadata>,
+	/// The events the pallet emits.
+	pub events: Vec<VariantMetadata>,
+	/// The errors the calls of the pallet return, named by the message they are returned as.
+	pub errors: Vec<VariantMetadata>,
+	/// The storage items of the pallet.
+	pub storage: Vec<FieldMetadata>,
+}
+
+impl PalletMetadata {
+	/// Describe the pallet `P`, with the given `calls`.
+	pub fn new<P: GetPalletMetadata>(
+		name: &'static str,
+		index: u8,
+		calls: Vec<VariantMetadata>,
+	) -> Self {
+		Self {
+			name,
+			index,
+			calls,
+			events: P::event_metadata(),
+			errors: P::error_metadata(),
+			storage: P::storage_metadata(),
+		}
+	}
+}
+
+/// A description of a variant of an enum, such as a call or an event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VariantMetadata {
+	/// The name of the variant.
+	pub name: &'static str,
+	/// The index of the variant in its enum.
+	pub index: u8,
+	/// The fields of the variant, such as the arguments of a call.
+	pub fields: Vec<FieldMetadata>,
+	/// The lines of the doc comment of the variant.
+	pub docs: Vec<&'static str>,
+}
+
+/// A description of a named and typed value, such as an argument of a call or a storage item.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldMetadata {
+	/// The name of the field.
+	pub name: &'static str,
+	/// The type of the field, with the types of the runtime filled in.
+	#[serde(rename = "type")]
+	pub ty: &'static str,
+	/// The lines of the doc comment of the field.
+	pub docs: Vec<&'static str>,
+}
+
+impl FieldMetadata {
+	/// Describe a field of type `T`.
+	pub fn new<T: ?Sized>(name: &'static str, docs: &[&'static str]) -> Self {
+		Self { name, ty: core::any::type_name::<T>(), docs: docs.to_vec() }
+	}
+}
+
+/// Implemented by the `Call` enum of every pallet, to describe its calls.
+///
+/// `#[macros::call]` implements this with the doc comments of the calls.
+pub trait GetCallMetadata {
+	/// Describe the calls, with the index of each call.
+	fn call_metadata() -> Vec<VariantMetadata>;
+}
+
+/// Implemented by the event enum of a pallet, to describe its events.
+///
+/// `#[macros::event]` implements this with the doc comments of the events.
+pub trait GetEventMetadata {
+	/// Describe the events, with the index of each event.
+	fn event_metadata() -> Vec<VariantMetadata>;
+}
+
+/// Implemented by the error enum of a pallet, to describe its errors.
+///
+/// `#[macros::error]` implements this with the doc comments of the errors.
+pub trait GetErrorMetadata {
+	/// Describe the errors, with the index of each error.
+	fn error_metadata() -> Vec<VariantMetadata>;
+}
+
+/// Implemented by every pallet, to describe its storage items.
+///
+/// `#[macros::storage]` implements this with the doc comments of the items.
+pub trait GetStorageMetadata {
+	/// Describe the storage items of the pallet.
+	fn storage_metadata() -> Vec<FieldMetadata>;
+}
+
+/// Implemented by every pallet, to read its storage items by name.
+///
+/// `#[macros::storage]` implements this with the `Debug` output of the items.
+pub trait ReadStorage {
+	/// Read the storage item named `item`, formatted with `Debug`.
+	/// Returns `None` if the pallet has no such item.
+	fn read_storage(&self, item: &str) -> Option<String>;
+
+	/// Read the entry of the map named `item` at `key`, given as JSON, formatted with `Debug`. The
+	/// key of a double map is a JSON array of its two keys.
+	/// Returns `None` if the pallet has no such map, or the map has no entry at this key.
+	fn read_storage_entry(&self, item: &str, key: &str) -> Option<String>;
+}
+
+/// Decode the key of a storage map from JSON, for `ReadStorage::read_storage_entry`.
+pub fn decode_key<K: serde::de::DeserializeOwned>(key: &str) -> Option<K> {
+	serde_json::from_str(key).ok()
+}
+
+/// Implemented by every pallet, to describe its events and errors, on top of its storage.
+pub trait GetPalletMetadata: GetStorageMetadata {
+	/// Describe the events of the pallet. Most pallets do not emit events.
+	fn event_metadata() -> Vec<VariantMetadata> {
+		Vec::new()
+	}
+
+	/// Describe the errors the calls of the pallet return.
+	fn error_metadata() -> Vec<VariantMetadata> {
+		Vec::new()
+	}
+}
+
+/// The runtime API used by client tools to discover the pallets and calls of a runtime.
+///
+/// The `#[macros::runtime]` attribute implements this for the `Runtime`.
+pub trait GetRuntimeMetadata {
+	/// Describe the runtime.
+	fn metadata() -> RuntimeMetadata;
+}
+
+/// The runtime API used by client tools to read the events emitted by the pallets of a runtime.
+///
+/// The `#[macros::runtime]` attribute implements this for the `Runtime`, with the events of the
+/// pallets marked with `#[event]`.
+pub trait GetRuntimeEvents {
+	/// The "outer"-enum of the events of all the pallets.
+	type RuntimeEvent;
+
+	/// Get the events held by the pallets, in the order of the pallets in the runtime.
+	fn events(&self) -> Vec<Self::RuntimeEvent>;
+}
+
+/// The runtime API used by client tools to read the storage of the pallets of a runtime.
+///
+/// The `#[macros::runtime]` attribute implements this for the `Runtime`, with the `ReadStorage`
+/// implementation of each pallet.
+pub trait ReadRuntimeStorage {
+	/// Read the storage item named `item` of the pallet named `pallet`, formatted with `Debug`.
+	/// Returns `None` if the runtime has no such pallet, or the pallet has no such item.
+	fn read_storage(&self, pallet: &str, item: &str) -> Option<String>;
+
+	/// Read the entry at `key`, given as JSON, of the map named `item` of the pallet named
+	/// `pallet`, formatted with `Debug`.
+	/// Returns `None` if the runtime has no such pallet, the pallet has no such map, or the map has
+	/// no entry at this key.
+	fn read_storage_entry(&self, pallet: &str, item: &str, key: &str) -> Option<String>;
+}
+
+/// The output of the hashing function used throughout the runtime.
+pub type Hash = u64;
+
+/// The parent hash of the first block, under which the genesis state is known.
+pub const GENESIS_HASH: Hash = 0;
+
+/// Hashes any value using the 64-bit FNV-1a algorithm.
+///
+/// FNV is not cryptographically secure, but it is simple, which is all this state machine needs to
+/// derive identifiers. The bytes hashed are those written by `core::hash::Hash`, which the standard
+/// library does not promise to keep the same across compiler releases, so hashes, such as block
+/// hashes and state roots, are only comparable between nodes built with the same compiler.
+pub fn hash<T: core::hash::Hash + ?Sized>(value: &T) -> Hash {
+	let mut hasher = Fnv1a(0xcbf2_9ce4_8422_2325);
+	value.hash(&mut hasher);
+	core::hash::Hasher::finish(&hasher)
+}
+
+/// A minimal implementation of the 64-bit FNV-1a hasher.
+struct Fnv1a(u64);
+
+impl core::hash::Hasher for Fnv1a {
+	fn finish(&self) -> u64 {
+		self.0
+	}
+
+	fn write(&mut self, bytes: &[u8]) {
+		for byte in bytes {
+			self.0 ^= u64::from(*byte);
+			self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+		}
+	}
+}
+
+/// Approximates the size of a value when encoded, by counting the bytes it feeds to a hasher.
+pub fn encoded_size<T: core::hash::Hash + ?Sized>(value: &T) -> usize {
+	let mut counter = ByteCounter(0);
+	value.hash(&mut counter);
+	counter.0
+}
+
+/// A hasher which only counts the bytes written to it.
+struct ByteCounter(usize);
+
+impl core::hash::Hasher for ByteCounter {
+	fn finish(&self) -> u64 {
+		self.0 as u64
+	}
+
+	fn write(&mut self, bytes: &[u8]) {
+		self.0 += bytes.len();
+	}
+}
+
+/// A trait for account ids which can be derived from a hash, such as the account controlled by a
+/// multisig.
+pub trait FromHash {
+	/// Derive a value from the given `hash`.
+	fn from_hash(hash: Hash) -> Self;
+}
+
+impl FromHash for String {
+	fn from_hash(hash: Hash) -> Self {
+		format!("{:016x}", hash)
+	}
+}
+
+impl FromHash for u64 {
+	fn from_hash(hash: Hash) -> Self {
+		hash
+	}
+}
@@ -1,6 +1,6 @@
+use crate::support::{self, DispatchResult, HasPallet, Hash, StorageMap, StorageValue};
 use core::ops::AddAssign;
 use num::traits::{One, Zero};
-use std::{collections::BTreeMap, marker::PhantomData};
 
 /// The configuration trait for the System pallet.
 /// Defines the common types used throughout the state machine.
@@ -11,28 +11,67 @@ pub trait Config {
 	type BlockNumber: Zero + One + AddAssign + Copy;
 	/// The type used to represent the number of transactions from an account.
 	type Nonce: Zero + One + AddAssign + Copy;
+
+	/// The version of the runtime.
+	const VERSION: support::RuntimeVersion =
+		support::RuntimeVersion { spec_name: "", spec_version: 0, transaction_version: 0 };
+
+	/// Called on the runtime at the start of the first block executed with a higher spec version,
+	/// to migrate the state of its pallets. An error, such as a failed check of a migration, makes
+	/// `set_code` fail.
+	fn on_runtime_upgrade(&mut self) -> DispatchResult {
+		Ok(())
+	}
+}
+
+/// The events emitted by the System pallet.
+#[macros::event]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Event<T: Config> {
+	/// `sender` made a remark, which has the hash `hash`.
+	Remarked { sender: T::AccountId, hash: Hash },
+	/// The runtime code was set to the code with the spec version `spec_version`.
+	CodeUpdated { spec_version: u32 },
+	/// The storage of the pallet with the index `pallet_index` was reset.
+	StorageKilled { pallet_index: u8 },
+}
+
+/// The errors returned by the System pallet.
+#[macros::error]
+pub enum Error {
+	/// The caller is not the root account.
+	NotRoot,
+	/// The spec version is not the one of the running runtime.
+	UnknownSpecVersion,
 }
 
 /// The System pallet, for managing low-level state of the blockchain.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[macros::storage]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Pallet<T: Config> {
 	/// The current block number.
-	block_number: T::BlockNumber,
+	#[getter(block_number)]
+	#[default(T::BlockNumber::zero())]
+	block_number: StorageValue<T::BlockNumber>,
+	/// The hash of the header of the last finalized block, which is the parent of the next block.
+	#[getter(parent_hash)]
+	#[default(support::GENESIS_HASH)]
+	parent_hash: StorageValue<Hash>,
 	/// A map from an account to their nonce.
-	nonce: BTreeMap<T::AccountId, T::Nonce>,
-	/// A marker for the generic type `T`.
-	_phantom: PhantomData<T>,
+	nonce: StorageMap<T::AccountId, T::Nonce>,
+	/// The account allowed to make root calls, which govern the chain itself.
+	root: StorageValue<Option<T::AccountId>>,
+	/// The spec version of the runtime which last migrated the state, if any.
+	#[getter(last_runtime_upgrade)]
+	last_runtime_upgrade: StorageValue<Option<u32>>,
+	/// The events emitted by this pallet in the current block.
+	events: StorageValue<Vec<Event<T>>>,
 }
 
 impl<T: Config> Pallet<T> {
-	/// Constructs a new instance of the System pallet.
-	pub fn new() -> Self {
-		Self { block_number: T::BlockNumber::zero(), nonce: BTreeMap::new(), _phantom: PhantomData }
-	}
-
-	/// Get the current block number.
-	pub fn block_number(&self) -> T::BlockNumber {
-		self.block_number
+	/// Set the hash of the header of the last finalized block.
+	pub fn set_parent_hash(&mut self, hash: Hash) {
+		self.parent_hash.put(hash);
 	}
 
 	/// Get the nonce of an account.
@@ -40,21 +79,131 @@ impl<T: Config> Pallet<T> {
 		*self.nonce.get(who).unwrap_or(&T::Nonce::zero())
 	}
 
+	/// Get the account allowed to make root calls, if any.
+	pub fn root(&self) -> Option<&T::AccountId> {
+		self.root.get().as_ref()
+	}
+
+	/// Set the account allowed to make root calls.
+	///
+	/// This is not a dispatchable call, but can be used to set up the root account at genesis.
+	pub fn set_root(&mut self, root: Option<T::AccountId>) {
+		self.root.put(root);
+	}
+
+	/// Check that `who` is the root account.
+	pub fn ensure_root(&self, who: &T::AccountId) -> DispatchResult {
+		if self.root.get().as_ref() != Some(who) {
+			return Err(Error::NotRoot.into());
+		}
+		Ok(())
+	}
+
+	/// Check if the runtime was upgraded since it last migrated the state.
+	pub fn needs_runtime_upgrade(&self) -> bool {
+		self.last_runtime_upgrade() < Some(T::VERSION.spec_version)
+	}
+
+	/// Record that the current runtime has migrated the state.
+	pub fn note_runtime_upgrade(&mut self) {
+		self.last_runtime_upgrade.put(Some(T::VERSION.spec_version));
+	}
+
+	/// Get the number of the block which follows the current block.
+	pub fn next_block_number(&self) -> T::BlockNumber {
+		let mut block_number = self.block_number();
+		block_number += T::BlockNumber::one();
+		block_number
+	}
+
 	/// Increments the block number by one.
 	pub fn inc_block_number(&mut self) {
-		self.block_number += T::BlockNumber::one();
+		self.block_number.mutate(|block_number| *block_number += T::BlockNumber::one());
 	}
 
 	/// Increments the nonce of an account.
 	pub fn inc_nonce(&mut self, who: &T::AccountId) {
-		let nonce = self.nonce.entry(who.clone()).or_insert(T::Nonce::zero());
-		*nonce += T::Nonce::one();
+		self.nonce.mutate(who.clone(), |nonce| {
+			*nonce.get_or_insert(T::Nonce::zero()) += T::Nonce::one();
+		});
+	}
+}
+
+/// The dispatchable functions of the System pallet.
+///
+/// These take the whole runtime, since setting the code runs the upgrade hooks of the runtime, and
+/// killing storage resets another pallet.
+#[macros::call]
+impl<T: Config> Pallet<T>
+where
+	T: HasPallet<Self> + support::KillStorage,
+{
+	/// Make a remark on chain. This does nothing, other than charge the caller for the call.
+	pub fn remark(_runtime: &mut T, _caller: T::AccountId, _remark: Vec<u8>) -> DispatchResult {
+		Ok(())
+	}
+
+	/// Make a remark on chain, and emit an event with its hash.
+	pub fn remark_with_event(
+		runtime: &mut T,
+		caller: T::AccountId,
+		remark: Vec<u8>,
+	) -> DispatchResult {
+		let system: &mut Self = runtime.pallet_mut();
+		system.deposit_event(Event::Remarked { sender: caller, hash: support::hash(&remark) });
+		Ok(())
+	}
+
+	/// Set the code of the runtime, and run its upgrade hooks. This is a root call.
+	///
+	/// The code of the runtime is built into the node, so the only code which can be set is the
+	/// running one, identified by its spec version. Its upgrade hooks run right away, instead of
+	/// at the start of the next block, and the call fails if they do.
+	pub fn set_code(runtime: &mut T, caller: T::AccountId, spec_version: u32) -> DispatchResult {
+		let system: &Self = runtime.pallet();
+		system.ensure_root(&caller)?;
+		if spec_version != T::VERSION.spec_version {
+			return Err(Error::UnknownSpecVersion.into());
+		}
+		Config::on_runtime_upgrade(runtime)?;
+		let system: &mut Self = runtime.pallet_mut();
+		system.note_runtime_upgrade();
+		system.deposit_event(Event::CodeUpdated { spec_version });
+		Ok(())
+	}
+
+	/// Reset the storage of the pallet with the index `pallet_index` to its initial state. This is
+	/// a root call.
+	pub fn kill_storage(runtime: &mut T, caller: T::AccountId, pallet_index: u8) -> DispatchResult {
+		let system: &Self = runtime.pallet();
+		system.ensure_root(&caller)?;
+		runtime.kill_storage(pallet_index)?;
+		let system: &mut Self = runtime.pallet_mut();
+		system.deposit_event(Event::StorageKilled { pallet_index });
+		Ok(())
+	}
+}
+
+// Events are only kept for the block which emitted them.
+impl<T: Config> support::Hooks<T::BlockNumber> for Pallet<T> {
+	fn on_initialize(&mut self, _block_number: T::BlockNumber) {
+		self.events.put(Vec::new());
+	}
+}
+
+impl<T: Config> support::GetPalletMetadata for Pallet<T> {
+	fn event_metadata() -> Vec<support::VariantMetadata> {
+		<Event<T> as support::GetEventMetadata>::event_metadata()
+	}
+
+	fn error_metadata() -> Vec<support::VariantMetadata> {
+		<Error as support::GetErrorMetadata>::error_metadata()
 	}
 }
 
 #[cfg(test)]
 mod test {
-	use super::{Config, Pallet};
+	use super::{Config, Error, Pallet};
 
 	// Mock struct for testing purposes.
 	struct TestConfig;
@@ -75,4 +224,202 @@ mod test {
 		assert_eq!(system.nonce(&"alice".to_string()), 1);
 		assert_eq!(system.nonce(&"bob".to_string()), 0);
 	}
+
+	#[test]
+	fn root_account() {
+		let mut system = Pallet::<TestConfig>::new();
+		assert_eq!(system.ensure_root(&"alice".to_string()), Err(Error::NotRoot.into()));
+
+		system.set_root(Some("alice".to_string()));
+		assert_eq!(system.root(), Some(&"alice".to_string()));
+		assert_eq!(system.ensure_root(&"alice".to_string()), Ok(()));
+		assert_eq!(system.ensure_root(&"bob".to_string()), Err(Error::NotRoot.into()));
+	}
+
+	mod runtime {
+		use crate::{
+			aura,
+			support::{self, BuildBlock, Dispatch, GetRuntimeEvents},
+			system,
+		};
+
+		mod types {
+			pub type Extrinsic = crate::support::Extrinsic<String, super::RuntimeCall, u32, u128>;
+			pub type Block = crate::support::Block<
+				crate::support::Header<u32>,
+				crate::support::UncheckedExtrinsic<Extrinsic, super::RuntimeCall>,
+			>;
+		}
+
+		// A minimal runtime to dispatch the calls of system with.
+		#[macros::runtime]
+		#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+		struct Runtime {
+			#[event]
+			system: system::Pallet<Self>,
+			aura: aura::Pallet<Self>,
+		}
+
+		impl system::Config for Runtime {
+			type AccountId = String;
+			type BlockNumber = u32;
+			type Nonce = u32;
+
+			const VERSION: support::RuntimeVersion = support::RuntimeVersion {
+				spec_name: "test",
+				spec_version: 2,
+				transaction_version: 1,
+			};
+		}
+
+		impl aura::Config for Runtime {
+			const MAX_AUTHORITIES: usize = 3;
+		}
+
+		impl support::ChargeTransaction<types::Extrinsic> for Runtime {}
+
+		fn genesis() -> Runtime {
+			let mut runtime = Runtime::new();
+			runtime.system.set_root(Some("root".to_string()));
+			runtime.aura.initialize_authorities(vec!["alice".to_string()]).unwrap();
+			runtime
+		}
+
+		#[test]
+		fn remarks() {
+			let mut runtime = genesis();
+			let remark = b"hello".to_vec();
+			let call = RuntimeCall::system(system::Call::remark { remark: remark.clone() });
+			assert_eq!(runtime.dispatch("alice".to_string(), call), Ok(()));
+			assert!(runtime.events().is_empty());
+
+			let call =
+				RuntimeCall::system(system::Call::remark_with_event { remark: remark.clone() });
+			assert_eq!(runtime.dispatch("alice".to_string(), call), Ok(()));
+			let remarked = system::Event::Remarked {
+				sender: "alice".to_string(),
+				hash: support::hash(&remark),
+			};
+			assert_eq!(runtime.events(), [RuntimeEvent::system(remarked)]);
+
+			// Events are only kept for the block which emitted them.
+			runtime.initialize_block(support::Digest::default());
+			assert!(runtime.events().is_empty());
+		}
+
+		// An error of the system pallet, as the runtime returns it.
+		fn error(error: system::Error) -> support::DispatchError {
+			support::DispatchError::from(error).in_pallet(0)
+		}
+
+		#[test]
+		fn root_sets_code() {
+			let mut runtime = genesis();
+			let call = |spec_version| RuntimeCall::system(system::Call::set_code { spec_version });
+			assert_eq!(
+				runtime.dispatch("alice".to_string(), call(2)),
+				Err(error(system::Error::NotRoot))
+			);
+			assert_eq!(
+				runtime.dispatch("root".to_string(), call(3)),
+				Err(error(system::Error::UnknownSpecVersion))
+			);
+			assert_eq!(runtime.system.last_runtime_upgrade(), None);
+
+			assert_eq!(runtime.dispatch("root".to_string(), call(2)), Ok(()));
+			assert_eq!(runtime.system.last_runtime_upgrade(), Some(2));
+			assert!(!runtime.system.needs_runtime_upgrade());
+			let updated = system::Event::CodeUpdated { spec_version: 2 };
+			assert_eq!(runtime.system.events(), [updated]);
+		}
+
+		#[test]
+		fn root_kills_storage() {
+			let mut runtime = genesis();
+			let call =
+				|pallet_index| RuntimeCall::system(system::Call::kill_storage { pallet_index });
+			assert_eq!(
+				runtime.dispatch("alice".to_string(), call(1)),
+				Err(error(system::Error::NotRoot))
+			);
+			assert_eq!(
+				runtime.dispatch("root".to_string(), call(0)),
+				Err("storage of system cannot be killed".into())
+			);
+			assert_eq!(
+				runtime.dispatch("root".to_string(), call(2)),
+				Err("no pallet has this index".into())
+			);
+			assert_eq!(runtime.aura.authorities(), &["alice".to_string()]);
+
+			assert_eq!(runtime.dispatch("root".to_string(), call(1)), Ok(()));
+			assert!(runtime.aura.authorities().is_empty());
+			assert_eq!(runtime.system.root(), Some(&"root".to_string()));
+			assert_eq!(runtime.system.events(), [system::Event::StorageKilled { pallet_index: 1 }]);
+		}
+	}
+
+	// A runtime built from the paths given to the macro, as a runtime outside of this crate would
+	// be, whose system pallet is not named `system`.
+	mod renamed {
+		use crate::{
+			block_builder::{BlockBuilder, BlockLimits, Inclusion},
+			support::{self, GetRuntimeEvents, GetRuntimeMetadata, InherentData},
+			system as frame,
+		};
+
+		mod chain {
+			pub type Extrinsic = crate::support::Extrinsic<String, super::RuntimeCall, u32, u128>;
+			pub type Block = crate::support::Block<
+				crate::support::Header<u32>,
+				crate::support::UncheckedExtrinsic<Extrinsic, super::RuntimeCall>,
+			>;
+		}
+
+		#[macros::runtime(
+			block = chain::Block,
+			extrinsic = chain::Extrinsic,
+			system = frame,
+			support = crate::support,
+		)]
+		#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+		struct Node {
+			#[event]
+			frame: frame::Pallet<Self>,
+		}
+
+		impl frame::Config for Node {
+			type AccountId = String;
+			type BlockNumber = u32;
+			type Nonce = u32;
+		}
+
+		impl support::ChargeTransaction<chain::Extrinsic> for Node {}
+
+		#[test]
+		fn runtime_args() {
+			let mut node = Node::new();
+			let limits = BlockLimits { max_weight: u64::MAX, max_size: usize::MAX };
+			let remark = b"hello".to_vec();
+			let call =
+				RuntimeCall::frame(frame::Call::remark_with_event { remark: remark.clone() });
+			let extrinsic = support::Extrinsic::new_signed("alice".to_string(), call, 0, 0);
+
+			let mut author = node.clone();
+			let mut builder =
+				BlockBuilder::new(&mut author, 0, &InherentData::new(), limits).unwrap();
+			assert_eq!(builder.push(extrinsic), Inclusion::Included(Ok(())));
+			let block = builder.build();
+			assert_eq!(node.execute_block(block), Ok(()));
+			assert_eq!(node, author);
+
+			let remarked = frame::Event::Remarked {
+				sender: "alice".to_string(),
+				hash: support::hash(&remark),
+			};
+			assert_eq!(node.events(), [RuntimeEvent::frame(remarked)]);
+			assert_eq!(node.frame.nonce(&"alice".to_string()), 1);
+			assert_eq!(Node::metadata().pallets[0].name, "frame");
+		}
+	}
 }
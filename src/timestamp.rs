@@ -0,0 +1,247 @@
+use crate::{
+	support::{self, DispatchResult, HasPallet, InherentData, InherentIdentifier, StorageValue},
+	system,
+};
+use core::hash::Hash;
+use num::traits::{CheckedAdd, Zero};
+
+/// Configuration trait for the Timestamp pallet.
+pub trait Config: system::Config<BlockNumber: Ord + Hash> {
+	/// The type used to represent a point in time, such as milliseconds since the Unix epoch.
+	type Moment: Zero + CheckedAdd + Copy + Ord + Hash + From<u64>;
+	/// The minimum time between two blocks.
+	const MINIMUM_PERIOD: Self::Moment;
+	/// How far ahead of the local time of a node the time of a block it imports may be.
+	const MAX_DRIFT: Self::Moment;
+
+	/// Called on the runtime whenever the timestamp is set, so that other pallets can keep track
+	/// of the time.
+	fn on_timestamp_set(&mut self, _now: Self::Moment) {}
+}
+
+/// The identifier of the time in the inherent data, in the same unit as `Config::Moment`.
+pub const INHERENT_IDENTIFIER: InherentIdentifier = *b"timstap0";
+
+/// The errors returned by the Timestamp pallet.
+#[macros::error]
+pub enum Error {
+	/// The time is already set in this block.
+	AlreadySet,
+	/// The time overflowed.
+	Overflow,
+	/// The time is less than `MINIMUM_PERIOD` after the time of the last block.
+	TooEarly,
+	/// The inherent data has no time.
+	MissingInherentData,
+	/// The time is more than `MAX_DRIFT` ahead of the local time.
+	TooFarInFuture,
+}
+
+/// The Timestamp pallet.
+/// Keeps the time of the current block, which the block author sets with an inherent.
+#[macros::storage]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Pallet<T: Config> {
+	/// The time of the current block.
+	#[getter(now)]
+	#[default(T::Moment::zero())]
+	now: StorageValue<T::Moment>,
+	/// The last block in which the time was set.
+	updated_at: StorageValue<Option<T::BlockNumber>>,
+}
+
+/// The dispatchable functions of the Timestamp pallet.
+///
+/// These take the whole runtime, since the time is set once per block, as counted by `system`.
+#[macros::call]
+impl<T: Config> Pallet<T>
+where
+	T: HasPallet<Self> + HasPallet<system::Pallet<T>>,
+{
+	/// Set the time of the current block. This is an inherent, added by the block author.
+	///
+	/// The time must be set exactly once per block, and at least `MINIMUM_PERIOD` after the time of
+	/// the last block.
+	#[call(inherent)]
+	pub fn set(runtime: &mut T, now: T::Moment) -> DispatchResult {
+		let system: &system::Pallet<T> = runtime.pallet();
+		let block_number = system.block_number();
+
+		let timestamp: &mut Self = runtime.pallet_mut();
+		if *timestamp.updated_at.get() == Some(block_number) {
+			return Err(Error::AlreadySet.into());
+		}
+		let earliest = timestamp.now().checked_add(&T::MINIMUM_PERIOD).ok_or(Error::Overflow)?;
+		if now < earliest {
+			return Err(Error::TooEarly.into());
+		}
+		timestamp.now.put(now);
+		timestamp.updated_at.put(Some(block_number));
+
+		runtime.on_timestamp_set(now);
+		Ok(())
+	}
+}
+
+impl<T: Config> support::ProvideInherent for Pallet<T> {
+	type Call = Call<T>;
+	const IS_MANDATORY: bool = true;
+
+	/// Set the time to the time in the inherent data, or to the earliest time allowed if the clock
+	/// of the author is behind.
+	fn create_inherent(&self, data: &InherentData) -> Option<Self::Call> {
+		let now = T::Moment::from(data.get(&INHERENT_IDENTIFIER)?);
+		let earliest = self.now().checked_add(&T::MINIMUM_PERIOD)?;
+		Some(Call::set { now: now.max(earliest) })
+	}
+
+	/// Check that the time of the block is not too far ahead of the local time.
+	fn check_inherent(&self, call: &Self::Call, data: &InherentData) -> DispatchResult {
+		let local = data.get(&INHERENT_IDENTIFIER).ok_or(Error::MissingInherentData)?;
+		let latest = T::Moment::from(local).checked_add(&T::MAX_DRIFT).ok_or(Error::Overflow)?;
+		match call {
+			Call::set { now } if *now > latest => Err(Error::TooFarInFuture.into()),
+			Call::set { .. } => Ok(()),
+			Call::__Ignore(_, never) => match *never {},
+		}
+	}
+}
+
+impl<T: Config> support::Hooks<T::BlockNumber> for Pallet<T> {}
+
+impl<T: Config> support::GetPalletMetadata for Pallet<T> {
+	fn error_metadata() -> Vec<support::VariantMetadata> {
+		<Error as support::GetErrorMetadata>::error_metadata()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		block_builder::{BlockBuilder, BlockLimits, Inclusion},
+		chain_store::{ChainStore, LongestChain},
+		proof_of_existence,
+		support::{self, InherentData, UncheckedExtrinsic},
+		system, timestamp,
+	};
+
+	mod types {
+		pub type Extrinsic = crate::support::Extrinsic<String, super::RuntimeCall, u32, u128>;
+		pub type Block = crate::support::Block<
+			crate::support::Header<u32>,
+			crate::support::UncheckedExtrinsic<Extrinsic, super::RuntimeCall>,
+		>;
+	}
+
+	// A minimal runtime which stamps claims with the time of their block.
+	#[macros::runtime]
+	#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+	struct Runtime {
+		system: system::Pallet<Self>,
+		#[inherent]
+		timestamp: timestamp::Pallet<Self>,
+		proof_of_existence: proof_of_existence::Pallet<Self>,
+	}
+
+	impl system::Config for Runtime {
+		type AccountId = String;
+		type BlockNumber = u32;
+		type Nonce = u32;
+	}
+
+	impl timestamp::Config for Runtime {
+		type Moment = u64;
+		const MINIMUM_PERIOD: u64 = 5;
+		const MAX_DRIFT: u64 = 20;
+
+		fn on_timestamp_set(&mut self, now: u64) {
+			self.proof_of_existence.set_now(now);
+		}
+	}
+
+	impl proof_of_existence::Config for Runtime {
+		type Content = String;
+	}
+
+	impl support::ChargeTransaction<types::Extrinsic> for Runtime {}
+
+	impl support::VerifyHeader for Runtime {}
+
+	const LIMITS: BlockLimits = BlockLimits { max_weight: u64::MAX, max_size: usize::MAX };
+
+	fn data(now: u64) -> InherentData {
+		let mut data = InherentData::new();
+		data.put(timestamp::INHERENT_IDENTIFIER, now);
+		data
+	}
+
+	fn set(now: u64) -> UncheckedExtrinsic<types::Extrinsic, RuntimeCall> {
+		UncheckedExtrinsic::Inherent(RuntimeCall::timestamp(timestamp::Call::set { now }))
+	}
+
+	fn claim(nonce: u32, claim: &str) -> types::Extrinsic {
+		let call = proof_of_existence::Call::create_claim { claim: claim.to_string() };
+		support::Extrinsic::new_signed(
+			"alice".to_string(),
+			RuntimeCall::proof_of_existence(call),
+			nonce,
+			0,
+		)
+	}
+
+	#[test]
+	fn set_once_per_block_by_inherent() {
+		let mut runtime = Runtime::new();
+		let mut author = runtime.clone();
+		let mut builder = BlockBuilder::new(&mut author, 0, &data(10), LIMITS).unwrap();
+		assert!(matches!(builder.push(set(20)), Inclusion::Invalid("AlreadySet")));
+		let call = RuntimeCall::timestamp(timestamp::Call::set { now: 20 });
+		let signed = support::Extrinsic::new_signed("alice".to_string(), call, 0, 0);
+		assert_eq!(
+			builder.push(signed),
+			Inclusion::Included(Err("call can only be dispatched as an inherent".into()))
+		);
+		assert!(matches!(builder.push(claim(1, "first")), Inclusion::Included(Ok(()))));
+		let block = builder.build();
+		let inherent = RuntimeCall::timestamp(timestamp::Call::set { now: 10 });
+		assert_eq!(block.extrinsics[0], UncheckedExtrinsic::Inherent(inherent));
+		assert_eq!(runtime.execute_block(block), Ok(()));
+		assert_eq!(runtime.timestamp.now(), 10);
+		assert_eq!(runtime.proof_of_existence.claimed_at(&"first".to_string()), Some(10));
+
+		// An author whose clock is behind still sets the time at least the minimum period later.
+		let mut builder = BlockBuilder::new(&mut author, 0, &data(12), LIMITS).unwrap();
+		assert!(matches!(builder.push(claim(2, "second")), Inclusion::Included(Ok(()))));
+		assert_eq!(runtime.execute_block(builder.build()), Ok(()));
+		assert_eq!(runtime.proof_of_existence.claimed_at(&"second".to_string()), Some(15));
+	}
+
+	#[test]
+	fn blocks_need_the_inherent() {
+		let mut chain = ChainStore::new(Runtime::new(), LongestChain, usize::MAX);
+		let mut state = chain.best_state().clone();
+		let mut builder = BlockBuilder::new(&mut state, 0, &InherentData::new(), LIMITS).unwrap();
+		builder.push(claim(0, "untimed"));
+		let block = builder.build();
+		assert_eq!(chain.import(block, &data(10)), Err("block is missing a mandatory inherent"));
+
+		let mut state = chain.best_state().clone();
+		let mut builder = BlockBuilder::new(&mut state, 0, &InherentData::new(), LIMITS).unwrap();
+		builder.push(claim(0, "late"));
+		builder.push(set(10));
+		let block = builder.build();
+		assert_eq!(
+			chain.import(block, &data(10)),
+			Err("inherents must be at the start of the block")
+		);
+
+		let mut state = chain.best_state().clone();
+		let mut builder = BlockBuilder::new(&mut state, 0, &data(31), LIMITS).unwrap();
+		builder.push(claim(0, "future"));
+		let block = builder.build();
+		assert_eq!(chain.import(block.clone(), &data(10)), Err("TooFarInFuture"));
+		let err = Err("MissingInherentData");
+		assert_eq!(chain.import(block.clone(), &InherentData::new()), err);
+		assert!(chain.import(block, &data(11)).is_ok());
+	}
+}
@@ -0,0 +1,382 @@
+use crate::support::{self, Extrinsic, Hash, ValidateTransaction};
+use core::{cmp::Reverse, ops::AddAssign};
+use num::traits::One;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap};
+
+/// A transaction in the pool, along with the information used to order it.
+struct PoolTransaction<Caller, Call, Nonce, Balance> {
+	extrinsic: Extrinsic<Caller, Call, Nonce, Balance>,
+	hash: Hash,
+	priority: u64,
+	// The order in which transactions were submitted, to break ties between equal priorities.
+	insertion: u64,
+}
+
+/// Transactions in the pool, by sender and nonce.
+type Transactions<Caller, Call, Nonce, Balance> =
+	BTreeMap<Caller, BTreeMap<Nonce, PoolTransaction<Caller, Call, Nonce, Balance>>>;
+
+/// An in-process pool of transactions waiting to be included in a block.
+///
+/// Transactions are validated against the runtime state when they are submitted. A transaction is
+/// ready if its nonce is the account nonce of its sender, or if it follows a ready transaction of
+/// the same sender. Other transactions are parked in the future queue until the transactions
+/// before them arrive.
+pub struct TransactionPool<Caller, Call, Nonce, Balance> {
+	// Transactions which can be included in the next block, in order.
+	ready: Transactions<Caller, Call, Nonce, Balance>,
+	// Transactions waiting for an earlier transaction of the same sender.
+	future: Transactions<Caller, Call, Nonce, Balance>,
+	// The hashes of all transactions in the pool.
+	hashes: BTreeSet<Hash>,
+	// The maximum number of transactions in the pool.
+	capacity: usize,
+	// The number of transactions inserted so far.
+	insertions: u64,
+}
+
+impl<Caller, Call, Nonce, Balance> TransactionPool<Caller, Call, Nonce, Balance>
+where
+	Caller: Ord + Clone + core::hash::Hash,
+	Call: core::hash::Hash,
+	Nonce: Ord + Copy + One + AddAssign + core::hash::Hash,
+	Balance: core::hash::Hash,
+{
+	/// Constructs a new, empty pool which holds at most `capacity` transactions.
+	pub fn new(capacity: usize) -> Self {
+		Self {
+			ready: BTreeMap::new(),
+			future: BTreeMap::new(),
+			hashes: BTreeSet::new(),
+			capacity,
+			insertions: 0,
+		}
+	}
+
+	/// Get the number of transactions in the pool.
+	pub fn len(&self) -> usize {
+		self.hashes.len()
+	}
+
+	/// Returns true if there are no transactions in the pool.
+	pub fn is_empty(&self) -> bool {
+		self.hashes.is_empty()
+	}
+
+	/// Get the number of transactions parked in the future queue.
+	pub fn future_len(&self) -> usize {
+		self.future.values().map(BTreeMap::len).sum()
+	}
+
+	/// Validate `extrinsic` against the state of `runtime`, and add it to the pool.
+	///
+	/// A transaction with the same sender and nonce as one already in the pool replaces it only if
+	/// it has a higher priority. Returns the hash of the transaction.
+	pub fn submit<R>(
+		&mut self,
+		runtime: &R,
+		extrinsic: Extrinsic<Caller, Call, Nonce, Balance>,
+	) -> Result<Hash, &'static str>
+	where
+		R: ValidateTransaction<Extrinsic = Extrinsic<Caller, Call, Nonce, Balance>>,
+	{
+		let hash = support::hash(&extrinsic);
+		if self.hashes.contains(&hash) {
+			return Err("transaction is already in the pool");
+		}
+		let valid = runtime.validate_transaction(&extrinsic)?;
+
+		let (caller, nonce) = (&extrinsic.caller, &extrinsic.nonce);
+		let in_ready = self.ready.get(caller).and_then(|txs| txs.get(nonce));
+		let in_future = self.future.get(caller).and_then(|txs| txs.get(nonce));
+		let replaces_ready = in_ready.is_some();
+		match in_ready.or(in_future) {
+			Some(existing) if existing.priority >= valid.priority =>
+				return Err("a transaction with the same nonce and priority is in the pool"),
+			Some(existing) => {
+				self.hashes.remove(&existing.hash);
+			},
+			None if self.len() >= self.capacity => return Err("transaction pool is full"),
+			None => {},
+		}
+
+		let insertion = self.insertions;
+		self.insertions += 1;
+		let tx = PoolTransaction { extrinsic, hash, priority: valid.priority, insertion };
+		if replaces_ready {
+			// Replace the ready transaction in place, so the transactions after it stay ready.
+			self.hashes.insert(hash);
+			let txs = self.ready.entry(tx.extrinsic.caller.clone()).or_default();
+			txs.insert(tx.extrinsic.nonce, tx);
+		} else {
+			self.insert(tx, valid.future);
+		}
+		Ok(hash)
+	}
+
+	/// Get the ready transactions in the order they should be included in a block.
+	///
+	/// Transactions with a higher priority come first, but the transactions of each sender are
+	/// always in nonce order.
+	pub fn ready(&self) -> Vec<&Extrinsic<Caller, Call, Nonce, Balance>> {
+		self.ready_order()
+			.into_iter()
+			.filter_map(|(caller, nonce)| self.ready.get(&caller)?.get(&nonce))
+			.map(|tx| &tx.extrinsic)
+			.collect()
+	}
+
+	/// Get every transaction in the pool, ready or future, in the order they were submitted, so
+	/// that they can be submitted again to another pool.
+	pub fn transactions(&self) -> Vec<&Extrinsic<Caller, Call, Nonce, Balance>> {
+		let mut transactions = self
+			.ready
+			.values()
+			.chain(self.future.values())
+			.flat_map(|txs| txs.values())
+			.collect::<Vec<_>>();
+		transactions.sort_by_key(|tx| tx.insertion);
+		transactions.into_iter().map(|tx| &tx.extrinsic).collect()
+	}
+
+	/// Remove the ready transactions from the pool, in the order they should be included in a
+	/// block. Transactions in the future queue stay in the pool.
+	pub fn take_ready(&mut self) -> Vec<Extrinsic<Caller, Call, Nonce, Balance>> {
+		let order = self.ready_order();
+		let mut ready = core::mem::take(&mut self.ready);
+		order
+			.into_iter()
+			.filter_map(|(caller, nonce)| ready.get_mut(&caller)?.remove(&nonce))
+			.map(|tx| {
+				self.hashes.remove(&tx.hash);
+				tx.extrinsic
+			})
+			.collect()
+	}
+
+	/// Revalidate every transaction against the state of `runtime`, after a block is imported.
+	///
+	/// Transactions which are no longer valid, such as those included in the block, are evicted,
+	/// and future transactions whose turn has come are moved to the ready queue. Returns the number
+	/// of evicted transactions.
+	pub fn maintain<R>(&mut self, runtime: &R) -> usize
+	where
+		R: ValidateTransaction<Extrinsic = Extrinsic<Caller, Call, Nonce, Balance>>,
+	{
+		let mut transactions = core::mem::take(&mut self.ready);
+		for (caller, mut txs) in core::mem::take(&mut self.future) {
+			transactions.entry(caller).or_default().append(&mut txs);
+		}
+		let before = self.len();
+		self.hashes.clear();
+
+		// Reinserting each sender's transactions in nonce order lets later transactions follow
+		// the ones before them into the ready queue.
+		for tx in transactions.into_values().flat_map(BTreeMap::into_values) {
+			if let Ok(valid) = runtime.validate_transaction(&tx.extrinsic) {
+				let tx = PoolTransaction { priority: valid.priority, ..tx };
+				self.insert(tx, valid.future);
+			}
+		}
+		before - self.len()
+	}
+
+	/// Insert a validated transaction into the ready or future queue.
+	fn insert(&mut self, tx: PoolTransaction<Caller, Call, Nonce, Balance>, future: bool) {
+		let caller = tx.extrinsic.caller.clone();
+		let nonce = tx.extrinsic.nonce;
+		self.hashes.insert(tx.hash);
+
+		if !future || self.next_ready_nonce(&caller) == Some(nonce) {
+			if let Some(txs) = self.future.get_mut(&caller) {
+				txs.remove(&nonce);
+			}
+			self.ready.entry(caller.clone()).or_default().insert(nonce, tx);
+			self.promote(&caller);
+		} else {
+			self.future.entry(caller).or_default().insert(nonce, tx);
+		}
+	}
+
+	/// Move the future transactions of `caller` which now follow its ready transactions into the
+	/// ready queue.
+	fn promote(&mut self, caller: &Caller) {
+		while let Some(next) = self.next_ready_nonce(caller) {
+			let Some(txs) = self.future.get_mut(caller) else { return };
+			let Some(tx) = txs.remove(&next) else { return };
+			if txs.is_empty() {
+				self.future.remove(caller);
+			}
+			self.ready.entry(caller.clone()).or_default().insert(next, tx);
+		}
+	}
+
+	/// Get the nonce which follows the last ready transaction of `caller`, if it has any.
+	fn next_ready_nonce(&self, caller: &Caller) -> Option<Nonce> {
+		let (last, _) = self.ready.get(caller)?.last_key_value()?;
+		let mut next = *last;
+		next += Nonce::one();
+		Some(next)
+	}
+
+	/// Get the sender and nonce of each ready transaction, in the order they should be included.
+	fn ready_order(&self) -> Vec<(Caller, Nonce)> {
+		// Only the first remaining transaction of each sender is a candidate. The best candidate is
+		// the one with the highest priority, or the earliest submitted if priorities are equal.
+		let mut queues = self.ready.values().map(|txs| txs.values().peekable()).collect::<Vec<_>>();
+		let mut candidates = BinaryHeap::new();
+		for (index, queue) in queues.iter_mut().enumerate() {
+			if let Some(tx) = queue.peek() {
+				candidates.push((tx.priority, Reverse(tx.insertion), index));
+			}
+		}
+
+		let mut order = Vec::new();
+		while let Some((_, _, index)) = candidates.pop() {
+			let queue = &mut queues[index];
+			if let Some(tx) = queue.next() {
+				order.push((tx.extrinsic.caller.clone(), tx.extrinsic.nonce));
+			}
+			if let Some(tx) = queue.peek() {
+				candidates.push((tx.priority, Reverse(tx.insertion), index));
+			}
+		}
+		order
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::TransactionPool;
+	use crate::support::{self, ValidateTransaction};
+	use std::collections::BTreeMap;
+
+	type Extrinsic = support::Extrinsic<&'static str, u32, u32, u64>;
+
+	// A mock runtime which only knows the account nonces, and uses the tip as the priority.
+	#[derive(Default)]
+	struct TestRuntime {
+		nonces: BTreeMap<&'static str, u32>,
+	}
+
+	impl ValidateTransaction for TestRuntime {
+		type Extrinsic = Extrinsic;
+
+		fn validate_transaction(&self, extrinsic: &Extrinsic) -> support::TransactionValidity {
+			if !extrinsic.verify() {
+				return Err("invalid signature");
+			}
+			let nonce = self.nonces.get(extrinsic.caller).copied().unwrap_or(0);
+			if extrinsic.nonce < nonce {
+				return Err("nonce is stale");
+			}
+			Ok(support::ValidTransaction {
+				priority: extrinsic.tip,
+				future: extrinsic.nonce > nonce,
+			})
+		}
+	}
+
+	fn senders(extrinsics: Vec<&Extrinsic>) -> Vec<(&'static str, u32)> {
+		extrinsics
+			.into_iter()
+			.map(|extrinsic| (extrinsic.caller, extrinsic.nonce))
+			.collect()
+	}
+
+	#[test]
+	fn orders_by_priority_and_nonce() {
+		let runtime = TestRuntime::default();
+		let mut pool = TransactionPool::new(10);
+
+		pool.submit(&runtime, Extrinsic::new_signed("alice", 0, 0, 1)).unwrap();
+		pool.submit(&runtime, Extrinsic::new_signed("alice", 1, 1, 10)).unwrap();
+		pool.submit(&runtime, Extrinsic::new_signed("bob", 2, 0, 5)).unwrap();
+		pool.submit(&runtime, Extrinsic::new_signed("charlie", 3, 0, 1)).unwrap();
+
+		// Alice's second transaction has the highest tip, but must wait for her first one.
+		assert_eq!(
+			senders(pool.ready()),
+			vec![("bob", 0), ("alice", 0), ("alice", 1), ("charlie", 0)]
+		);
+
+		let taken = pool.take_ready();
+		assert_eq!(taken.len(), 4);
+		assert!(pool.is_empty());
+	}
+
+	#[test]
+	fn parks_future_transactions() {
+		let runtime = TestRuntime::default();
+		let mut pool = TransactionPool::new(10);
+
+		pool.submit(&runtime, Extrinsic::new_signed("alice", 0, 2, 0)).unwrap();
+		pool.submit(&runtime, Extrinsic::new_signed("alice", 0, 0, 0)).unwrap();
+		assert_eq!(senders(pool.ready()), vec![("alice", 0)]);
+		assert_eq!(pool.future_len(), 1);
+
+		// The missing nonce lets the parked transaction into the ready queue.
+		pool.submit(&runtime, Extrinsic::new_signed("alice", 0, 1, 0)).unwrap();
+		assert_eq!(senders(pool.ready()), vec![("alice", 0), ("alice", 1), ("alice", 2)]);
+		assert_eq!(pool.future_len(), 0);
+	}
+
+	#[test]
+	fn rejects_invalid_and_duplicate_transactions() {
+		let mut runtime = TestRuntime::default();
+		runtime.nonces.insert("alice", 1);
+		let mut pool = TransactionPool::new(2);
+
+		assert_eq!(
+			pool.submit(&runtime, Extrinsic::new_signed("alice", 0, 0, 0)),
+			Err("nonce is stale")
+		);
+		let mut forged = Extrinsic::new_signed("alice", 0, 1, 0);
+		forged.caller = "bob";
+		assert_eq!(pool.submit(&runtime, forged), Err("invalid signature"));
+
+		pool.submit(&runtime, Extrinsic::new_signed("alice", 0, 1, 1)).unwrap();
+		assert_eq!(
+			pool.submit(&runtime, Extrinsic::new_signed("alice", 0, 1, 1)),
+			Err("transaction is already in the pool")
+		);
+		assert_eq!(
+			pool.submit(&runtime, Extrinsic::new_signed("alice", 1, 1, 0)),
+			Err("a transaction with the same nonce and priority is in the pool")
+		);
+
+		// A higher tip replaces the transaction with the same nonce.
+		pool.submit(&runtime, Extrinsic::new_signed("alice", 1, 1, 2)).unwrap();
+		assert_eq!(pool.len(), 1);
+		assert_eq!(pool.ready()[0].call, 1);
+
+		pool.submit(&runtime, Extrinsic::new_signed("bob", 0, 0, 0)).unwrap();
+		assert_eq!(
+			pool.submit(&runtime, Extrinsic::new_signed("charlie", 0, 0, 0)),
+			Err("transaction pool is full")
+		);
+	}
+
+	#[test]
+	fn maintain_evicts_stale_transactions() {
+		let mut runtime = TestRuntime::default();
+		let mut pool = TransactionPool::new(10);
+
+		pool.submit(&runtime, Extrinsic::new_signed("alice", 0, 0, 0)).unwrap();
+		pool.submit(&runtime, Extrinsic::new_signed("alice", 0, 1, 0)).unwrap();
+		pool.submit(&runtime, Extrinsic::new_signed("alice", 0, 3, 0)).unwrap();
+		pool.submit(&runtime, Extrinsic::new_signed("bob", 0, 1, 0)).unwrap();
+		assert_eq!(pool.future_len(), 2);
+
+		// A block from elsewhere included Alice's first two transactions and one of Bob's.
+		runtime.nonces.insert("alice", 2);
+		runtime.nonces.insert("bob", 1);
+		assert_eq!(pool.maintain(&runtime), 2);
+		assert_eq!(senders(pool.ready()), vec![("bob", 1)]);
+		assert_eq!(pool.future_len(), 1);
+
+		runtime.nonces.insert("alice", 3);
+		assert_eq!(pool.maintain(&runtime), 0);
+		assert_eq!(senders(pool.ready()), vec![("alice", 3), ("bob", 1)]);
+	}
+}
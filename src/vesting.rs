@@ -0,0 +1,459 @@
+use crate::{
+	balances::{self, LockIdentifier},
+	support::{self, DispatchResult, HasPallet, StorageMap},
+	system,
+};
+use core::hash::Hash;
+use num::traits::{CheckedDiv, CheckedMul, CheckedSub, One, Saturating, Zero};
+
+/// The identifier of the lock which the Vesting pallet places on vesting funds.
+const VESTING_ID: LockIdentifier = *b"vesting ";
+
+/// Configuration trait for the Vesting pallet.
+/// Vested funds are measured in blocks, so block numbers must convert into balances.
+pub trait Config:
+	balances::Config<
+	AccountId: Hash,
+	BlockNumber: Ord + CheckedSub + Hash,
+	Balance: From<Self::BlockNumber> + One + CheckedMul + CheckedDiv + Saturating + Hash,
+>
+{
+	/// The minimum amount which can be transferred with a vesting schedule.
+	const MIN_VESTED_TRANSFER: Self::Balance;
+	/// The maximum number of vesting schedules an account can have.
+	const MAX_VESTING_SCHEDULES: usize;
+}
+
+/// A linear vesting schedule, unlocking `per_block` of the `locked` funds every block after the
+/// `starting_block`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct VestingInfo<Balance, BlockNumber> {
+	/// The amount locked when the schedule starts.
+	pub locked: Balance,
+	/// The amount unlocked every block after the schedule starts.
+	pub per_block: Balance,
+	/// The block after which funds start to unlock.
+	pub starting_block: BlockNumber,
+}
+
+impl<Balance, BlockNumber> VestingInfo<Balance, BlockNumber>
+where
+	Balance: Copy + Ord + Zero + One + CheckedMul + CheckedDiv + Saturating + From<BlockNumber>,
+	BlockNumber: Copy + Ord + Zero + CheckedSub,
+{
+	/// Get the amount of this schedule which is still locked at the block `now`.
+	pub fn locked_at(&self, now: BlockNumber) -> Balance {
+		let elapsed = now.checked_sub(&self.starting_block).unwrap_or(BlockNumber::zero());
+		let vested = Balance::from(elapsed).checked_mul(&self.per_block).unwrap_or(self.locked);
+		self.locked.saturating_sub(vested)
+	}
+
+	/// Get the block at which this schedule is fully unlocked, as a balance so that it cannot
+	/// overflow the block number type.
+	fn ending_block(&self) -> Balance {
+		let duration = div_ceil(self.locked, self.per_block);
+		Balance::from(self.starting_block).saturating_add(duration)
+	}
+}
+
+/// Divide `amount` by `divisor`, rounding up, or get `amount` if `divisor` is zero.
+fn div_ceil<Balance>(amount: Balance, divisor: Balance) -> Balance
+where
+	Balance: Copy + Ord + One + CheckedMul + CheckedDiv + Saturating,
+{
+	let quotient = amount.checked_div(&divisor).unwrap_or(amount);
+	if quotient.checked_mul(&divisor).unwrap_or(amount) < amount {
+		quotient.saturating_add(Balance::one())
+	} else {
+		quotient
+	}
+}
+
+/// The errors returned by the Vesting pallet.
+#[macros::error]
+pub enum Error {
+	/// The amount is below `MIN_VESTED_TRANSFER`.
+	AmountLow,
+	/// The schedule unlocks nothing per block.
+	InvalidSchedule,
+	/// The account has `MAX_VESTING_SCHEDULES` schedules already.
+	TooManySchedules,
+	/// The account has no vesting schedules.
+	NotVesting,
+	/// There is no schedule at the given index.
+	ScheduleIndexOutOfBounds,
+}
+
+/// The Vesting pallet.
+/// Locks funds in `balances` which unlock linearly as blocks are produced.
+#[macros::storage]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Pallet<T: Config> {
+	/// A mapping from an account to its vesting schedules.
+	#[allow(clippy::type_complexity)]
+	vesting: StorageMap<T::AccountId, Vec<VestingInfo<T::Balance, T::BlockNumber>>>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Get the vesting schedules of an account.
+	pub fn vesting(&self, who: &T::AccountId) -> &[VestingInfo<T::Balance, T::BlockNumber>] {
+		self.vesting.get(who).map(Vec::as_slice).unwrap_or(&[])
+	}
+
+	/// Get the amount of an account's funds which are still locked by vesting at the block `now`.
+	pub fn vesting_balance(&self, who: &T::AccountId, now: T::BlockNumber) -> T::Balance {
+		self.vesting(who).iter().fold(T::Balance::zero(), |total, schedule| {
+			total.saturating_add(schedule.locked_at(now))
+		})
+	}
+
+	/// Check that `schedule` is valid and can be added to the schedules of an account.
+	fn ensure_schedule(
+		&self,
+		who: &T::AccountId,
+		schedule: &VestingInfo<T::Balance, T::BlockNumber>,
+	) -> DispatchResult {
+		if schedule.locked < T::MIN_VESTED_TRANSFER {
+			return Err(Error::AmountLow.into());
+		}
+		if schedule.per_block.is_zero() {
+			return Err(Error::InvalidSchedule.into());
+		}
+		if self.vesting(who).len() >= T::MAX_VESTING_SCHEDULES {
+			return Err(Error::TooManySchedules.into());
+		}
+		Ok(())
+	}
+}
+
+/// The dispatchable functions of the Vesting pallet.
+///
+/// These take the whole runtime, since vesting depends on the block number in `system` and places
+/// locks in `balances`.
+#[macros::call]
+impl<T: Config> Pallet<T>
+where
+	T: HasPallet<Self> + HasPallet<balances::Pallet<T>> + HasPallet<system::Pallet<T>>,
+{
+	/// Add a vesting schedule to an account, locking its funds.
+	///
+	/// This is not a dispatchable call, but can be used to set up vesting at genesis.
+	#[call(skip)]
+	pub fn add_vesting_schedule(
+		runtime: &mut T,
+		who: &T::AccountId,
+		schedule: VestingInfo<T::Balance, T::BlockNumber>,
+	) -> DispatchResult {
+		let vesting: &mut Self = runtime.pallet_mut();
+		vesting.ensure_schedule(who, &schedule)?;
+		vesting
+			.vesting
+			.mutate(who.clone(), |schedules| schedules.get_or_insert_with(Vec::new).push(schedule));
+		Self::update_lock(runtime, who);
+		Ok(())
+	}
+
+	/// Unlock the funds of the `caller` which have vested so far.
+	pub fn vest(runtime: &mut T, caller: T::AccountId) -> DispatchResult {
+		let vesting: &Self = runtime.pallet();
+		if vesting.vesting(&caller).is_empty() {
+			return Err(Error::NotVesting.into());
+		}
+		Self::update_lock(runtime, &caller);
+		Ok(())
+	}
+
+	/// Transfer `schedule.locked` from the `caller` to `target`, locked with the vesting
+	/// `schedule`.
+	pub fn vested_transfer(
+		runtime: &mut T,
+		caller: T::AccountId,
+		target: T::AccountId,
+		schedule: VestingInfo<T::Balance, T::BlockNumber>,
+	) -> DispatchResult {
+		let vesting: &Self = runtime.pallet();
+		vesting.ensure_schedule(&target, &schedule)?;
+
+		let balances: &mut balances::Pallet<T> = runtime.pallet_mut();
+		balances.transfer(caller, target.clone(), schedule.locked)?;
+		Self::add_vesting_schedule(runtime, &target, schedule)
+	}
+
+	/// Merge two vesting schedules of the `caller` into one.
+	///
+	/// The merged schedule starts at the later of the current block and the two starting blocks,
+	/// and locks what remains of both schedules. Its amount per block is rounded up, so it ends no
+	/// later than the later of the two ending blocks. Merging a schedule with itself does nothing.
+	pub fn merge_schedules(
+		runtime: &mut T,
+		caller: T::AccountId,
+		schedule1_index: u32,
+		schedule2_index: u32,
+	) -> DispatchResult {
+		let system: &system::Pallet<T> = runtime.pallet();
+		let now = system.block_number();
+
+		let vesting: &mut Self = runtime.pallet_mut();
+		vesting.vesting.mutate(caller.clone(), |schedules| -> DispatchResult {
+			let schedules = schedules.as_mut().ok_or(Error::NotVesting)?;
+			let (first, second) = (schedule1_index as usize, schedule2_index as usize);
+			if first.max(second) >= schedules.len() {
+				return Err(Error::ScheduleIndexOutOfBounds.into());
+			}
+			if first == second {
+				return Ok(());
+			}
+			// Remove the later index first, so that the earlier index stays valid.
+			let schedule2 = schedules.remove(first.max(second));
+			let schedule1 = schedules.remove(first.min(second));
+
+			let locked = schedule1.locked_at(now).saturating_add(schedule2.locked_at(now));
+			if !locked.is_zero() {
+				let starting_block =
+					now.max(schedule1.starting_block).max(schedule2.starting_block);
+				let ending_block = schedule1.ending_block().max(schedule2.ending_block());
+				let duration = ending_block.saturating_sub(T::Balance::from(starting_block));
+				let per_block = div_ceil(locked, duration).max(T::Balance::one());
+				schedules.push(VestingInfo { locked, per_block, starting_block });
+			}
+			Ok(())
+		})?;
+
+		Self::update_lock(runtime, &caller);
+		Ok(())
+	}
+
+	/// Update the vesting lock of an account to what is still locked at the current block,
+	/// removing schedules which have fully vested.
+	#[call(skip)]
+	fn update_lock(runtime: &mut T, who: &T::AccountId) {
+		let system: &system::Pallet<T> = runtime.pallet();
+		let now = system.block_number();
+
+		let vesting: &mut Self = runtime.pallet_mut();
+		vesting.vesting.mutate(who.clone(), |schedules| {
+			if let Some(vesting) = schedules {
+				vesting.retain(|schedule| !schedule.locked_at(now).is_zero());
+				if vesting.is_empty() {
+					*schedules = None;
+				}
+			}
+		});
+		let locked = vesting.vesting_balance(who, now);
+
+		let balances: &mut balances::Pallet<T> = runtime.pallet_mut();
+		if locked.is_zero() {
+			balances.remove_lock(VESTING_ID, who);
+		} else {
+			balances.set_lock(VESTING_ID, who, locked);
+		}
+	}
+}
+
+impl<T: Config> support::Hooks<T::BlockNumber> for Pallet<T> {}
+
+impl<T: Config> support::GetPalletMetadata for Pallet<T> {
+	fn error_metadata() -> Vec<support::VariantMetadata> {
+		<Error as support::GetErrorMetadata>::error_metadata()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		balances,
+		block_builder::{BlockBuilder, BlockLimits},
+		support::{self, Dispatch, InherentData},
+		system,
+		vesting::{self, VestingInfo},
+	};
+
+	mod types {
+		pub type Extrinsic = crate::support::Extrinsic<String, super::RuntimeCall, u32, u128>;
+		pub type Block = crate::support::Block<
+			crate::support::Header<u32>,
+			crate::support::UncheckedExtrinsic<Extrinsic, super::RuntimeCall>,
+		>;
+	}
+
+	// A minimal runtime to dispatch vesting calls against.
+	#[macros::runtime]
+	#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+	struct Runtime {
+		system: system::Pallet<Self>,
+		balances: balances::Pallet<Self>,
+		vesting: vesting::Pallet<Self>,
+	}
+
+	impl system::Config for Runtime {
+		type AccountId = String;
+		type BlockNumber = u32;
+		type Nonce = u32;
+	}
+
+	impl balances::Config for Runtime {
+		type Balance = u128;
+	}
+
+	impl support::ChargeTransaction<types::Extrinsic> for Runtime {}
+
+	impl vesting::Config for Runtime {
+		const MIN_VESTED_TRANSFER: u128 = 10;
+		const MAX_VESTING_SCHEDULES: usize = 2;
+	}
+
+	fn transfer(amount: u128) -> balances::Call<Runtime> {
+		balances::Call::transfer { to: "bob".to_string(), amount }
+	}
+
+	// Build and execute empty blocks until the runtime reaches `block_number`.
+	fn run_to_block(runtime: &mut Runtime, block_number: u32) {
+		let limits = BlockLimits { max_weight: u64::MAX, max_size: usize::MAX };
+		while runtime.system.block_number() < block_number {
+			let block = BlockBuilder::new(&mut runtime.clone(), 0, &InherentData::new(), limits)
+				.unwrap()
+				.build();
+			runtime.execute_block(block).unwrap();
+		}
+	}
+
+	// An error of the vesting pallet, as the runtime returns it.
+	fn error(error: vesting::Error) -> support::DispatchError {
+		support::DispatchError::from(error).in_pallet(2)
+	}
+
+	#[test]
+	fn locked_at_unlocks_linearly() {
+		let schedule = VestingInfo::<u128, u32> { locked: 100, per_block: 30, starting_block: 5 };
+		assert_eq!(schedule.locked_at(0), 100);
+		assert_eq!(schedule.locked_at(5), 100);
+		assert_eq!(schedule.locked_at(6), 70);
+		assert_eq!(schedule.locked_at(8), 10);
+		assert_eq!(schedule.locked_at(9), 0);
+		assert_eq!(schedule.ending_block(), 9);
+	}
+
+	#[test]
+	fn vest_unlocks_funds() {
+		let mut runtime = Runtime::new();
+		let alice = "alice".to_string();
+		runtime.balances.set_balance(&alice, 100);
+
+		let schedule = VestingInfo { locked: 100, per_block: 10, starting_block: 0 };
+		assert_eq!(vesting::Pallet::add_vesting_schedule(&mut runtime, &alice, schedule), Ok(()));
+		assert_eq!(runtime.balances.locked_balance(&alice), 100);
+		assert_eq!(
+			runtime.dispatch(alice.clone(), RuntimeCall::balances(transfer(1))),
+			Err(support::DispatchError::Module {
+				error: support::ModuleError { index: 1, error: 1 },
+				message: "FundsLocked",
+			})
+		);
+
+		// Locks are only updated by calling `vest`.
+		run_to_block(&mut runtime, 3);
+		assert_eq!(runtime.vesting.vesting_balance(&alice, 3), 70);
+		assert_eq!(runtime.balances.locked_balance(&alice), 100);
+		assert_eq!(
+			runtime.dispatch(alice.clone(), RuntimeCall::vesting(vesting::Call::vest {})),
+			Ok(())
+		);
+		assert_eq!(runtime.balances.locked_balance(&alice), 70);
+		assert_eq!(runtime.dispatch(alice.clone(), RuntimeCall::balances(transfer(30))), Ok(()));
+
+		// Once fully vested, the schedule and lock are removed.
+		run_to_block(&mut runtime, 10);
+		assert_eq!(
+			runtime.dispatch(alice.clone(), RuntimeCall::vesting(vesting::Call::vest {})),
+			Ok(())
+		);
+		assert_eq!(runtime.balances.locked_balance(&alice), 0);
+		assert!(runtime.vesting.vesting(&alice).is_empty());
+		assert_eq!(
+			runtime.dispatch(alice.clone(), RuntimeCall::vesting(vesting::Call::vest {})),
+			Err(error(vesting::Error::NotVesting))
+		);
+	}
+
+	#[test]
+	fn vested_transfer_locks_target_funds() {
+		let mut runtime = Runtime::new();
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		runtime.balances.set_balance(&alice, 100);
+
+		let vested_transfer = |locked| {
+			RuntimeCall::vesting(vesting::Call::vested_transfer {
+				target: bob.clone(),
+				schedule: VestingInfo { locked, per_block: 5, starting_block: 0 },
+			})
+		};
+		assert_eq!(
+			runtime.dispatch(alice.clone(), vested_transfer(5)),
+			Err(error(vesting::Error::AmountLow))
+		);
+		assert_eq!(
+			runtime.dispatch(alice.clone(), vested_transfer(200)),
+			Err(support::DispatchError::Module {
+				error: support::ModuleError { index: 1, error: 0 },
+				message: "InsufficientBalance",
+			})
+		);
+		assert_eq!(runtime.dispatch(alice.clone(), vested_transfer(20)), Ok(()));
+		assert_eq!(runtime.dispatch(alice.clone(), vested_transfer(20)), Ok(()));
+		assert_eq!(
+			runtime.dispatch(alice.clone(), vested_transfer(20)),
+			Err(error(vesting::Error::TooManySchedules))
+		);
+
+		assert_eq!(runtime.balances.balance(&bob), 40);
+		assert_eq!(runtime.balances.locked_balance(&bob), 40);
+		assert_eq!(runtime.vesting.vesting(&bob).len(), 2);
+	}
+
+	#[test]
+	fn merge_schedules_keeps_remaining_funds_locked() {
+		let mut runtime = Runtime::new();
+		let alice = "alice".to_string();
+		runtime.balances.set_balance(&alice, 1000);
+
+		let first = VestingInfo { locked: 100, per_block: 10, starting_block: 0 };
+		let second = VestingInfo { locked: 300, per_block: 10, starting_block: 10 };
+		assert_eq!(vesting::Pallet::add_vesting_schedule(&mut runtime, &alice, first), Ok(()));
+		assert_eq!(vesting::Pallet::add_vesting_schedule(&mut runtime, &alice, second), Ok(()));
+
+		// At block 5, 50 of the first and all 300 of the second schedule are locked. The merged
+		// schedule starts at block 10 and ends at block 40, when the second schedule would end.
+		run_to_block(&mut runtime, 5);
+		let merge = vesting::Call::merge_schedules { schedule1_index: 1, schedule2_index: 0 };
+		assert_eq!(runtime.dispatch(alice.clone(), RuntimeCall::vesting(merge)), Ok(()));
+		let merged = VestingInfo { locked: 350, per_block: 12, starting_block: 10 };
+		assert_eq!(runtime.vesting.vesting(&alice), &[merged]);
+		assert_eq!(merged.ending_block(), 40);
+		assert_eq!(merged.locked_at(39), 2);
+		assert_eq!(merged.locked_at(40), 0);
+		assert_eq!(runtime.balances.locked_balance(&alice), 350);
+
+		let merge = vesting::Call::merge_schedules { schedule1_index: 0, schedule2_index: 1 };
+		assert_eq!(
+			runtime.dispatch(alice.clone(), RuntimeCall::vesting(merge)),
+			Err(error(vesting::Error::ScheduleIndexOutOfBounds))
+		);
+
+		// A schedule merged with itself is left as it is, but the index is still checked.
+		let merge = |index| vesting::Call::merge_schedules {
+			schedule1_index: index,
+			schedule2_index: index,
+		};
+		assert_eq!(runtime.dispatch(alice.clone(), RuntimeCall::vesting(merge(0))), Ok(()));
+		assert_eq!(runtime.vesting.vesting(&alice), &[merged]);
+		assert_eq!(
+			runtime.dispatch(alice, RuntimeCall::vesting(merge(1))),
+			Err(error(vesting::Error::ScheduleIndexOutOfBounds))
+		);
+		assert_eq!(
+			runtime.dispatch("bob".to_string(), RuntimeCall::vesting(merge(0))),
+			Err(error(vesting::Error::NotVesting))
+		);
+	}
+}
@@ -0,0 +1,489 @@
+// The demo chain the node used to run, built with the same limits and inherent data as the node.
+use rust_state_machine::{
+	assets, aura, balances, block_builder, chain_store, multisig,
+	node::{inherent_data, Chain, BLOCK_LIMITS, FINALITY_DEPTH},
+	proof_of_existence, proxy,
+	runtime::{types, ProxyType, Runtime, RuntimeCall, RuntimeEvent, SLOT_DURATION},
+	support::{self, GetRuntimeEvents, GetStorageVersion},
+	system, transaction_pool, vesting,
+};
+
+/// Build a block of `extrinsics` on top of the `parent` state in `slot`, as the author of the slot
+/// would, and seal it. The block starts with the inherents created from the author's inherent data.
+/// Returns the block and the result of each call.
+fn build_block(
+	parent: &Runtime,
+	slot: support::Slot,
+	extrinsics: Vec<types::Extrinsic>,
+) -> (types::Block, Vec<support::DispatchResult>) {
+	let mut state = parent.clone();
+	let mut builder =
+		block_builder::BlockBuilder::new(&mut state, slot, &inherent_data(slot), BLOCK_LIMITS)
+			.expect("invalid inherent");
+	let mut results = Vec::new();
+	for extrinsic in extrinsics {
+		match builder.push(extrinsic) {
+			block_builder::Inclusion::Included(result) => results.push(result),
+			block_builder::Inclusion::Invalid(e) => panic!("invalid extrinsic: {}", e),
+			block_builder::Inclusion::Exhausted(_) => panic!("block is full"),
+		}
+	}
+	let mut block = builder.build();
+	block
+		.header
+		.seal(parent.aura.slot_author(slot).expect("there are no authorities"));
+	(block, results)
+}
+
+/// Build a block of `extrinsics` on top of the best block of `chain` in the next slot, and import
+/// it as the new best block. Returns the result of each call.
+fn import_block(
+	chain: &mut Chain,
+	extrinsics: Vec<types::Extrinsic>,
+) -> Vec<support::DispatchResult> {
+	let best_slot = chain.block(&chain.best_hash()).map_or(0, |best| best.header.digest.slot);
+	let slot = best_slot + 1;
+	let (block, results) = build_block(chain.best_state(), slot, extrinsics);
+	let outcome = chain.import(block, &inherent_data(slot)).expect("invalid block");
+	assert!(matches!(outcome, chain_store::ImportOutcome::NewBest { .. }));
+	results
+}
+
+// Run a chain of blocks exercising every pallet of the runtime, as a node would build and import
+// them, and check the state the chain ends up in.
+#[test]
+fn demo() {
+	// Describe the runtime, as a client tool would discover it.
+	let metadata = <Runtime as support::GetRuntimeMetadata>::metadata();
+	assert!(metadata.to_json().contains("\"name\": \"proof_of_existence\""));
+	let names = metadata.pallets.iter().map(|pallet| pallet.name).collect::<Vec<_>>();
+	assert_eq!(names[..3], ["system", "balances", "proof_of_existence"]);
+
+	// Instantiate the runtime.
+	let mut runtime = Runtime::new();
+	let alice = "alice".to_string();
+	let bob = "bob".to_string();
+	let charlie = "charlie".to_string();
+	let dave = "dave".to_string();
+
+	// Set up the genesis state. Alice holds the root key, and authors blocks in turns with Bob.
+	runtime.system.set_root(Some(alice.clone()));
+	let genesis_claims = vec![("Genesis claim".to_string(), alice.clone())];
+	runtime.proof_of_existence =
+		proof_of_existence::Pallet::from_genesis(&proof_of_existence::GenesisConfig {
+			claims: genesis_claims,
+			..Default::default()
+		})
+		.expect("invalid genesis claims");
+	runtime
+		.aura
+		.initialize_authorities(vec![alice.clone(), bob.clone()])
+		.expect("invalid genesis authorities");
+	runtime.balances.set_balance(&alice, 100);
+	// Bob holds the reward token, kept by a second instance of the Balances pallet.
+	runtime.rewards.set_balance(&bob, 50);
+	// Dave has a little more than his vesting schedule locks, to pay fees.
+	runtime.balances.set_balance(&dave, 103);
+	let dave_vesting = vesting::VestingInfo { locked: 100, per_block: 20, starting_block: 2 };
+	vesting::Pallet::add_vesting_schedule(&mut runtime, &dave, dave_vesting)
+		.expect("invalid genesis vesting");
+
+	// Construct block 1: Balance transfers.
+	let block_1 = vec![
+		support::Extrinsic::new_signed(
+			alice.clone(),
+			RuntimeCall::balances(balances::Call::transfer { to: bob.clone(), amount: 30 }),
+			0,
+			0,
+		),
+		support::Extrinsic::new_signed(
+			alice.clone(),
+			RuntimeCall::balances(balances::Call::transfer { to: charlie.clone(), amount: 20 }),
+			1,
+			0,
+		),
+	];
+
+	// Construct block 2: Proof of Existence claims.
+	let block_2 = vec![
+		support::Extrinsic::new_signed(
+			alice.clone(),
+			RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+				claim: "Hello, world!".to_string(),
+			}),
+			2,
+			0,
+		),
+		support::Extrinsic::new_signed(
+			bob.clone(),
+			RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+				claim: "Hello, world!".to_string(),
+			}),
+			0,
+			0,
+		),
+	];
+
+	// Construct block 3: Claim revocation and re-claim.
+	let block_3 = vec![
+		support::Extrinsic::new_signed(
+			alice.clone(),
+			RuntimeCall::proof_of_existence(proof_of_existence::Call::revoke_claim {
+				claim: "Hello, world!".to_string(),
+			}),
+			3,
+			0,
+		),
+		support::Extrinsic::new_signed(
+			bob.clone(),
+			RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+				claim: "Hello, world!".to_string(),
+			}),
+			1,
+			0,
+		),
+	];
+
+	// Construct block 4: A 2-of-3 multisig transfer.
+	let signatories = [alice.clone(), bob.clone(), charlie.clone()];
+	let multisig_account = multisig::Pallet::<Runtime>::multi_account_id(&signatories, 2);
+	let multisig_call =
+		RuntimeCall::balances(balances::Call::transfer { to: charlie.clone(), amount: 5 });
+	let block_4 = vec![
+		support::Extrinsic::new_signed(
+			alice.clone(),
+			RuntimeCall::balances(balances::Call::transfer {
+				to: multisig_account.clone(),
+				amount: 10,
+			}),
+			4,
+			0,
+		),
+		support::Extrinsic::new_signed(
+			alice.clone(),
+			RuntimeCall::multisig(multisig::Call::approve_as_multi {
+				threshold: 2,
+				other_signatories: vec![bob.clone(), charlie.clone()],
+				call_hash: support::hash(&multisig_call),
+			}),
+			5,
+			0,
+		),
+		support::Extrinsic::new_signed(
+			bob.clone(),
+			RuntimeCall::multisig(multisig::Call::as_multi {
+				threshold: 2,
+				other_signatories: vec![alice.clone(), charlie.clone()],
+				call: Box::new(multisig_call),
+			}),
+			2,
+			0,
+		),
+	];
+
+	// Construct block 5: Bob claims content on behalf of Alice as her proxy.
+	let block_5 = vec![
+		support::Extrinsic::new_signed(
+			alice.clone(),
+			RuntimeCall::proxy(proxy::Call::add_proxy {
+				delegate: bob.clone(),
+				proxy_type: ProxyType::ProofOfExistence,
+				delay: 0,
+			}),
+			6,
+			0,
+		),
+		support::Extrinsic::new_signed(
+			bob.clone(),
+			RuntimeCall::proxy(proxy::Call::proxy {
+				real: alice.clone(),
+				force_proxy_type: None,
+				call: Box::new(RuntimeCall::proof_of_existence(
+					proof_of_existence::Call::create_claim { claim: "Delegated claim".to_string() },
+				)),
+			}),
+			3,
+			0,
+		),
+		support::Extrinsic::new_signed(
+			bob.clone(),
+			RuntimeCall::proxy(proxy::Call::proxy {
+				real: alice.clone(),
+				force_proxy_type: None,
+				call: Box::new(RuntimeCall::balances(balances::Call::transfer {
+					to: bob.clone(),
+					amount: 10,
+				})),
+			}),
+			4,
+			0,
+		),
+	];
+
+	// Construct block 6: Dave unlocks his vested funds and transfers them.
+	let block_6 = vec![
+		support::Extrinsic::new_signed(
+			dave.clone(),
+			RuntimeCall::vesting(vesting::Call::vest {}),
+			0,
+			0,
+		),
+		support::Extrinsic::new_signed(
+			dave.clone(),
+			RuntimeCall::balances(balances::Call::transfer { to: charlie.clone(), amount: 80 }),
+			1,
+			0,
+		),
+		support::Extrinsic::new_signed(
+			dave.clone(),
+			RuntimeCall::balances(balances::Call::transfer { to: charlie.clone(), amount: 20 }),
+			2,
+			0,
+		),
+	];
+
+	// Construct block 7: Alice creates an asset, mints it to Bob, and Bob pays Charlie.
+	let block_7 = vec![
+		support::Extrinsic::new_signed(
+			alice.clone(),
+			RuntimeCall::assets(assets::Call::create {
+				id: 1,
+				admin: alice.clone(),
+				min_balance: 1,
+			}),
+			7,
+			0,
+		),
+		support::Extrinsic::new_signed(
+			alice.clone(),
+			RuntimeCall::assets(assets::Call::set_metadata {
+				id: 1,
+				name: "Reward Token".to_string(),
+				symbol: "RWD".to_string(),
+				decimals: 0,
+			}),
+			8,
+			0,
+		),
+		support::Extrinsic::new_signed(
+			alice.clone(),
+			RuntimeCall::assets(assets::Call::mint {
+				id: 1,
+				beneficiary: bob.clone(),
+				amount: 1000,
+			}),
+			9,
+			0,
+		),
+		support::Extrinsic::new_signed(
+			bob.clone(),
+			RuntimeCall::assets(assets::Call::transfer {
+				id: 1,
+				target: charlie.clone(),
+				amount: 250,
+			}),
+			5,
+			0,
+		),
+	];
+
+	// Construct block 8: Charlie spends from Alice's balance with her approval.
+	let block_8 = vec![
+		support::Extrinsic::new_signed(
+			alice.clone(),
+			RuntimeCall::balances(balances::Call::approve { spender: charlie.clone(), amount: 20 }),
+			10,
+			0,
+		),
+		support::Extrinsic::new_signed(
+			charlie.clone(),
+			RuntimeCall::balances(balances::Call::transfer_from {
+				owner: alice.clone(),
+				to: bob.clone(),
+				amount: 15,
+			}),
+			0,
+			0,
+		),
+	];
+
+	// Build and import the blocks.
+	let mut chain = Chain::new(runtime, chain_store::LongestChain, FINALITY_DEPTH);
+	import_block(&mut chain, block_1);
+	let results = import_block(&mut chain, block_2);
+	assert_eq!(
+		results,
+		vec![
+			Ok(()),
+			Err(support::DispatchError::Module {
+				error: support::ModuleError { index: 2, error: 0 },
+				message: "AlreadyClaimed",
+			})
+		]
+	);
+	import_block(&mut chain, block_3);
+	import_block(&mut chain, block_4);
+	import_block(&mut chain, block_5);
+	import_block(&mut chain, block_6);
+	import_block(&mut chain, block_7);
+	import_block(&mut chain, block_8);
+
+	// Build block 9 from the transaction pool. Bob's second transaction arrives first and waits
+	// for his first one, while Alice's tip puts her transaction ahead of both.
+	let mut pool = transaction_pool::TransactionPool::new(100);
+	let pool_extrinsics = [
+		support::Extrinsic::new_signed(
+			bob.clone(),
+			RuntimeCall::balances(balances::Call::transfer { to: charlie.clone(), amount: 5 }),
+			7,
+			0,
+		),
+		support::Extrinsic::new_signed(
+			bob.clone(),
+			RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+				claim: "Pooled claim".to_string(),
+			}),
+			6,
+			0,
+		),
+		support::Extrinsic::new_signed(
+			alice.clone(),
+			RuntimeCall::balances(balances::Call::transfer { to: bob.clone(), amount: 4 }),
+			11,
+			2,
+		),
+	];
+	for extrinsic in pool_extrinsics {
+		pool.submit(chain.best_state(), extrinsic).expect("invalid transaction");
+	}
+	assert_eq!(pool.future_len(), 0);
+	let order = pool.ready().into_iter().map(|extrinsic| (&extrinsic.caller, extrinsic.nonce));
+	assert_eq!(order.collect::<Vec<_>>(), vec![(&alice, 11), (&bob, 6), (&bob, 7)]);
+	import_block(&mut chain, pool.take_ready());
+	assert_eq!(pool.maintain(chain.best_state()), 0);
+	assert!(pool.is_empty());
+
+	// Alice and Bob author competing blocks on top of block 9, in slots 10 and 11. The first one
+	// imported stays the best block, until the other fork grows longer and the chain reorganizes
+	// onto it.
+	let claim = |who: &types::AccountId, nonce, claim: &str| {
+		let call = proof_of_existence::Call::create_claim { claim: claim.to_string() };
+		support::Extrinsic::new_signed(who.clone(), RuntimeCall::proof_of_existence(call), nonce, 0)
+	};
+	let (block_10a, _) = build_block(chain.best_state(), 10, vec![claim(&alice, 12, "Fork A")]);
+	let (block_10b, _) = build_block(chain.best_state(), 11, vec![claim(&bob, 8, "Fork B")]);
+	let (hash_10a, hash_10b) = (block_10a.header.hash(), block_10b.header.hash());
+	chain.import(block_10a, &inherent_data(10)).expect("invalid block");
+	let outcome = chain.import(block_10b, &inherent_data(11)).expect("invalid block");
+	assert_eq!(outcome, chain_store::ImportOutcome::SideFork);
+
+	let (block_11b, _) =
+		build_block(chain.state(&hash_10b).expect("block 10b is imported"), 12, vec![]);
+	let hash_11b = block_11b.header.hash();
+	let outcome = chain.import(block_11b, &inherent_data(12)).expect("invalid block");
+	let retracted = vec![hash_10a];
+	let enacted = vec![hash_10b, hash_11b];
+	assert_eq!(outcome, chain_store::ImportOutcome::NewBest { retracted, enacted });
+	assert_eq!(chain.best_hash(), hash_11b);
+
+	// Alice's claim was only in the retracted block, so it goes back to the pool for block 12.
+	let retracted_block = chain.block(&hash_10a).expect("blocks are kept until they are pruned");
+	let retracted = retracted_block
+		.extrinsics
+		.iter()
+		.filter_map(support::UncheckedExtrinsic::signed);
+	for extrinsic in retracted.cloned() {
+		pool.submit(chain.best_state(), extrinsic).expect("invalid transaction");
+	}
+	import_block(&mut chain, pool.take_ready());
+	let fork_a =
+		proof_of_existence::Event::ClaimCreated { who: alice.clone(), claim: "Fork A".to_string() };
+	assert_eq!(chain.best_state().events().last(), Some(&RuntimeEvent::from(fork_a.clone())));
+	assert_eq!(chain.best_state().proof_of_existence.events(), [fork_a]);
+
+	// Block 12 finalizes block 10b, so the fork of block 10a is pruned.
+	assert_eq!(chain.finalized_head(), hash_10b);
+	assert!(chain.block(&hash_10a).is_none());
+
+	// Alice uses the root key to add Charlie to the authorities, ahead of the others, so that
+	// Charlie authors block 14 in slot 15, where Alice makes a remark.
+	let authorities = vec![charlie.clone(), alice.clone(), bob.clone()];
+	let call = RuntimeCall::aura(aura::Call::set_authorities { authorities });
+	import_block(&mut chain, vec![support::Extrinsic::new_signed(alice.clone(), call, 13, 0)]);
+	let remark = b"Charlie joined the authorities".to_vec();
+	let hash = support::hash(&remark);
+	let call = RuntimeCall::system(system::Call::remark_with_event { remark });
+	import_block(&mut chain, vec![support::Extrinsic::new_signed(alice.clone(), call, 14, 0)]);
+	let best_block = chain.block(&chain.best_hash()).expect("the best block is imported");
+	assert!(best_block.header.verify_seal(&charlie));
+	let runtime = chain.best_state();
+	let remarked = system::Event::Remarked { sender: alice.clone(), hash };
+	assert_eq!(runtime.events().first(), Some(&RuntimeEvent::from(remarked.clone())));
+	assert_eq!(runtime.system.events(), [remarked]);
+
+	// The second instances of the Balances and Proof of Existence pallets have their own calls,
+	// state and events. Bob pays Charlie in reward tokens, and claims the genesis claim of Alice
+	// in the notary, while fees are still paid in the native token.
+	let notarized = RuntimeCall::notary(proof_of_existence::Call::create_claim {
+		claim: "Genesis claim".to_string(),
+	});
+	let extrinsics = vec![
+		support::Extrinsic::new_signed(
+			bob.clone(),
+			RuntimeCall::rewards(balances::Call::transfer { to: charlie.clone(), amount: 10 }),
+			9,
+			0,
+		),
+		support::Extrinsic::new_signed(bob.clone(), notarized, 10, 0),
+	];
+	assert_eq!(import_block(&mut chain, extrinsics), vec![Ok(()), Ok(())]);
+	let runtime = chain.best_state();
+	let notary_claim = proof_of_existence::Event::ClaimCreated {
+		who: bob.clone(),
+		claim: "Genesis claim".to_string(),
+	};
+	assert_eq!(runtime.events().last(), Some(&RuntimeEvent::from(notary_claim.clone())));
+	assert_eq!(runtime.notary.events(), [notary_claim]);
+
+	// Verify the final state.
+	assert_eq!(runtime.system.block_number(), 15);
+	assert_eq!(runtime.system.nonce(&alice), 15);
+	assert_eq!(runtime.system.nonce(&bob), 11);
+	assert_eq!(runtime.system.root(), Some(&alice));
+	assert_eq!(runtime.system.last_runtime_upgrade(), Some(1));
+	assert_eq!(runtime.balances.on_chain_storage_version(), 1);
+	assert_eq!(runtime.aura.authorities(), &[charlie.clone(), alice.clone(), bob.clone()]);
+	assert_eq!(runtime.balances.balance(&alice), 4);
+	assert_eq!(runtime.balances.balance(&bob), 33);
+	assert_eq!(runtime.balances.allowance(&alice, &charlie), 5);
+	// Events are only kept for the block which emitted them, and the last block has no transfers.
+	assert!(runtime.balances.events().is_empty());
+	assert_eq!(runtime.balances.balance(&charlie), 109);
+	assert_eq!(runtime.balances.balance(&dave), 20);
+	assert_eq!(runtime.balances.locked_balance(&dave), 20);
+	assert_eq!(runtime.balances.balance(&multisig_account), 5);
+	// The fees paid are burned, so the total issuance is the sum of the balances above.
+	assert_eq!(runtime.balances.total_issuance(), 171);
+	assert_eq!(runtime.rewards.balance(&bob), 40);
+	assert_eq!(runtime.rewards.balance(&charlie), 10);
+	assert_eq!(runtime.rewards.total_issuance(), 50);
+	assert_eq!(runtime.proof_of_existence.get_claim(&"Hello, world!".to_string()), Some(&bob));
+	assert_eq!(runtime.proof_of_existence.get_claim(&"Delegated claim".to_string()), Some(&alice));
+	assert_eq!(runtime.proof_of_existence.get_claim(&"Pooled claim".to_string()), Some(&bob));
+	assert_eq!(runtime.proof_of_existence.get_claim(&"Fork A".to_string()), Some(&alice));
+	assert_eq!(runtime.proof_of_existence.get_claim(&"Fork B".to_string()), Some(&bob));
+	assert_eq!(
+		runtime.proof_of_existence.claimed_at(&"Fork B".to_string()),
+		Some(11 * SLOT_DURATION)
+	);
+	assert_eq!(runtime.proof_of_existence.get_claim(&"Genesis claim".to_string()), Some(&alice));
+	assert_eq!(runtime.notary.get_claim(&"Genesis claim".to_string()), Some(&bob));
+	assert_eq!(runtime.notary.claimed_at(&"Genesis claim".to_string()), Some(16 * SLOT_DURATION));
+	// Events are only kept for the block which emitted them.
+	assert!(runtime.proof_of_existence.events().is_empty());
+	assert_eq!(runtime.timestamp.now(), 16 * SLOT_DURATION);
+	assert_eq!(runtime.assets.balance(&1, &bob), 750);
+	assert_eq!(runtime.assets.balance(&1, &charlie), 250);
+	assert_eq!(runtime.assets.total_supply(&1), 1000);
+	assert_eq!(runtime.assets.metadata(&1).map(|metadata| metadata.symbol.as_str()), Some("RWD"));
+}
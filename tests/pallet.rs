@@ -0,0 +1,114 @@
+// A pallet declared outside of this crate, as a downstream crate would declare one.
+use rust_state_machine::{
+	support::{self, Dispatch, GetRuntimeEvents, GetRuntimeMetadata, ReadRuntimeStorage},
+	system,
+};
+
+#[rust_state_machine::macros::pallet(
+	system = rust_state_machine::system,
+	support = rust_state_machine::support,
+)]
+pub mod counter {
+	use rust_state_machine::{
+		support::{DispatchResult, StorageMap, StorageValue},
+		system,
+	};
+
+	/// Configuration trait for the Counter pallet.
+	#[pallet::config]
+	pub trait Config: system::Config {}
+
+	/// The state of the Counter pallet.
+	#[pallet::storage]
+	#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+	pub struct Pallet<T: Config> {
+		/// The count of each account.
+		counts: StorageMap<T::AccountId, u32>,
+		/// The events emitted by this pallet.
+		events: StorageValue<Vec<Event<T>>>,
+	}
+
+	/// The events emitted by the Counter pallet.
+	#[pallet::event]
+	#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+	pub enum Event<T: Config> {
+		/// The count of `who` was incremented to `count`.
+		Incremented { who: T::AccountId, count: u32 },
+	}
+
+	/// The errors returned by the Counter pallet.
+	#[pallet::error]
+	pub enum Error {
+		/// The count would overflow.
+		Overflow,
+	}
+
+	/// The dispatchable functions of the Counter pallet.
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Increment the count of the `caller` by `by`.
+		pub fn increment(&mut self, caller: T::AccountId, by: u32) -> DispatchResult {
+			let count = self.counts.get(&caller).copied().unwrap_or(0);
+			let count = count.checked_add(by).ok_or(Error::Overflow)?;
+			self.counts.insert(caller.clone(), count);
+			self.deposit_event(Event::Incremented { who: caller, count });
+			Ok(())
+		}
+	}
+}
+
+mod types {
+	pub type Extrinsic =
+		rust_state_machine::support::Extrinsic<String, super::RuntimeCall, u32, u128>;
+	pub type Block = rust_state_machine::support::Block<
+		rust_state_machine::support::Header<u32>,
+		rust_state_machine::support::UncheckedExtrinsic<Extrinsic, super::RuntimeCall>,
+	>;
+}
+
+#[rust_state_machine::macros::runtime(
+	system = rust_state_machine::system,
+	support = rust_state_machine::support,
+)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Chain {
+	pub system: system::Pallet<Self>,
+	#[event]
+	pub counter: counter::Pallet<Self>,
+}
+
+impl system::Config for Chain {
+	type AccountId = String;
+	type BlockNumber = u32;
+	type Nonce = u32;
+}
+
+impl counter::Config for Chain {}
+
+impl support::ChargeTransaction<types::Extrinsic> for Chain {}
+
+#[test]
+fn dispatches_calls_of_pallets_declared_outside_the_library() {
+	let mut chain = Chain::new();
+	let alice = "alice".to_string();
+
+	let call = RuntimeCall::counter(counter::Call::increment { by: 3 });
+	assert_eq!(chain.dispatch(alice.clone(), call), Ok(()));
+	let incremented = counter::Event::Incremented { who: alice.clone(), count: 3 };
+	assert_eq!(chain.events(), [RuntimeEvent::counter(incremented)]);
+	assert_eq!(chain.read_storage_entry("counter", "counts", "\"alice\""), Some("3".to_string()));
+
+	// The errors of the pallet are given the index of the pallet in the runtime.
+	let call = RuntimeCall::counter(counter::Call::increment { by: u32::MAX });
+	let overflow = support::DispatchError::Module {
+		error: support::ModuleError { index: 1, error: 0 },
+		message: "Overflow",
+	};
+	assert_eq!(chain.dispatch(alice, call), Err(overflow));
+
+	let metadata = Chain::metadata();
+	let counter = &metadata.pallets[1];
+	assert_eq!(counter.name, "counter");
+	assert_eq!(counter.errors[0].name, "Overflow");
+	assert_eq!(counter.events[0].name, "Incremented");
+}
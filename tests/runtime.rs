@@ -0,0 +1,71 @@
+// A runtime built outside of this crate from its pallets, as a downstream crate would build one.
+use rust_state_machine::{
+	balances,
+	block_builder::{BlockBuilder, BlockLimits, Inclusion},
+	support::{self, Dispatch, GetRuntimeEvents, GetRuntimeMetadata, InherentData},
+	system,
+};
+
+mod types {
+	pub type Extrinsic =
+		rust_state_machine::support::Extrinsic<String, super::RuntimeCall, u32, u128>;
+	pub type Block = rust_state_machine::support::Block<
+		rust_state_machine::support::Header<u32>,
+		rust_state_machine::support::UncheckedExtrinsic<Extrinsic, super::RuntimeCall>,
+	>;
+}
+
+#[rust_state_machine::macros::runtime(
+	system = rust_state_machine::system,
+	support = rust_state_machine::support,
+)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Chain {
+	pub system: system::Pallet<Self>,
+	#[event]
+	pub balances: balances::Pallet<Self>,
+}
+
+impl system::Config for Chain {
+	type AccountId = String;
+	type BlockNumber = u32;
+	type Nonce = u32;
+}
+
+impl balances::Config for Chain {
+	type Balance = u128;
+}
+
+impl support::ChargeTransaction<types::Extrinsic> for Chain {}
+
+#[test]
+fn builds_and_executes_blocks() {
+	let mut chain = Chain::new();
+	let (alice, bob) = ("alice".to_string(), "bob".to_string());
+	chain.balances.set_balance(&alice, 100);
+
+	let limits = BlockLimits { max_weight: u64::MAX, max_size: usize::MAX };
+	let call = RuntimeCall::balances(balances::Call::transfer { to: bob.clone(), amount: 30 });
+	let extrinsic = support::Extrinsic::new_signed(alice.clone(), call, 0, 0);
+	let mut author = chain.clone();
+	let mut builder = BlockBuilder::new(&mut author, 0, &InherentData::new(), limits).unwrap();
+	assert_eq!(builder.push(extrinsic), Inclusion::Included(Ok(())));
+	let block = builder.build();
+	assert_eq!(chain.execute_block(block), Ok(()));
+	assert_eq!(chain, author);
+
+	assert_eq!(chain.system.block_number(), 1);
+	assert_eq!(chain.balances.balance(&bob), 30);
+	let transfer = balances::Event::Transfer { from: alice.clone(), to: bob.clone(), amount: 30 };
+	assert_eq!(chain.events(), [RuntimeEvent::balances(transfer)]);
+
+	// Calls can also be dispatched directly, such as in the tests of a pallet.
+	let call = RuntimeCall::balances(balances::Call::transfer { to: alice, amount: 31 });
+	let insufficient = support::DispatchError::Module {
+		error: support::ModuleError { index: 1, error: 0 },
+		message: "InsufficientBalance",
+	};
+	assert_eq!(chain.dispatch(bob, call), Err(insufficient));
+	let names = Chain::metadata().pallets.iter().map(|pallet| pallet.name).collect::<Vec<_>>();
+	assert_eq!(names, ["system", "balances"]);
+}